@@ -0,0 +1,451 @@
+use crate::{export, io, project};
+use crate::export::templates::{parse_template_file, TemplateEnv, TemplateLibrary};
+use crate::export::frame::FrameTransform;
+use crate::export::render::RenderSettings;
+use crate::export::report::ReportSettings;
+use crate::export::ExportFormat;
+use crate::models::ExportTemplate;
+use std::collections::BTreeMap;
+use crate::models::validation::{validate_project_options, Severity};
+use crate::project::route::RouteOptions;
+
+/// Handle headless subcommands that run without opening a window.
+/// Returns the process exit code, or `None` when the GUI should start instead.
+pub fn run_headless(args: &[String]) -> Option<i32> {
+    let command = args.get(1)?;
+    let rest = &args[2..];
+    match command.as_str() {
+        "merge-driver" => Some(merge_driver(rest)),
+        "diff" => Some(diff(rest)),
+        "export" => Some(export(rest)),
+        _ => None,
+    }
+}
+
+/// git merge driver: `waypoint-tool merge-driver %O %A %B`.
+/// The merged project is written over %A; exit code 1 tells git there are conflicts.
+fn merge_driver(args: &[String]) -> i32 {
+    let [base, ours, theirs, ..] = args else {
+        eprintln!("usage: waypoint-tool merge-driver <base> <ours> <theirs>");
+        return 2;
+    };
+
+    match project::merge::merge_project_files(base, ours, theirs, ours) {
+        Ok(result) => {
+            for c in &result.conflicts {
+                eprintln!(
+                    "CONFLICT {} {}: kept ours",
+                    c.node_id.as_deref().unwrap_or("<project>"),
+                    c.field
+                );
+            }
+            if result.is_clean() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("waypoint-tool merge failed: {}", e);
+            2
+        }
+    }
+}
+
+/// `waypoint-tool diff <before> <after> [--json]`.
+/// Exit codes follow diff(1): 0 = identical, 1 = differences, 2 = error.
+fn diff(args: &[String]) -> i32 {
+    let json = args.iter().any(|a| a == "--json");
+    let paths: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    let [before, after] = paths[..] else {
+        eprintln!("usage: waypoint-tool diff <before> <after> [--json]");
+        return 2;
+    };
+
+    match project::diff::diff_project_files(before, after) {
+        Ok(diff) => {
+            if json {
+                match serde_json::to_string_pretty(&diff) {
+                    Ok(out) => println!("{}", out),
+                    Err(e) => {
+                        eprintln!("Serialization error: {}", e);
+                        return 2;
+                    }
+                }
+            } else {
+                print!("{}", diff.summary);
+            }
+            if diff.is_empty() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("waypoint-tool diff failed: {}", e);
+            2
+        }
+    }
+}
+
+/// Value following `--name`, if present.
+fn flag_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1))
+}
+
+/// Positional arguments, skipping `--flag value` pairs for the given value flags.
+fn positionals<'a>(args: &'a [String], value_flags: &[&str]) -> Vec<&'a String> {
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            out.push(arg);
+        }
+    }
+    out
+}
+
+const EXPORT_USAGE: &str = "usage: waypoint-tool export <project> <output> [--template <file|id> [--library <dir>] [--helpers <file>] | --format <name> | --profile <id> [--library <dir>] | --image [--render <file>] | --report [--settings <file>]] [--transform <file>] [--frame-id <id>] [--index-start <n>]";
+
+/// Flags followed by a value.
+const EXPORT_VALUE_FLAGS: &[&str] = &["--template", "--library", "--helpers", "--index-start", "--format", "--frame-id", "--profile", "--transform", "--render", "--settings"];
+
+/// A template file (with optional front matter), or a library template by id.
+fn load_template(name: &str, library: Option<&TemplateLibrary>) -> Result<ExportTemplate, String> {
+    let path = std::path::Path::new(name);
+    if path.is_file() {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("File read error: {}", e))?;
+        return parse_template_file(&content, name);
+    }
+    match library {
+        Some(library) => library.get(name),
+        None => Err(format!("File not found: {}", name)),
+    }
+}
+
+/// The YAML file named by `flag`, if given. `what` names it in errors.
+fn yaml_flag<T: serde::de::DeserializeOwned>(args: &[String], flag: &str, what: &str) -> Result<Option<T>, String> {
+    flag_value(args, flag)
+        .map(|path| {
+            let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", what, e))?;
+            serde_yaml::from_str(&content).map_err(|e| format!("Invalid {} file: {}", what, e))
+        })
+        .transpose()
+}
+
+/// What `export` writes.
+enum ExportMode {
+    /// A template file or library id; the plain waypoint list without one
+    Template(Option<ExportTemplate>),
+    Builtin(ExportFormat),
+    Profile(String),
+    Image(RenderSettings),
+    Report(ReportSettings),
+}
+
+/// Parsed `export` arguments.
+struct ExportArgs {
+    project_path: String,
+    output: String,
+    mode: ExportMode,
+    route_options: RouteOptions,
+    library: Option<TemplateLibrary>,
+    helpers: BTreeMap<String, String>,
+    transform: FrameTransform,
+}
+
+impl ExportArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let [project_path, output] = positionals(args, EXPORT_VALUE_FLAGS)[..] else {
+            return Err(EXPORT_USAGE.to_string());
+        };
+        let has = |flag: &str| args.iter().any(|a| a == flag);
+        // `--render` alone implies `--image`
+        let image = has("--image") || has("--render");
+        let report = has("--report");
+        let modes = [has("--template"), has("--format"), has("--profile"), image, report];
+        if modes.iter().filter(|&&set| set).count() > 1 || (has("--settings") && !report) {
+            return Err(EXPORT_USAGE.to_string());
+        }
+        if (image || report) && (has("--transform") || has("--frame-id")) {
+            return Err("--transform and --frame-id do not apply to --image or --report: they are drawn in the map frame".to_string());
+        }
+
+        let index_start = flag_value(args, "--index-start")
+            .map(|v| v.parse::<usize>().map_err(|e| format!("Invalid --index-start: {}", e)))
+            .transpose()?
+            .unwrap_or(0);
+        let library = flag_value(args, "--library").map(TemplateLibrary::new);
+        let mut transform: FrameTransform = yaml_flag(args, "--transform", "transform")?.unwrap_or_default();
+        if let Some(frame_id) = flag_value(args, "--frame-id") {
+            transform.frame_id = Some(frame_id.clone());
+        }
+
+        let mode = if image {
+            ExportMode::Image(yaml_flag(args, "--render", "render settings")?.unwrap_or_default())
+        } else if report {
+            ExportMode::Report(yaml_flag(args, "--settings", "report settings")?.unwrap_or_default())
+        } else if let Some(name) = flag_value(args, "--format") {
+            let format = serde_json::from_value(serde_json::json!({ "format": name }))
+                .map_err(|e| format!("Invalid --format: {}", e))?;
+            ExportMode::Builtin(format)
+        } else if let Some(id) = flag_value(args, "--profile") {
+            ExportMode::Profile(id.clone())
+        } else {
+            let template = flag_value(args, "--template")
+                .map(|name| load_template(name, library.as_ref()).map_err(|e| format!("Failed to read template: {}", e)))
+                .transpose()?;
+            ExportMode::Template(template)
+        };
+
+        Ok(Self {
+            project_path: project_path.clone(),
+            output: output.clone(),
+            mode,
+            route_options: RouteOptions { index_start },
+            helpers: yaml_flag(args, "--helpers", "helpers")?.unwrap_or_default(),
+            library,
+            transform,
+        })
+    }
+}
+
+/// `waypoint-tool export <project> <output> [--template <file|id> [--library <dir>] [--helpers <file>] | --format <name> | --profile <id> [--library <dir>] | --image [--render <file>] | --report [--settings <file>]] [--transform <file>] [--frame-id <id>] [--index-start <n>]`.
+/// `--format` takes a built-in format such as `nav2_follow_waypoints`;
+/// `--profile` runs a project export profile with `<output>` as the base directory;
+/// `--image` writes a PNG of the route drawn over the map, with `--render` a YAML `RenderSettings` file;
+/// `--report` writes the mission report (SVG or PDF by extension), with `--settings` a YAML `ReportSettings` file;
+/// `--transform` is a YAML `FrameTransform` into the target frame, whose `frame_id` `--frame-id` overrides
+/// (neither applies to images and reports, which stay in the map frame);
+/// `--library` is a template library directory providing partials and templates by id;
+/// `--helpers` is a YAML map of user template helpers (name -> Handlebars snippet).
+/// Option values are validated first; exit code 1 means validation errors, 2 other failures.
+fn export(args: &[String]) -> i32 {
+    let ExportArgs { project_path, output, mode, route_options, library, helpers, transform } = match ExportArgs::parse(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let data = match io::load_project(&project_path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("waypoint-tool export failed: {}", e);
+            return 2;
+        }
+    };
+    // Exports read the saved file; say so when the GUI holds newer unsaved work
+    if let Some(recovery) = io::recovery::default_recovery_dir().and_then(|dir| io::recovery::find_recovery(&dir, Some(&project_path))) {
+        eprintln!("warning: {} has a newer autosave that is not exported: {}", project_path, recovery.autosave_path);
+    }
+    // stderr only: `--profile` prints the written paths on stdout
+    for issue in &project::integrity::validate_project(&data).issues {
+        eprintln!("warning: structure: {}", issue);
+    }
+    let report = validate_project_options(&data);
+    for d in &report.diagnostics {
+        let level = if d.severity == Severity::Error { "error" } else { "warning" };
+        eprintln!("{}: {} / {}: {}", level, d.node_id, d.option, d.issue);
+    }
+    if report.has_errors() {
+        return 1;
+    }
+
+    let env = match library.as_ref().map(|l| l.env(helpers.clone())) {
+        None => TemplateEnv { helpers, ..Default::default() },
+        Some(Ok(env)) => env,
+        Some(Err(e)) => {
+            eprintln!("Failed to read template library: {}", e);
+            return 2;
+        }
+    };
+    // Templates see the project file's name as `project.name`
+    let project_name = std::path::Path::new(&project_path).file_stem().map(|s| s.to_string_lossy().to_string());
+    let result = match mode {
+        ExportMode::Image(settings) => export::export_map_image(&output, &data, &route_options, &settings),
+        ExportMode::Report(settings) => export::export_report(&output, &data, &route_options, &settings, project_name.as_deref()),
+        ExportMode::Builtin(format) => export::export_builtin(&output, &data, &format, &route_options, &transform),
+        ExportMode::Profile(id) => {
+            let name = project_name.unwrap_or_default();
+            export::profile::run_profile(&data, &id, std::path::Path::new(&output), library.as_ref(), &env, &route_options, &name)
+                .map(|written| written.iter().for_each(|p| println!("{}", p.display())))
+        }
+        ExportMode::Template(template) => {
+            let env = match &template {
+                Some(template) => env.for_template(template),
+                None => env,
+            };
+            let request = export::ExportRequest { route_options, env, project_name, transform };
+            io::export_project(&output, &data, template.map(|t| t.content), None, &request)
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("waypoint-tool export failed: {}", e);
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_arguments_start_the_gui() {
+        assert_eq!(run_headless(&["waypoint-tool".to_string()]), None);
+        assert_eq!(run_headless(&["waypoint-tool".to_string(), "--foo".to_string()]), None);
+    }
+
+    #[test]
+    fn test_merge_driver_requires_three_paths() {
+        let args: Vec<String> = ["waypoint-tool", "merge-driver", "base"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(2));
+    }
+
+    #[test]
+    fn test_diff_requires_two_paths() {
+        let args: Vec<String> = ["waypoint-tool", "diff", "a", "--json"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(2));
+    }
+
+    #[test]
+    fn test_merge_driver_writes_result_over_ours() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, x: f64| {
+            let path = dir.path().join(name);
+            let json = format!(r#"{{"root_node_ids":["a"],"nodes":{{"a":{{"id":"a","type":"manual","transform":{{"x":{},"y":0.0}}}}}}}}"#, x);
+            std::fs::write(&path, json).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let (base, ours, theirs) = (write("base", 0.0), write("ours", 0.0), write("theirs", 4.0));
+
+        let args = vec!["waypoint-tool".to_string(), "merge-driver".to_string(), base, ours.clone(), theirs];
+        assert_eq!(run_headless(&args), Some(0));
+        let merged = io::load_project(&ours).unwrap();
+        assert_eq!(merged.nodes["a"].transform.as_ref().unwrap().x, 4.0);
+    }
+
+    #[test]
+    fn test_export_writes_flattened_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["g"],"nodes":{
+            "g":{"id":"g","type":"generator","children_ids":["a","b"]},
+            "a":{"id":"a","type":"manual","transform":{"x":1.0,"y":0.0}},
+            "b":{"id":"b","type":"manual","transform":{"x":2.0,"y":0.0}}}}"#).unwrap();
+        let output = dir.path().join("route.json");
+
+        let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--index-start", "1"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(0));
+        let exported: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[1]["id"], "b");
+        assert_eq!(exported[1]["index"], 2);
+    }
+
+    #[test]
+    fn test_export_with_builtin_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["a"],"nodes":{"a":{"id":"a","type":"manual","transform":{"x":1.0,"y":0.0}}}}"#).unwrap();
+        let output = dir.path().join("goal.yaml");
+
+        let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--format", "nav2_follow_waypoints", "--frame-id", "site"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(0));
+        let goal: serde_json::Value = serde_yaml::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(goal["poses"][0]["header"]["frame_id"], "site");
+
+        let mut args = args;
+        args[5] = "nav3".to_string();
+        assert_eq!(run_headless(&args), Some(2));
+    }
+
+    #[test]
+    fn test_export_with_library_template_and_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["a"],"nodes":{"a":{"id":"a","type":"manual","transform":{"x":1.0,"y":0.0}}}}"#).unwrap();
+        let library_dir = dir.path().join("templates");
+        let library = TemplateLibrary::new(&library_dir);
+        library.save_partial("header", "# route\n").unwrap();
+        library.save(&ExportTemplate {
+            id: "plc".to_string(),
+            name: "PLC".to_string(),
+            extension: "txt".to_string(),
+            suffix: String::new(),
+            content: "{{> header}}{{#each waypoints}}{{id}}{{/each}}".to_string(),
+            escape_html: true,
+        }).unwrap();
+        let output = dir.path().join("route.txt");
+
+        let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--template", "plc", "--library", library_dir.to_str().unwrap()]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(0));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "# route\na");
+    }
+
+    #[test]
+    fn test_export_profile_writes_into_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["a"],"nodes":{"a":{"id":"a","type":"manual","transform":{"x":1.0,"y":0.0}}},
+            "export_profiles":[{"id":"deploy","name":"deploy","targets":[{"source":"data","path":"{project}.json"},{"source":{"builtin":{"format":"nav2_waypoints"}},"path":"nav2/{project}.yaml"}]}]}"#).unwrap();
+        let out = dir.path().join("out");
+
+        let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), out.to_str().unwrap(), "--profile", "deploy"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(0));
+        assert!(out.join("site.json").is_file());
+        assert!(out.join("nav2/site.yaml").is_file());
+    }
+
+    #[test]
+    fn test_export_map_image_with_render_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["a","b"],"nodes":{
+            "a":{"id":"a","type":"manual","transform":{"x":0.0,"y":0.0}},
+            "b":{"id":"b","type":"manual","transform":{"x":3.0,"y":0.0}}}}"#).unwrap();
+        let settings = dir.path().join("render.yaml");
+        std::fs::write(&settings, "scale: 10\nmargin: 0.5\nlabels: index\n").unwrap();
+        let output = dir.path().join("site.png");
+
+        let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--render", settings.to_str().unwrap()]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&args), Some(0));
+        assert_eq!(image::image_dimensions(&output).unwrap(), (40, 10));
+
+        let both: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--image", "--format", "csv"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(run_headless(&both), Some(2));
+    }
+
+    #[test]
+    fn test_export_report_pdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("site.wptroj");
+        std::fs::write(&project, r#"{"root_node_ids":["a","b"],"nodes":{
+            "a":{"id":"a","type":"manual","transform":{"x":0.0,"y":0.0}},
+            "b":{"id":"b","type":"manual","transform":{"x":3.0,"y":4.0}}}}"#).unwrap();
+        let output = dir.path().join("site.pdf");
+
+        let settings = dir.path().join("report.yaml");
+        std::fs::write(&settings, "title: Sign-off\nspeed: 0.25\n").unwrap();
+        let cli = |extra: &[&str]| {
+            let args: Vec<String> = ["waypoint-tool", "export", project.to_str().unwrap(), output.to_str().unwrap(), "--report"]
+                .iter().chain(extra).map(|s| s.to_string()).collect();
+            run_headless(&args)
+        };
+
+        assert_eq!(cli(&["--settings", settings.to_str().unwrap()]), Some(0));
+        let pdf = String::from_utf8_lossy(&std::fs::read(&output).unwrap()).to_string();
+        assert!(pdf.contains("(Sign-off)"));
+        assert!(pdf.contains("(Project: site)"));
+        assert!(pdf.contains("Total distance: 5.00 m    Estimated time: 0:20"));
+
+        // The report is drawn in the map frame
+        assert_eq!(cli(&["--frame-id", "site"]), Some(2));
+        // `--render` is for images
+        assert_eq!(cli(&["--render", settings.to_str().unwrap()]), Some(2));
+    }
+}
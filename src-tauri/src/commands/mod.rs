@@ -1,5 +1,9 @@
-use tauri::{command, AppHandle};
-use crate::{map, io, models::ProjectData};
+use tauri::{command, AppHandle, Manager};
+use crate::{map, io, project, models::ProjectData};
+use crate::export::frame::FrameTransform;
+use crate::export::templates::{TemplateEnv, TemplateLibrary};
+use crate::models::ExportTemplate;
+use std::collections::BTreeMap;
 
 #[command]
 pub fn load_ros_map(yaml_path: String) -> Result<map::MapLoadResult, String> {
@@ -7,13 +11,216 @@ pub fn load_ros_map(yaml_path: String) -> Result<map::MapLoadResult, String> {
 }
 
 #[command]
-pub fn save_project(path: String, data: ProjectData) -> Result<(), String> {
-    io::save_project(&path, &data)
+pub fn generate_sim_world(yaml_path: String, output_path: String, options: map::world::WorldOptions, markers: Option<Vec<map::world::WorldMarker>>) -> Result<(), String> {
+    map::world::generate_world(&yaml_path, &output_path, &options, &markers.unwrap_or_default())
+}
+
+fn recovery_dir(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not find app_data_dir: {}", e))?;
+    Ok(app_data_dir.join("recovery"))
+}
+
+/// `first_save` is set when the project had no file yet, so its untitled autosave is superseded too.
+#[command]
+pub fn save_project(app: AppHandle, path: String, data: ProjectData, options: Option<io::SaveOptions>, first_save: Option<bool>) -> Result<(), String> {
+    io::save_project_with_options(&path, &data, &options.unwrap_or_default())?;
+    // The saved file supersedes any autosave taken while it was being edited
+    let dir = recovery_dir(&app)?;
+    io::recovery::discard_recovery(&dir, Some(&path))?;
+    if first_save.unwrap_or(false) {
+        io::recovery::discard_recovery(&dir, None)?;
+    }
+    Ok(())
+}
+
+#[command]
+pub fn save_project_archive(path: String, data: ProjectData, options: Option<io::archive::ArchiveSaveOptions>) -> Result<(), String> {
+    io::archive::save_project_archive(&path, &data, &options.unwrap_or_default())
+}
+
+/// Pinned plugins are extracted only with `extract_plugins`, after the user agreed to install their code.
+#[command]
+pub fn load_project_archive(app: AppHandle, path: String, extract_plugins: Option<bool>) -> Result<io::archive::LoadedArchive, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not find app_data_dir: {}", e))?;
+    let stem = std::path::Path::new(&path).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extract_dir = app_data_dir.join("pinned_plugins").join(stem);
+    io::archive::load_project_archive(&path, extract_plugins.unwrap_or(false).then_some(extract_dir.as_path()))
+}
+
+#[command]
+pub fn autosave_project(app: AppHandle, project_path: Option<String>, data: ProjectData) -> Result<String, String> {
+    io::recovery::autosave_project(&recovery_dir(&app)?, project_path.as_deref(), &data)
+}
+
+#[command]
+pub fn find_project_recovery(app: AppHandle, project_path: Option<String>) -> Result<Option<io::recovery::RecoveryInfo>, String> {
+    Ok(io::recovery::find_recovery(&recovery_dir(&app)?, project_path.as_deref()))
+}
+
+#[command]
+pub fn discard_project_recovery(app: AppHandle, project_path: Option<String>) -> Result<(), String> {
+    io::recovery::discard_recovery(&recovery_dir(&app)?, project_path.as_deref())
+}
+
+#[command]
+pub fn load_project(app: AppHandle, path: String) -> Result<io::LoadedProject, String> {
+    io::load_project_with_recovery(&path, &recovery_dir(&app)?)
+}
+
+#[command]
+pub fn validate_project(data: ProjectData) -> project::integrity::IntegrityReport {
+    project::integrity::validate_project(&data)
+}
+
+#[derive(serde::Serialize)]
+pub struct RepairProjectResponse {
+    pub data: ProjectData,
+    pub result: project::integrity::RepairResult,
+}
+
+#[command]
+pub fn repair_project(mut data: ProjectData) -> RepairProjectResponse {
+    let result = project::integrity::repair_project(&mut data);
+    RepairProjectResponse { data, result }
+}
+
+#[command]
+pub fn merge_projects(base_path: String, ours_path: String, theirs_path: String, output_path: String) -> Result<project::merge::MergeResult, String> {
+    project::merge::merge_project_files(&base_path, &ours_path, &theirs_path, &output_path)
+}
+
+#[command]
+pub fn diff_projects(before_path: String, after_path: String) -> Result<project::diff::ProjectDiff, String> {
+    project::diff::diff_project_files(&before_path, &after_path)
+}
+
+#[command]
+pub fn resolve_node_options(data: ProjectData, node_id: String) -> Result<std::collections::BTreeMap<String, project::inheritance::ResolvedOption>, String> {
+    project::inheritance::resolve_node_options(&data, &node_id)
+}
+
+#[command]
+pub fn build_route(data: ProjectData, options: Option<project::route::RouteOptions>) -> Vec<project::route::RouteWaypoint> {
+    project::route::build_route(&data, &options.unwrap_or_default())
+}
+
+fn template_library(app: &AppHandle) -> Result<TemplateLibrary, String> {
+    let app_config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Could not find app_config_dir: {}", e))?;
+    Ok(TemplateLibrary::new(app_config_dir.join("templates")))
+}
+
+#[command]
+pub fn export_project(app: AppHandle, path: String, data: ProjectData, template: Option<String>, image_data_b64: Option<String>, request: Option<crate::export::ExportRequest>) -> Result<(), String> {
+    let mut request = request.unwrap_or_default();
+    let escape_html = request.env.escape_html;
+    request.env = TemplateEnv { escape_html, ..template_library(&app)?.env(request.env.helpers)? };
+    io::export_project(&path, &data, template, image_data_b64, &request)
 }
 
 #[command]
-pub fn load_project(path: String) -> Result<ProjectData, String> {
-    io::load_project(&path)
+pub fn export_builtin(path: String, data: ProjectData, format: crate::export::ExportFormat, options: Option<project::route::RouteOptions>, transform: Option<FrameTransform>) -> Result<(), String> {
+    crate::export::export_builtin(&path, &data, &format, &options.unwrap_or_default(), &transform.unwrap_or_default())
+}
+
+/// Draw the route over the map layers on the backend and write it as a PNG.
+#[command]
+pub fn render_map_image(path: String, data: ProjectData, settings: Option<crate::export::render::RenderSettings>, options: Option<project::route::RouteOptions>) -> Result<(), String> {
+    crate::export::export_map_image(&path, &data, &options.unwrap_or_default(), &settings.unwrap_or_default())
+}
+
+/// Write the mission report as SVG or PDF, chosen by the extension of `path`.
+#[command]
+pub fn export_report(
+    path: String,
+    data: ProjectData,
+    settings: Option<crate::export::report::ReportSettings>,
+    options: Option<project::route::RouteOptions>,
+    project_name: Option<String>,
+) -> Result<(), String> {
+    crate::export::export_report(&path, &data, &options.unwrap_or_default(), &settings.unwrap_or_default(), project_name.as_deref())
+}
+
+/// Run every target of a project export profile. Returns the written paths.
+#[command]
+pub fn run_export_profile(
+    app: AppHandle,
+    data: ProjectData,
+    profile_id: String,
+    base_dir: String,
+    options: Option<project::route::RouteOptions>,
+    helpers: Option<BTreeMap<String, String>>,
+    project_name: Option<String>,
+) -> Result<Vec<String>, String> {
+    let library = template_library(&app)?;
+    let env = library.env(helpers.unwrap_or_default())?;
+    let base = std::path::Path::new(&base_dir);
+    let name = project_name.unwrap_or_else(|| base.file_name().unwrap_or_default().to_string_lossy().to_string());
+    let written = crate::export::profile::run_profile(&data, &profile_id, base, Some(&library), &env, &options.unwrap_or_default(), &name)?;
+    Ok(written.iter().map(|p| p.to_string_lossy().to_string()).collect())
+}
+
+#[command]
+pub fn preview_template(
+    app: AppHandle,
+    template: ExportTemplate,
+    data: Option<ProjectData>,
+    options: Option<project::route::RouteOptions>,
+    helpers: Option<BTreeMap<String, String>>,
+    strict: Option<bool>,
+    project_name: Option<String>,
+) -> Result<crate::export::preview::TemplatePreview, String> {
+    let env = template_library(&app)?.env(helpers.unwrap_or_default())?.for_template(&template);
+    let meta = crate::export::context::ExportMeta::new(project_name.as_deref(), "", data.as_ref());
+    crate::export::preview::preview_project_template(&template.content, data.as_ref(), &options.unwrap_or_default(), &env, &meta, strict.unwrap_or(true))
+}
+
+#[command]
+pub fn list_export_templates(app: AppHandle) -> Result<crate::export::templates::TemplateListing, String> {
+    template_library(&app)?.list()
+}
+
+#[command]
+pub fn save_export_template(app: AppHandle, template: ExportTemplate) -> Result<(), String> {
+    template_library(&app)?.save(&template)
+}
+
+#[command]
+pub fn delete_export_template(app: AppHandle, id: String) -> Result<(), String> {
+    template_library(&app)?.delete(&id)
+}
+
+#[command]
+pub fn import_export_template(app: AppHandle, path: String) -> Result<ExportTemplate, String> {
+    template_library(&app)?.import(std::path::Path::new(&path))
+}
+
+#[command]
+pub fn export_export_template(app: AppHandle, id: String, path: String) -> Result<(), String> {
+    template_library(&app)?.export(&id, std::path::Path::new(&path))
+}
+
+#[command]
+pub fn list_template_partials(app: AppHandle) -> Result<BTreeMap<String, String>, String> {
+    template_library(&app)?.partials()
+}
+
+#[command]
+pub fn save_template_partial(app: AppHandle, name: String, content: String) -> Result<(), String> {
+    template_library(&app)?.save_partial(&name, &content)
+}
+
+#[command]
+pub fn delete_template_partial(app: AppHandle, name: String) -> Result<(), String> {
+    template_library(&app)?.delete_partial(&name)
 }
 
 #[command]
@@ -22,8 +229,32 @@ pub fn load_options_schema(yaml_path: String) -> Result<crate::models::options::
 }
 
 #[command]
-pub fn export_waypoints(path: String, waypoints: Vec<serde_json::Value>, template: Option<String>, image_data_b64: Option<String>) -> Result<(), String> {
-    io::export_waypoints(&path, waypoints, template, image_data_b64)
+pub fn save_options_schema(yaml_path: String, schema: crate::models::options::OptionsSchema) -> Result<(), String> {
+    crate::models::options::save_options_schema(&yaml_path, &schema)
+}
+
+#[command]
+pub fn validate_options(data: ProjectData) -> crate::models::validation::OptionReport {
+    crate::models::validation::validate_project_options(&data)
+}
+
+#[derive(serde::Serialize)]
+pub struct SchemaMigrationResponse {
+    pub data: ProjectData,
+    pub reports: Vec<crate::models::schema_evolution::SchemaChangeReport>,
+}
+
+#[command]
+pub fn migrate_options_schema(mut data: ProjectData, operations: Vec<crate::models::schema_evolution::SchemaOperation>) -> Result<SchemaMigrationResponse, String> {
+    let reports = crate::models::schema_evolution::apply_schema_operations(&mut data, &operations)?;
+    Ok(SchemaMigrationResponse { data, reports })
+}
+
+#[command]
+pub fn export_waypoints(app: AppHandle, path: String, waypoints: Vec<serde_json::Value>, template: Option<String>, image_data_b64: Option<String>, helpers: Option<BTreeMap<String, String>>, project_name: Option<String>) -> Result<(), String> {
+    let env = template_library(&app)?.env(helpers.unwrap_or_default())?;
+    let meta = crate::export::context::ExportMeta::new(project_name.as_deref(), &path, None);
+    io::export_waypoints(&path, waypoints, template, image_data_b64, &env, &meta)
 }
 
 pub mod plugins;
@@ -37,10 +268,39 @@ pub fn force_exit(app: AppHandle) {
 pub fn get_handlers() -> impl Fn(tauri::ipc::Invoke) -> bool {
     tauri::generate_handler![
         load_ros_map,
+        generate_sim_world,
         save_project,
         load_project,
+        save_project_archive,
+        load_project_archive,
+        autosave_project,
+        find_project_recovery,
+        discard_project_recovery,
+        validate_project,
+        repair_project,
+        merge_projects,
+        diff_projects,
         export_waypoints,
+        build_route,
+        resolve_node_options,
+        export_project,
+        export_builtin,
+        render_map_image,
+        export_report,
+        run_export_profile,
+        preview_template,
+        list_export_templates,
+        save_export_template,
+        delete_export_template,
+        import_export_template,
+        export_export_template,
+        list_template_partials,
+        save_template_partial,
+        delete_template_partial,
         load_options_schema,
+        save_options_schema,
+        validate_options,
+        migrate_options_schema,
         force_exit,
         plugins::fetch_installed_plugins,
         plugins::run_plugin,
@@ -151,7 +151,10 @@ pub fn run_plugin(plugin_instance: PluginInstance, context_json: String, python_
     // セキュリティと拡張性、言語非依存性を重視し、「標準入出力ストリームを介したJSON通信」を採用しています。
     // プロセス間通信（IPC）にstdin/stdoutを用いることで、複雑なRPCライブラリを介さずとも
     // 開発者が使い慣れた言語で柔軟に拡張機能を作成できるよう設計されています。
-    
+
+    // Fail fast on parameters the manifest does not allow, before anything is spawned
+    plugin_instance.manifest.validate_params(&context_json)?;
+
     if plugin_instance.manifest.plugin_type == "python" {
         use std::process::{Command, Stdio};
         use std::io::Write;
@@ -0,0 +1,191 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use crate::models::ProjectData;
+
+/// Map metadata exposed to templates as `map`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MapMeta {
+    /// Layer name as shown in the layer panel
+    pub name: String,
+    /// `image` entry of the map YAML
+    pub image: String,
+    /// Meters per pixel
+    pub resolution: f64,
+    /// `[x, y, yaw]` of the lower-left pixel
+    pub origin: [f64; 3],
+}
+
+impl MapMeta {
+    /// The lowest visible layer with map info, or the lowest layer when none is visible.
+    pub fn from_project(project: &ProjectData) -> Option<Self> {
+        let mut layers: Vec<_> = project.map_layers.iter().flatten().filter(|l| l.info.is_some()).collect();
+        layers.sort_by_key(|l| (!l.visible, l.z_index));
+        let layer = layers.first()?;
+        let info = layer.info.as_ref()?;
+        let origin = info.get("origin").and_then(Value::as_array).map(|o| {
+            let at = |i: usize| o.get(i).and_then(Value::as_f64).unwrap_or(0.0);
+            [at(0), at(1), at(2)]
+        });
+        Some(Self {
+            name: layer.name.clone(),
+            image: info.get("image").and_then(Value::as_str).unwrap_or_default().to_string(),
+            resolution: info.get("resolution").and_then(Value::as_f64)?,
+            origin: origin.unwrap_or_default(),
+        })
+    }
+}
+
+/// Everything besides the waypoints that a template can reference.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportMeta {
+    pub project_name: String,
+    /// RFC 3339 UTC timestamp of the export
+    pub exported_at: String,
+    pub map: Option<MapMeta>,
+    /// Target frame set by the export's `FrameTransform`; `{{frame_id}}` is "map" when unset
+    pub frame_id: Option<String>,
+}
+
+impl ExportMeta {
+    /// `name` falls back to the output file name without its extension.
+    pub fn new(name: Option<&str>, output_path: &str, project: Option<&ProjectData>) -> Self {
+        let stem = Path::new(output_path).file_stem().map(|s| s.to_string_lossy().to_string());
+        Self {
+            project_name: name
+                .filter(|n| !n.is_empty())
+                .map(str::to_string)
+                .or(stem)
+                .unwrap_or_else(|| "untitled".to_string()),
+            exported_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            map: project.and_then(MapMeta::from_project),
+            frame_id: None,
+        }
+    }
+}
+
+fn coord(wp: &Value, key: &str) -> f64 {
+    wp.get(key).and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+fn distance(a: &Value, b: &Value) -> f64 {
+    let d = |k| coord(b, k) - coord(a, k);
+    (d("x").powi(2) + d("y").powi(2) + d("z").powi(2)).sqrt()
+}
+
+fn heading(from: &Value, to: &Value) -> f64 {
+    (coord(to, "y") - coord(from, "y")).atan2(coord(to, "x") - coord(from, "x"))
+}
+
+/// Add the derived fields templates rely on to each waypoint object:
+/// `index`, `yaw`, `yaw_deg`, `distance_from_prev`, `cumulative_distance`,
+/// `heading` (direction of the leg leaving the waypoint; the last one keeps the
+/// arriving leg's, a lone waypoint its yaw) and `parent_id` (null outside generators).
+pub fn enrich_waypoints(waypoints: &[Value]) -> Vec<Value> {
+    let mut cumulative = 0.0;
+    waypoints
+        .iter()
+        .enumerate()
+        .map(|(i, wp)| {
+            let Value::Object(fields) = wp else { return wp.clone() };
+            let prev = i.checked_sub(1).and_then(|p| waypoints.get(p));
+            let next = waypoints.get(i + 1);
+            let step = prev.map(|p| distance(p, wp)).unwrap_or(0.0);
+            cumulative += step;
+
+            let yaw = wp.get("yaw").and_then(Value::as_f64).unwrap_or_else(|| super::helpers::yaw_of(&[wp]));
+            let leg_heading = match (prev, next) {
+                (_, Some(n)) => heading(wp, n),
+                (Some(p), None) => heading(p, wp),
+                (None, None) => yaw,
+            };
+
+            let mut fields = fields.clone();
+            fields.entry("index").or_insert_with(|| json!(i));
+            fields.entry("parent_id").or_insert(Value::Null);
+            fields.insert("yaw".into(), json!(yaw));
+            fields.insert("yaw_deg".into(), json!(yaw.to_degrees()));
+            fields.insert("distance_from_prev".into(), json!(step));
+            fields.insert("cumulative_distance".into(), json!(cumulative));
+            fields.insert("heading".into(), json!(leg_heading));
+            Value::Object(fields)
+        })
+        .collect()
+}
+
+/// `{ waypoints, project: { name, exported_at }, map, frame_id }`; `map` is null without a map layer.
+pub fn build_context(waypoints: &[Value], meta: &ExportMeta) -> Value {
+    json!({
+        "waypoints": enrich_waypoints(waypoints),
+        "project": { "name": meta.project_name, "exported_at": meta.exported_at },
+        "map": meta.map,
+        "frame_id": meta.frame_id.as_deref().unwrap_or("map"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectMapLayer;
+
+    fn layer(name: &str, visible: bool, z_index: usize, resolution: f64) -> ProjectMapLayer {
+        ProjectMapLayer {
+            id: name.to_string(),
+            name: name.to_string(),
+            info: Some(json!({ "image": format!("{}.pgm", name), "resolution": resolution, "origin": [-10.0, -5.5, 0.0], "negate": 0 })),
+            image_base64: String::new(),
+            image_file: None,
+            visible,
+            opacity: 1.0,
+            z_index,
+        }
+    }
+
+    #[test]
+    fn test_derived_waypoint_fields() {
+        let waypoints = vec![
+            json!({ "id": "a", "x": 0.0, "y": 0.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 }),
+            json!({ "id": "b", "x": 3.0, "y": 4.0, "yaw": std::f64::consts::PI, "parent_id": "g" }),
+            json!({ "id": "c", "x": 3.0, "y": 10.0 }),
+        ];
+        let out = enrich_waypoints(&waypoints);
+
+        assert_eq!(out[0]["index"], json!(0));
+        assert_eq!(out[0]["parent_id"], Value::Null);
+        assert_eq!(out[0]["distance_from_prev"], json!(0.0));
+        assert!((out[0]["heading"].as_f64().unwrap() - (4.0f64).atan2(3.0)).abs() < 1e-9);
+        assert_eq!(out[1]["yaw_deg"], json!(180.0));
+        assert_eq!(out[1]["distance_from_prev"], json!(5.0));
+        assert_eq!(out[1]["parent_id"], json!("g"));
+        assert_eq!(out[2]["cumulative_distance"], json!(11.0));
+        assert_eq!(out[2]["heading"], json!(std::f64::consts::FRAC_PI_2));
+    }
+
+    #[test]
+    fn test_map_meta_prefers_lowest_visible_layer() {
+        let mut project: ProjectData = serde_json::from_str(r#"{"root_node_ids":[],"nodes":{}}"#).unwrap();
+        assert_eq!(MapMeta::from_project(&project), None);
+
+        project.map_layers = Some(vec![layer("hidden", false, 0, 0.01), layer("top", true, 2, 0.1), layer("base", true, 1, 0.05)]);
+        let map = MapMeta::from_project(&project).unwrap();
+        assert_eq!(map.name, "base");
+        assert_eq!(map.image, "base.pgm");
+        assert_eq!(map.resolution, 0.05);
+        assert_eq!(map.origin, [-10.0, -5.5, 0.0]);
+    }
+
+    #[test]
+    fn test_context_project_name_and_timestamp() {
+        let meta = ExportMeta::new(None, "/tmp/site_a_nav2.yaml", None);
+        assert_eq!(meta.project_name, "site_a_nav2");
+        assert!(meta.exported_at.ends_with('Z'), "{}", meta.exported_at);
+        assert_eq!(ExportMeta::new(Some("Warehouse"), "", None).project_name, "Warehouse");
+        assert_eq!(ExportMeta::new(Some(""), "", None).project_name, "untitled");
+
+        let ctx = build_context(&[], &meta);
+        assert_eq!(ctx["project"]["name"], json!("site_a_nav2"));
+        assert_eq!(ctx["map"], Value::Null);
+        assert_eq!(ctx["frame_id"], json!("map"));
+        assert_eq!(ctx["waypoints"], json!([]));
+    }
+}
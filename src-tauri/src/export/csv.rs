@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::project::route::RouteWaypoint;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+/// What a CSV column contains.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "field", rename_all = "snake_case")]
+pub enum CsvField {
+    Index,
+    Id,
+    Type,
+    ParentId,
+    X,
+    Y,
+    Z,
+    Yaw {
+        #[serde(default)]
+        unit: AngleUnit,
+    },
+    Qx,
+    Qy,
+    Qz,
+    Qw,
+    /// A resolved option value; lists are joined with `list_delimiter`
+    Option { key: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CsvColumn {
+    #[serde(flatten)]
+    pub field: CsvField,
+    /// Header text, defaults to the field name (or the option key)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+}
+
+impl CsvColumn {
+    fn header(&self) -> String {
+        if let Some(header) = &self.header {
+            return header.clone();
+        }
+        match &self.field {
+            CsvField::Index => "index".into(),
+            CsvField::Id => "id".into(),
+            CsvField::Type => "type".into(),
+            CsvField::ParentId => "parent_id".into(),
+            CsvField::X => "x".into(),
+            CsvField::Y => "y".into(),
+            CsvField::Z => "z".into(),
+            CsvField::Yaw { unit: AngleUnit::Radians } => "yaw".into(),
+            CsvField::Yaw { unit: AngleUnit::Degrees } => "yaw_deg".into(),
+            CsvField::Qx => "qx".into(),
+            CsvField::Qy => "qy".into(),
+            CsvField::Qz => "qz".into(),
+            CsvField::Qw => "qw".into(),
+            CsvField::Option { key } => key.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvSettings {
+    pub columns: Vec<CsvColumn>,
+    /// Write a header row
+    pub header: bool,
+    /// Field separator; must be a single ASCII character
+    pub delimiter: char,
+    /// Separator for list option values inside one cell
+    pub list_delimiter: String,
+    /// Digits after the decimal point for floats; `None` keeps full precision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<usize>,
+}
+
+impl Default for CsvSettings {
+    fn default() -> Self {
+        let column = |field| CsvColumn { field, header: None };
+        Self {
+            columns: vec![
+                column(CsvField::Index),
+                column(CsvField::Id),
+                column(CsvField::X),
+                column(CsvField::Y),
+                column(CsvField::Yaw { unit: AngleUnit::Radians }),
+            ],
+            header: true,
+            delimiter: ',',
+            list_delimiter: ";".to_string(),
+            precision: None,
+        }
+    }
+}
+
+impl CsvSettings {
+    fn float(&self, v: f64) -> String {
+        match self.precision {
+            Some(p) => format!("{:.*}", p, v),
+            None => v.to_string(),
+        }
+    }
+
+    fn value(&self, value: &Value) -> String {
+        match value {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            Value::Number(n) if n.is_f64() => self.float(n.as_f64().unwrap_or_default()),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(items) => items.iter().map(|v| self.value(v)).collect::<Vec<_>>().join(&self.list_delimiter),
+            Value::Object(_) => value.to_string(),
+        }
+    }
+
+    fn cell(&self, column: &CsvColumn, wp: &RouteWaypoint) -> String {
+        match &column.field {
+            CsvField::Index => wp.index.to_string(),
+            CsvField::Id => wp.id.clone(),
+            CsvField::Type => wp.node_type.clone(),
+            CsvField::ParentId => wp.parent_id.clone().unwrap_or_default(),
+            CsvField::X => self.float(wp.x),
+            CsvField::Y => self.float(wp.y),
+            CsvField::Z => self.float(wp.z),
+            CsvField::Yaw { unit: AngleUnit::Radians } => self.float(wp.yaw),
+            CsvField::Yaw { unit: AngleUnit::Degrees } => self.float(wp.yaw.to_degrees()),
+            CsvField::Qx => self.float(wp.qx),
+            CsvField::Qy => self.float(wp.qy),
+            CsvField::Qz => self.float(wp.qz),
+            CsvField::Qw => self.float(wp.qw),
+            CsvField::Option { key } => wp.options.get(key).map(|v| self.value(v)).unwrap_or_default(),
+        }
+    }
+}
+
+pub fn render_csv(route: &[RouteWaypoint], settings: &CsvSettings) -> Result<String, String> {
+    if !settings.delimiter.is_ascii() {
+        return Err(format!("CSV delimiter must be a single ASCII character: {:?}", settings.delimiter));
+    }
+    if settings.columns.is_empty() {
+        return Err("CSV export needs at least one column".to_string());
+    }
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(settings.delimiter as u8)
+        .from_writer(Vec::new());
+    if settings.header {
+        writer.write_record(settings.columns.iter().map(CsvColumn::header))
+            .map_err(|e| format!("CSV write error: {}", e))?;
+    }
+    for wp in route {
+        writer.write_record(settings.columns.iter().map(|c| settings.cell(c, wp)))
+            .map_err(|e| format!("CSV write error: {}", e))?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| format!("CSV write error: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("CSV write error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn route() -> Vec<RouteWaypoint> {
+        vec![RouteWaypoint {
+            index: 1,
+            id: "a".to_string(),
+            node_type: "manual".to_string(),
+            x: 1.23456,
+            y: -2.0,
+            z: 0.0,
+            yaw: std::f64::consts::FRAC_PI_2,
+            qx: 0.0,
+            qy: 0.0,
+            qz: std::f64::consts::FRAC_1_SQRT_2,
+            qw: std::f64::consts::FRAC_1_SQRT_2,
+            options: BTreeMap::from([
+                ("actions".to_string(), json!(["dock", "charge"])),
+                ("note".to_string(), json!("left; then right")),
+                ("retries".to_string(), json!(3)),
+            ]),
+            parent_id: Some("g".to_string()),
+        }]
+    }
+
+    #[test]
+    fn test_default_columns_with_header() {
+        let out = render_csv(&route(), &CsvSettings::default()).unwrap();
+        assert_eq!(out, format!("index,id,x,y,yaw\n1,a,1.23456,-2,{}\n", std::f64::consts::FRAC_PI_2));
+    }
+
+    #[test]
+    fn test_custom_columns_precision_and_lists() {
+        let settings: CsvSettings = serde_json::from_value(json!({
+            "columns": [
+                { "field": "id", "header": "name" },
+                { "field": "x" },
+                { "field": "yaw", "unit": "degrees" },
+                { "field": "option", "key": "actions" },
+                { "field": "option", "key": "retries" },
+                { "field": "option", "key": "missing" },
+            ],
+            "header": false,
+            "delimiter": ";",
+            "list_delimiter": "|",
+            "precision": 2,
+        })).unwrap();
+        assert_eq!(render_csv(&route(), &settings).unwrap(), "a;1.23;90.00;dock|charge;3;\n");
+    }
+
+    #[test]
+    fn test_cells_containing_the_delimiter_are_quoted() {
+        let settings = CsvSettings {
+            columns: vec![CsvColumn { field: CsvField::Option { key: "note".into() }, header: None }],
+            delimiter: ';',
+            ..Default::default()
+        };
+        assert_eq!(render_csv(&route(), &settings).unwrap(), "note\n\"left; then right\"\n");
+
+        let settings = CsvSettings { delimiter: '→', ..Default::default() };
+        assert!(render_csv(&route(), &settings).is_err());
+    }
+}
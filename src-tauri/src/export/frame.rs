@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use crate::models::Transform;
+use crate::project::route::RouteWaypoint;
+
+/// A signed source axis, written `"x"`, `"-y"`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    #[serde(rename = "x")]
+    X,
+    #[serde(rename = "y")]
+    Y,
+    #[serde(rename = "z")]
+    Z,
+    #[serde(rename = "-x")]
+    NegX,
+    #[serde(rename = "-y")]
+    NegY,
+    #[serde(rename = "-z")]
+    NegZ,
+}
+
+impl Axis {
+    /// (source axis index, sign)
+    fn source(self) -> (usize, f64) {
+        match self {
+            Axis::X => (0, 1.0),
+            Axis::Y => (1, 1.0),
+            Axis::Z => (2, 1.0),
+            Axis::NegX => (0, -1.0),
+            Axis::NegY => (1, -1.0),
+            Axis::NegZ => (2, -1.0),
+        }
+    }
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_axes() -> [Axis; 3] {
+    [Axis::X, Axis::Y, Axis::Z]
+}
+
+/// Moves waypoints from the map frame into a target frame. Applied in order:
+/// the rigid transform (pose of the map frame in the target frame, meters and
+/// radians), then the axis convention, then the unit scale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameTransform {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+    /// Target axes in terms of the source ones, e.g. `["x", "-y", "z"]` flips y
+    #[serde(default = "default_axes")]
+    pub axes: [Axis; 3],
+    /// Multiplies positions, e.g. 1000 for millimeters
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// `frame_id` written by formats that have one (Nav2 headers, `{{frame_id}}` in templates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<String>,
+}
+
+impl Default for FrameTransform {
+    fn default() -> Self {
+        Self { x: 0.0, y: 0.0, z: 0.0, roll: 0.0, pitch: 0.0, yaw: 0.0, axes: default_axes(), scale: 1.0, frame_id: None }
+    }
+}
+
+/// Hamilton product `a * b` of `[x, y, z, w]` quaternions.
+fn quat_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+fn quat_from_rpy(roll: f64, pitch: f64, yaw: f64) -> [f64; 4] {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+    [
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+        cr * cp * cy + sr * sp * sy,
+    ]
+}
+
+fn rotate(q: [f64; 4], v: [f64; 3]) -> [f64; 3] {
+    let [x, y, z, _] = quat_mul(quat_mul(q, [v[0], v[1], v[2], 0.0]), [-q[0], -q[1], -q[2], q[3]]);
+    [x, y, z]
+}
+
+impl FrameTransform {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// True when waypoints come out unchanged (`frame_id` aside).
+    pub fn is_identity(&self) -> bool {
+        Self { frame_id: None, ..self.clone() }.is_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut used = [false; 3];
+        for axis in self.axes {
+            used[axis.source().0] = true;
+        }
+        if used.contains(&false) {
+            return Err(format!("Axes must use x, y and z once each: {:?}", self.axes));
+        }
+        if !self.scale.is_finite() || self.scale == 0.0 {
+            return Err(format!("Scale must be a non-zero number: {}", self.scale));
+        }
+        Ok(())
+    }
+
+    /// Orientation sign of the axis mapping: -1 when it mirrors (changes handedness).
+    fn axes_determinant(&self) -> f64 {
+        let order: Vec<usize> = self.axes.iter().map(|a| a.source().0).collect();
+        let inversions = (0..3).flat_map(|i| (i + 1..3).map(move |j| (i, j))).filter(|&(i, j)| order[i] > order[j]).count();
+        let parity = if inversions % 2 == 0 { 1.0 } else { -1.0 };
+        self.axes.iter().fold(parity, |d, a| d * a.source().1)
+    }
+
+    fn map_axes(&self, v: [f64; 3]) -> [f64; 3] {
+        self.axes.map(|a| {
+            let (i, sign) = a.source();
+            sign * v[i]
+        })
+    }
+
+    pub fn apply(&self, wp: &mut RouteWaypoint) {
+        let r = quat_from_rpy(self.roll, self.pitch, self.yaw);
+        let [px, py, pz] = rotate(r, [wp.x, wp.y, wp.z]);
+        let p = self.map_axes([px + self.x, py + self.y, pz + self.z]);
+        (wp.x, wp.y, wp.z) = (p[0] * self.scale, p[1] * self.scale, p[2] * self.scale);
+
+        // An orthogonal axis map M turns rotation R into M R Mᵀ: the quaternion's
+        // vector part becomes det(M)·M·v and its scalar part is kept.
+        let [qx, qy, qz, qw] = quat_mul(r, [wp.qx, wp.qy, wp.qz, wp.qw]);
+        let det = self.axes_determinant();
+        let v = self.map_axes([qx, qy, qz]).map(|c| c * det);
+        let t = Transform { x: wp.x, y: wp.y, z: Some(wp.z), qx: v[0], qy: v[1], qz: v[2], qw };
+        (wp.qx, wp.qy, wp.qz, wp.qw) = (t.qx, t.qy, t.qz, t.qw);
+        wp.yaw = t.yaw();
+    }
+
+    pub fn apply_all(&self, route: &mut [RouteWaypoint]) -> Result<(), String> {
+        self.validate()?;
+        if !self.is_identity() {
+            route.iter_mut().for_each(|wp| self.apply(wp));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+    fn waypoint(x: f64, y: f64) -> RouteWaypoint {
+        RouteWaypoint {
+            index: 0,
+            id: "a".to_string(),
+            node_type: "manual".to_string(),
+            x,
+            y,
+            z: 0.0,
+            yaw: 0.0,
+            qx: 0.0,
+            qy: 0.0,
+            qz: 0.0,
+            qw: 1.0,
+            options: BTreeMap::new(),
+            parent_id: None,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_rigid_transform_moves_position_and_heading() {
+        let mut route = vec![waypoint(1.0, 0.0)];
+        let transform = FrameTransform { x: 10.0, y: -2.0, yaw: FRAC_PI_2, ..Default::default() };
+        transform.apply_all(&mut route).unwrap();
+        let wp = &route[0];
+        assert!(close(wp.x, 10.0) && close(wp.y, -1.0), "{:?}", (wp.x, wp.y));
+        assert!(close(wp.yaw, FRAC_PI_2));
+        assert!(close(wp.qz, FRAC_1_SQRT_2) && close(wp.qw, FRAC_1_SQRT_2));
+
+        let mut unchanged = vec![waypoint(1.0, 2.0)];
+        FrameTransform { frame_id: Some("site".into()), ..Default::default() }.apply_all(&mut unchanged).unwrap();
+        assert_eq!(unchanged, vec![waypoint(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn test_flipped_y_and_millimeters() {
+        let mut wp = waypoint(1.5, 2.0);
+        (wp.qz, wp.qw, wp.yaw) = (FRAC_1_SQRT_2, FRAC_1_SQRT_2, FRAC_PI_2);
+        let mut route = vec![wp];
+        let transform: FrameTransform = serde_json::from_str(r#"{ "axes": ["x", "-y", "z"], "scale": 1000 }"#).unwrap();
+        transform.apply_all(&mut route).unwrap();
+        let wp = &route[0];
+        assert!(close(wp.x, 1500.0) && close(wp.y, -2000.0));
+        // Facing +y becomes facing -y in the mirrored frame
+        assert!(close(wp.yaw, -FRAC_PI_2), "{}", wp.yaw);
+        assert!(close(wp.qz, -FRAC_1_SQRT_2) && close(wp.qw, FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_invalid_axes_and_scale_are_rejected() {
+        let mut route = vec![waypoint(0.0, 0.0)];
+        let transform: FrameTransform = serde_json::from_str(r#"{ "axes": ["x", "x", "z"] }"#).unwrap();
+        assert!(transform.apply_all(&mut route).is_err());
+        assert!(FrameTransform { scale: 0.0, ..Default::default() }.apply_all(&mut route).is_err());
+        assert!(serde_json::from_str::<FrameTransform>(r#"{ "axes": ["x", "w", "z"] }"#).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use handlebars::{
+    handlebars_helper, no_escape, Context, Handlebars, Helper, HelperDef, RenderContext, RenderError,
+    RenderErrorReason, ScopedJson,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Names registered by `register_builtin_helpers`; user helpers may not shadow them.
+pub const BUILTIN_HELPERS: &[&str] = &[
+    "yaw", "deg", "round", "fixed", "add", "mul", "pad", "json", "yaml", "upper", "eq", "gt", "first", "last",
+];
+
+/// Yaw in radians from `(yaw this)` or `(yaw qx qy qz qw)`.
+pub(super) fn yaw_of(args: &[&Value]) -> f64 {
+    let q: Vec<f64> = match args {
+        [Value::Object(o)] => ["qx", "qy", "qz", "qw"].iter().map(|k| o.get(*k).and_then(Value::as_f64).unwrap_or(0.0)).collect(),
+        _ => args.iter().map(|v| v.as_f64().unwrap_or(0.0)).collect(),
+    };
+    match q[..] {
+        [qx, qy, qz, qw] => (2.0 * (qw * qz + qx * qy)).atan2(1.0 - 2.0 * (qy * qy + qz * qz)),
+        _ => 0.0,
+    }
+}
+
+/// Integer arithmetic when both sides are integers, so `{{add @index 1}}` renders `1` and not `1.0`.
+fn arith(a: &Value, b: &Value, int_op: fn(i64, i64) -> Option<i64>, float_op: fn(f64, f64) -> f64) -> Value {
+    if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+        if let Some(r) = int_op(x, y) {
+            return json!(r);
+        }
+    }
+    json!(float_op(a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0)))
+}
+
+/// Plain text of a value: strings without quotes, everything else as JSON.
+fn text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn loose_eq(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+fn greater(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => x > y,
+        _ => matches!((a.as_f64(), b.as_f64()), (Some(x), Some(y)) if x > y),
+    }
+}
+
+handlebars_helper!(yaw: |*args| yaw_of(&args));
+handlebars_helper!(deg: |rad: f64| rad.to_degrees());
+handlebars_helper!(round: |v: f64, *args| {
+    match args.get(1).and_then(|d| d.as_u64()).unwrap_or(0) {
+        0 => json!(v.round() as i64),
+        digits => {
+            let factor = 10f64.powi(digits as i32);
+            json!((v * factor).round() / factor)
+        }
+    }
+});
+handlebars_helper!(fixed: |v: f64, digits: u64| format!("{:.*}", digits as usize, v));
+handlebars_helper!(add: |a: Json, b: Json| arith(a, b, i64::checked_add, |x, y| x + y));
+handlebars_helper!(mul: |a: Json, b: Json| arith(a, b, i64::checked_mul, |x, y| x * y));
+handlebars_helper!(pad: |v: Json, width: u64, *args| {
+    let fill = args.get(2).and_then(|c| c.as_str()).and_then(|c| c.chars().next()).unwrap_or('0');
+    let s = text(v);
+    let missing = (width as usize).saturating_sub(s.chars().count());
+    format!("{}{}", fill.to_string().repeat(missing), s)
+});
+handlebars_helper!(to_json: |v: Json| v.to_string());
+handlebars_helper!(to_yaml: |v: Json| serde_yaml::to_string(v).map(|s| s.trim_end().to_string()).unwrap_or_default());
+handlebars_helper!(upper: |s: str| s.to_uppercase());
+handlebars_helper!(eq: |a: Json, b: Json| loose_eq(a, b));
+handlebars_helper!(gt: |a: Json, b: Json| greater(a, b));
+handlebars_helper!(first: |items: array| items.first().cloned().unwrap_or(Value::Null));
+handlebars_helper!(last: |items: array| items.last().cloned().unwrap_or(Value::Null));
+
+pub fn register_builtin_helpers(reg: &mut Handlebars) {
+    reg.register_helper("yaw", Box::new(yaw));
+    reg.register_helper("deg", Box::new(deg));
+    reg.register_helper("round", Box::new(round));
+    reg.register_helper("fixed", Box::new(fixed));
+    reg.register_helper("add", Box::new(add));
+    reg.register_helper("mul", Box::new(mul));
+    reg.register_helper("pad", Box::new(pad));
+    reg.register_helper("json", Box::new(to_json));
+    reg.register_helper("yaml", Box::new(to_yaml));
+    reg.register_helper("upper", Box::new(upper));
+    reg.register_helper("eq", Box::new(eq));
+    reg.register_helper("gt", Box::new(gt));
+    reg.register_helper("first", Box::new(first));
+    reg.register_helper("last", Box::new(last));
+}
+
+/// A user helper written as a Handlebars snippet. The snippet sees the call's
+/// positional parameters as `args` and its hash parameters as `hash`, e.g.
+/// `{{pose}}` defined as `[{{fixed (lookup args 0) 3}}, {{fixed (lookup args 1) 3}}]`.
+pub struct TemplateHelper {
+    pub template: String,
+}
+
+impl HelperDef for TemplateHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let args: Vec<Value> = h.params().iter().map(|p| p.value().clone()).collect();
+        let hash: serde_json::Map<String, Value> = h.hash().iter().map(|(k, v)| (k.to_string(), v.value().clone())).collect();
+        let rendered = r
+            .render_template(&self.template, &json!({ "args": args, "hash": hash }))
+            .map_err(|e| RenderErrorReason::Other(format!("helper '{}': {}", h.name(), e)))?;
+        Ok(ScopedJson::Derived(Value::String(rendered)))
+    }
+}
+
+/// Register user helpers (name -> Handlebars snippet), rejecting bad names and snippets up front.
+pub fn register_user_helpers(reg: &mut Handlebars, helpers: &BTreeMap<String, String>) -> Result<(), String> {
+    for (name, template) in helpers {
+        if BUILTIN_HELPERS.contains(&name.as_str()) {
+            return Err(format!("Helper '{}' shadows a built-in helper", name));
+        }
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("Invalid helper name: {:?}", name));
+        }
+        handlebars::Template::compile(template).map_err(|e| format!("Helper '{}' template error: {}", name, e))?;
+        reg.register_helper(name, Box::new(TemplateHelper { template: template.clone() }));
+    }
+    Ok(())
+}
+
+/// Registry used for every export template. Without `escape_html` values are written
+/// as they are, which YAML or CSV output wants; with it they are HTML-escaped as usual.
+pub fn export_registry(user_helpers: &BTreeMap<String, String>, escape_html: bool) -> Result<Handlebars<'static>, String> {
+    let mut reg = Handlebars::new();
+    if !escape_html {
+        reg.register_escape_fn(no_escape);
+    }
+    register_builtin_helpers(&mut reg);
+    register_user_helpers(&mut reg, user_helpers)?;
+    Ok(reg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, data: Value) -> String {
+        export_registry(&BTreeMap::new(), false).unwrap().render_template(template, &data).unwrap()
+    }
+
+    #[test]
+    fn test_numeric_helpers() {
+        let wp = json!({ "qx": 0.0, "qy": 0.0, "qz": std::f64::consts::FRAC_1_SQRT_2, "qw": std::f64::consts::FRAC_1_SQRT_2 });
+        assert_eq!(render("{{round (deg (yaw this))}}", wp.clone()), "90");
+        assert_eq!(render("{{fixed (yaw qx qy qz qw) 3}}", wp), "1.571");
+        assert_eq!(render("{{round 2.71828 2}}", json!({})), "2.72");
+        assert_eq!(render("{{#each items}}{{add @index 1}}{{/each}}", json!({ "items": ["a", "b"] })), "12");
+        assert_eq!(render("{{mul 1.5 2}}", json!({})), "3.0");
+        assert_eq!(render("{{pad 7 3}}|{{pad \"ab\" 4 \" \"}}", json!({})), "007|  ab");
+    }
+
+    #[test]
+    fn test_format_and_comparison_helpers() {
+        let data = json!({ "wps": [{ "id": "a", "speed": 1 }, { "id": "b", "speed": 0.5 }], "opts": { "tag": "x&y" } });
+        assert_eq!(render("{{json opts}}", data.clone()), r#"{"tag":"x&y"}"#);
+        assert_eq!(render("{{yaml opts}}", data.clone()), "tag: x&y");
+        assert_eq!(render("{{#with (first wps)}}{{upper id}}{{/with}}{{#with (last wps)}}{{id}}{{/with}}", data.clone()), "Ab");
+        assert_eq!(
+            render("{{#each wps}}{{#if (eq speed 1.0)}}full{{/if}}{{#if (gt speed 0.7)}}!{{/if}}{{#unless @last}},{{/unless}}{{/each}}", data),
+            "full!,"
+        );
+    }
+
+    #[test]
+    fn test_user_helpers() {
+        let helpers = BTreeMap::from([("xy".to_string(), "[{{fixed (lookup args 0) 1}}, {{fixed (lookup args 1) 1}}]{{hash.suffix}}".to_string())]);
+        let reg = export_registry(&helpers, false).unwrap();
+        assert_eq!(reg.render_template("{{xy x y suffix=\";\"}}", &json!({ "x": 1, "y": 2.26 })).unwrap(), "[1.0, 2.3];");
+
+        let shadowing = BTreeMap::from([("yaw".to_string(), "{{args}}".to_string())]);
+        assert!(export_registry(&shadowing, false).is_err());
+        let broken = BTreeMap::from([("bad".to_string(), "{{#if x}}".to_string())]);
+        assert!(export_registry(&broken, false).is_err());
+    }
+
+    #[test]
+    fn test_escaping_follows_the_template_setting() {
+        let data = json!({ "opts": { "tag": "x&y" } });
+        let escaped = export_registry(&BTreeMap::new(), true).unwrap();
+        assert_eq!(escaped.render_template("{{opts.tag}} {{{opts.tag}}}", &data).unwrap(), "x&amp;y x&y");
+        assert_eq!(render("{{opts.tag}}", data), "x&y");
+    }
+}
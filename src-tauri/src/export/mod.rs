@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::models::ProjectData;
+use crate::project::route::{build_route, RouteOptions, RouteWaypoint};
+
+pub mod context;
+pub mod csv;
+pub mod frame;
+pub mod helpers;
+pub mod nav2;
+pub mod preview;
+pub mod profile;
+pub mod render;
+pub mod report;
+pub mod templates;
+
+/// How a template export is produced, besides its data and output path.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportRequest {
+    pub route_options: RouteOptions,
+    /// User helpers and the template's `escape_html`; the template library supplies the partials
+    #[serde(flatten)]
+    pub env: templates::TemplateEnv,
+    /// `project.name` in templates, the output file name when missing
+    pub project_name: Option<String>,
+    /// Applied to every waypoint before rendering
+    pub transform: frame::FrameTransform,
+}
+
+/// Built-in export formats that need no template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// `waypoints: { waypoint0: { pose, orientation } }` as saved by the Nav2 RViz panel
+    Nav2Waypoints(nav2::Nav2Settings),
+    /// Goal YAML for `ros2 action send_goal /follow_waypoints nav2_msgs/action/FollowWaypoints`
+    Nav2FollowWaypoints(nav2::Nav2Settings),
+    /// Goal YAML for `nav2_msgs/action/NavigateThroughPoses`
+    Nav2NavigateThroughPoses(nav2::Nav2Settings),
+    /// `<node>: { ros__parameters: ... }` params file
+    Nav2Params(nav2::Nav2Settings),
+    /// One row per waypoint with a user-defined column list
+    Csv(csv::CsvSettings),
+}
+
+impl ExportFormat {
+    /// Render the route in this format.
+    pub fn render(&self, route: &[RouteWaypoint]) -> Result<String, String> {
+        match self {
+            ExportFormat::Nav2Waypoints(s) => nav2::render_waypoints(route, s),
+            ExportFormat::Nav2FollowWaypoints(s) => nav2::render_follow_waypoints(route, s),
+            ExportFormat::Nav2NavigateThroughPoses(s) => nav2::render_navigate_through_poses(route, s),
+            ExportFormat::Nav2Params(s) => nav2::render_params(route, s),
+            ExportFormat::Csv(s) => csv::render_csv(route, s),
+        }
+    }
+
+    /// The same format writing `frame_id` instead of its configured frame, where it has one.
+    pub fn with_frame_id(&self, frame_id: Option<&str>) -> ExportFormat {
+        let mut format = self.clone();
+        if let Some(frame_id) = frame_id {
+            match &mut format {
+                ExportFormat::Nav2Waypoints(s)
+                | ExportFormat::Nav2FollowWaypoints(s)
+                | ExportFormat::Nav2NavigateThroughPoses(s)
+                | ExportFormat::Nav2Params(s) => s.frame_id = frame_id.to_string(),
+                ExportFormat::Csv(_) => {}
+            }
+        }
+        format
+    }
+}
+
+/// Render a user template against the waypoints and `meta`.
+pub fn render_template(template: &str, waypoints: &[Value], env: &templates::TemplateEnv, meta: &context::ExportMeta) -> Result<String, String> {
+    env.registry()?
+        .render_template(template, &context::build_context(waypoints, meta))
+        .map_err(|e| format!("Template render error: {}", e))
+}
+
+/// The plain waypoint list: YAML for `.yaml`/`.yml` paths, pretty JSON otherwise.
+pub fn render_data(path: &str, waypoints: &[Value]) -> Result<String, String> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".yaml") || lower.ends_with(".yml") {
+        serde_yaml::to_string(waypoints).map_err(|e| format!("YAML serialization error: {}", e))
+    } else {
+        serde_json::to_string_pretty(waypoints).map_err(|e| format!("JSON serialization error: {}", e))
+    }
+}
+
+/// Flatten the project, move it into the target frame and write it in a built-in format.
+pub fn export_builtin(path: &str, project: &ProjectData, format: &ExportFormat, route_options: &RouteOptions, transform: &frame::FrameTransform) -> Result<(), String> {
+    let mut route = build_route(project, route_options);
+    transform.apply_all(&mut route)?;
+    let content = format.with_frame_id(transform.frame_id.as_deref()).render(&route)?;
+    std::fs::write(path, content).map_err(|e| format!("File write error: {}", e))
+}
+
+/// Draw the project's route over its map layers and write it as a PNG.
+pub fn export_map_image(path: &str, project: &ProjectData, route_options: &RouteOptions, settings: &render::RenderSettings) -> Result<(), String> {
+    let image = render::render_map(project, &build_route(project, route_options), settings)?;
+    std::fs::write(path, render::encode_png(&image)?).map_err(|e| format!("Image write error: {}", e))
+}
+
+/// Write the mission report as SVG or PDF, chosen by the extension of `path`.
+pub fn export_report(path: &str, project: &ProjectData, route_options: &RouteOptions, settings: &report::ReportSettings, project_name: Option<&str>) -> Result<(), String> {
+    let meta = context::ExportMeta::new(project_name, path, None);
+    let report = report::build_report(project, build_route(project, route_options), settings, &meta.project_name)?;
+    let content = report::render_report(std::path::Path::new(path), &report)?;
+    std::fs::write(path, content).map_err(|e| format!("File write error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_tag_uses_default_settings() {
+        let format: ExportFormat = serde_json::from_str(r#"{ "format": "nav2_params" }"#).unwrap();
+        assert_eq!(format, ExportFormat::Nav2Params(nav2::Nav2Settings::default()));
+
+        let format: ExportFormat = serde_json::from_str(r#"{ "format": "nav2_waypoints", "frame_id": "site" }"#).unwrap();
+        let ExportFormat::Nav2Waypoints(settings) = format else { panic!("wrong variant") };
+        assert_eq!(settings.frame_id, "site");
+    }
+
+    #[test]
+    fn test_transform_frame_id_overrides_nav2_settings() {
+        let format = ExportFormat::Nav2FollowWaypoints(nav2::Nav2Settings::default());
+        let ExportFormat::Nav2FollowWaypoints(settings) = format.with_frame_id(Some("site")) else { panic!("wrong variant") };
+        assert_eq!(settings.frame_id, "site");
+        assert_eq!(format.with_frame_id(None), format);
+    }
+
+    #[test]
+    fn test_export_request_takes_helpers_at_top_level() {
+        let request: ExportRequest = serde_json::from_str(r#"{
+            "route_options": { "index_start": 1 },
+            "helpers": { "shout": "{{upper value}}" },
+            "project_name": "site",
+            "transform": { "frame_id": "site" }
+        }"#).unwrap();
+        assert_eq!(request.route_options.index_start, 1);
+        assert_eq!(request.env.helpers["shout"], "{{upper value}}");
+        assert_eq!(request.project_name.as_deref(), Some("site"));
+        assert_eq!(request.transform.frame_id.as_deref(), Some("site"));
+        assert!(serde_json::from_str::<ExportRequest>("{}").is_ok());
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::project::route::RouteWaypoint;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Nav2Settings {
+    /// `header.frame_id` of every pose
+    pub frame_id: String,
+    /// Top-level key of the params file
+    pub node_name: String,
+}
+
+impl Default for Nav2Settings {
+    fn default() -> Self {
+        Self {
+            frame_id: "map".to_string(),
+            node_name: "waypoint_follower".to_string(),
+        }
+    }
+}
+
+fn to_yaml<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_yaml::to_string(value).map_err(|e| format!("YAML serialization error: {}", e))
+}
+
+/// Mapping that keeps insertion order. `serde_json::Map` sorts its keys, which would put
+/// `waypoint10` before `waypoint2`.
+fn ordered_mapping(entries: impl IntoIterator<Item = (String, Value)>) -> Result<serde_yaml::Mapping, String> {
+    entries
+        .into_iter()
+        .map(|(key, value)| {
+            let value = serde_yaml::to_value(value).map_err(|e| format!("YAML serialization error: {}", e))?;
+            Ok((serde_yaml::Value::String(key), value))
+        })
+        .collect()
+}
+
+/// `geometry_msgs/PoseStamped` as a YAML-ready value.
+fn pose_stamped(wp: &RouteWaypoint, frame_id: &str) -> Value {
+    json!({
+        "header": { "frame_id": frame_id },
+        "pose": {
+            "position": { "x": wp.x, "y": wp.y, "z": wp.z },
+            "orientation": { "x": wp.qx, "y": wp.qy, "z": wp.qz, "w": wp.qw },
+        },
+    })
+}
+
+/// RViz Nav2 panel / waypoint_follower file. The panel has no frame field, so
+/// `frame_id` is ignored here.
+pub fn render_waypoints(route: &[RouteWaypoint], _settings: &Nav2Settings) -> Result<String, String> {
+    let waypoints = ordered_mapping(route.iter().enumerate().map(|(i, wp)| {
+        (format!("waypoint{}", i), json!({
+            "pose": [wp.x, wp.y, wp.z],
+            "orientation": [wp.qx, wp.qy, wp.qz, wp.qw],
+        }))
+    }))?;
+    let mut file = serde_yaml::Mapping::new();
+    file.insert("waypoints".into(), waypoints.into());
+    to_yaml(&file)
+}
+
+pub fn render_follow_waypoints(route: &[RouteWaypoint], settings: &Nav2Settings) -> Result<String, String> {
+    let poses: Vec<Value> = route.iter().map(|wp| pose_stamped(wp, &settings.frame_id)).collect();
+    to_yaml(&json!({ "poses": poses }))
+}
+
+/// An empty `behavior_tree` makes bt_navigator use its default tree.
+pub fn render_navigate_through_poses(route: &[RouteWaypoint], settings: &Nav2Settings) -> Result<String, String> {
+    let poses: Vec<Value> = route.iter().map(|wp| pose_stamped(wp, &settings.frame_id)).collect();
+    to_yaml(&json!({ "poses": poses, "behavior_tree": "" }))
+}
+
+/// Params file. Node ids are often UUIDs, which are not valid parameter names,
+/// so waypoints are keyed `waypoint<index>` and keep their id as a string parameter.
+pub fn render_params(route: &[RouteWaypoint], settings: &Nav2Settings) -> Result<String, String> {
+    let mut params = vec![("frame_id".to_string(), json!(settings.frame_id))];
+    let names: Vec<String> = route.iter().map(|wp| format!("waypoint{}", wp.index)).collect();
+    params.push(("waypoints".to_string(), json!(names)));
+
+    for (name, wp) in names.into_iter().zip(route) {
+        let mut entry = json!({
+            "id": wp.id,
+            "position": [wp.x, wp.y, wp.z],
+            "orientation": [wp.qx, wp.qy, wp.qz, wp.qw],
+            "yaw": wp.yaw,
+        });
+        // Parameters cannot be null
+        let options: Map<String, Value> = wp.options.iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if !options.is_empty() {
+            entry["options"] = Value::Object(options);
+        }
+        params.push((name, entry));
+    }
+
+    let mut node = serde_yaml::Mapping::new();
+    node.insert("ros__parameters".into(), ordered_mapping(params)?.into());
+    let mut file = serde_yaml::Mapping::new();
+    file.insert(settings.node_name.clone().into(), node.into());
+    to_yaml(&file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn route() -> Vec<RouteWaypoint> {
+        let wp = |index: usize, id: &str, x: f64, qz: f64, qw: f64| RouteWaypoint {
+            index,
+            id: id.to_string(),
+            node_type: "manual".to_string(),
+            x,
+            y: 2.0,
+            z: 0.0,
+            yaw: 2.0 * qz.atan2(qw),
+            qx: 0.0,
+            qy: 0.0,
+            qz,
+            qw,
+            options: BTreeMap::from([("speed".to_string(), json!(0.3))]),
+            parent_id: None,
+        };
+        vec![wp(1, "a-1", 1.0, 0.0, 1.0), wp(2, "b-2", 3.5, 1.0, 0.0)]
+    }
+
+    #[test]
+    fn test_follow_waypoints_goal_nests_pose() {
+        let yaml = render_follow_waypoints(&route(), &Nav2Settings { frame_id: "site".into(), ..Default::default() }).unwrap();
+        let v: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(v["poses"][1]["header"]["frame_id"], json!("site"));
+        assert_eq!(v["poses"][1]["pose"]["position"]["x"], json!(3.5));
+        assert_eq!(v["poses"][1]["pose"]["orientation"]["w"], json!(0.0));
+        assert!(v.get("behavior_tree").is_none());
+
+        let yaml = render_navigate_through_poses(&route(), &Nav2Settings::default()).unwrap();
+        let v: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(v["behavior_tree"], json!(""));
+        assert_eq!(v["poses"][0]["header"]["frame_id"], json!("map"));
+    }
+
+    #[test]
+    fn test_rviz_waypoints_file_uses_arrays() {
+        let v: Value = serde_yaml::from_str(&render_waypoints(&route(), &Nav2Settings::default()).unwrap()).unwrap();
+        assert_eq!(v["waypoints"]["waypoint0"]["pose"], json!([1.0, 2.0, 0.0]));
+        assert_eq!(v["waypoints"]["waypoint1"]["orientation"], json!([0.0, 0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_params_file_is_keyed_by_node_name() {
+        let yaml = render_params(&route(), &Nav2Settings::default()).unwrap();
+        let v: Value = serde_yaml::from_str(&yaml).unwrap();
+        let params = &v["waypoint_follower"]["ros__parameters"];
+        assert_eq!(params["waypoints"], json!(["waypoint1", "waypoint2"]));
+        assert_eq!(params["waypoint2"]["id"], json!("b-2"));
+        assert_eq!(params["waypoint2"]["options"]["speed"], json!(0.3));
+    }
+
+    #[test]
+    fn test_waypoint_keys_keep_route_order_past_ten() {
+        let base = route().remove(0);
+        let long: Vec<RouteWaypoint> = (0..12)
+            .map(|i| RouteWaypoint { index: i, id: format!("wp-{}", i), x: i as f64, ..base.clone() })
+            .collect();
+
+        let keys = |yaml: &str| -> Vec<usize> {
+            yaml.lines()
+                .filter_map(|l| l.trim().strip_prefix("waypoint")?.strip_suffix(':')?.parse().ok())
+                .collect()
+        };
+        assert_eq!(keys(&render_waypoints(&long, &Nav2Settings::default()).unwrap()), (0..12).collect::<Vec<_>>());
+        assert_eq!(keys(&render_params(&long, &Nav2Settings::default()).unwrap()), (0..12).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,167 @@
+use handlebars::Template;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use crate::models::ProjectData;
+use crate::project::route::{build_route, route_to_values, RouteOptions, RouteWaypoint};
+use super::context::{build_context, ExportMeta, MapMeta};
+use super::templates::TemplateEnv;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateStage {
+    Compile,
+    Render,
+}
+
+/// A template problem with its 1-based position, when Handlebars reports one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateDiagnostic {
+    pub stage: TemplateStage,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplatePreview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<TemplateDiagnostic>,
+}
+
+/// Compile only, reporting syntax errors with their position.
+pub fn check_template(template: &str) -> Result<(), TemplateDiagnostic> {
+    Template::compile(template).map(|_| ()).map_err(|e| {
+        let (line, column) = e.pos().unzip();
+        TemplateDiagnostic { stage: TemplateStage::Compile, message: e.reason().to_string(), line, column }
+    })
+}
+
+/// A short route to preview against when the project has no waypoints yet.
+pub fn sample_route() -> Vec<RouteWaypoint> {
+    [(0.0, 0.0, 0.0), (2.0, 0.0, std::f64::consts::FRAC_PI_2), (2.0, 1.5, std::f64::consts::PI)]
+        .iter()
+        .enumerate()
+        .map(|(i, &(x, y, yaw))| {
+            let (qz, qw) = ((yaw / 2.0).sin(), (yaw / 2.0).cos());
+            RouteWaypoint {
+                index: i,
+                id: format!("sample-{}", i + 1),
+                node_type: "manual".to_string(),
+                x,
+                y,
+                z: 0.0,
+                yaw,
+                qx: 0.0,
+                qy: 0.0,
+                qz,
+                qw,
+                options: BTreeMap::new(),
+                parent_id: None,
+            }
+        })
+        .collect()
+}
+
+/// Stand-in map so `{{map.resolution}}` previews before a map is loaded.
+pub fn sample_map() -> MapMeta {
+    MapMeta { name: "sample".to_string(), image: "sample.pgm".to_string(), resolution: 0.05, origin: [-10.0, -10.0, 0.0] }
+}
+
+/// Render `template` against the waypoints without writing anything.
+/// In strict mode a missing field such as `{{speeed}}` is an error instead of an empty string.
+pub fn preview_template(template: &str, waypoints: &[Value], env: &TemplateEnv, meta: &ExportMeta, strict: bool) -> TemplatePreview {
+    let failed = |error| TemplatePreview { output: None, error: Some(error) };
+    if let Err(e) = check_template(template) {
+        return failed(e);
+    }
+
+    let mut reg = match env.registry() {
+        Ok(reg) => reg,
+        Err(message) => return failed(TemplateDiagnostic { stage: TemplateStage::Compile, message, line: None, column: None }),
+    };
+    reg.set_strict_mode(strict);
+    if let Err(e) = reg.register_template_string("preview", template) {
+        let (line, column) = e.pos().unzip();
+        return failed(TemplateDiagnostic { stage: TemplateStage::Compile, message: e.reason().to_string(), line, column });
+    }
+
+    match reg.render("preview", &build_context(waypoints, meta)) {
+        Ok(output) => TemplatePreview { output: Some(output), error: None },
+        Err(e) => failed(TemplateDiagnostic {
+            stage: TemplateStage::Render,
+            message: e.reason().to_string(),
+            line: e.line_no,
+            column: e.column_no,
+        }),
+    }
+}
+
+/// Preview against the project's route, or `sample_route` when it has no waypoints
+/// (and `sample_map` when `meta` has no map).
+pub fn preview_project_template(
+    template: &str,
+    project: Option<&ProjectData>,
+    route_options: &RouteOptions,
+    env: &TemplateEnv,
+    meta: &ExportMeta,
+    strict: bool,
+) -> Result<TemplatePreview, String> {
+    let mut route = project.map(|p| build_route(p, route_options)).unwrap_or_default();
+    if route.is_empty() {
+        route = sample_route();
+    }
+    let meta = ExportMeta { map: meta.map.clone().or_else(|| Some(sample_map())), ..meta.clone() };
+    Ok(preview_template(template, &route_to_values(&route)?, env, &meta, strict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(template: &str, strict: bool) -> TemplatePreview {
+        preview_project_template(template, None, &RouteOptions::default(), &TemplateEnv::default(), &ExportMeta::new(Some("demo"), "", None), strict).unwrap()
+    }
+
+    #[test]
+    fn test_syntax_errors_report_position() {
+        let error = preview("ok\n{{#each waypoints}}\n{{id}", false).error.unwrap();
+        assert_eq!(error.stage, TemplateStage::Compile);
+        assert_eq!(error.line, Some(3));
+        assert!(error.column.is_some());
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_missing_fields() {
+        let template = "{{#each waypoints}}{{id}}:{{speeed}};{{/each}}";
+        assert_eq!(preview(template, false).output.as_deref(), Some("sample-1:;sample-2:;sample-3:;"));
+
+        let error = preview(template, true).error.unwrap();
+        assert_eq!(error.stage, TemplateStage::Render);
+        assert!(error.message.contains("speeed"), "{}", error.message);
+        assert_eq!(error.line, Some(1));
+    }
+
+    #[test]
+    fn test_preview_has_project_map_and_derived_fields() {
+        let template = "{{project.name}} {{map.resolution}}{{#each waypoints}} {{index}}:{{cumulative_distance}}{{/each}}";
+        assert_eq!(preview(template, true).output.as_deref(), Some("demo 0.05 0:0.0 1:2.0 2:3.5"));
+    }
+
+    #[test]
+    fn test_preview_uses_project_route_and_helpers() {
+        let project: ProjectData = serde_json::from_str(r#"{"root_node_ids":["a"],"nodes":{"a":{"id":"a","type":"manual","transform":{"x":1.5,"y":0.0}}}}"#).unwrap();
+        let env = TemplateEnv {
+            helpers: BTreeMap::from([("tag".to_string(), "<{{lookup args 0}}>".to_string())]),
+            partials: BTreeMap::from([("sep".to_string(), ";".to_string())]),
+            escape_html: false,
+        };
+        let meta = ExportMeta::new(None, "", Some(&project));
+        let result = preview_project_template("{{#each waypoints}}{{tag id}}{{fixed x 2}}{{> sep}}{{/each}}", Some(&project), &RouteOptions::default(), &env, &meta, true).unwrap();
+        assert_eq!(result.output.as_deref(), Some("<a>1.50;"));
+    }
+}
@@ -0,0 +1,370 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use crate::models::ProjectData;
+use crate::project::inheritance::{ancestors, parent_map};
+use crate::project::route::{build_route, route_to_values, RouteOptions, RouteWaypoint};
+use super::context::ExportMeta;
+use super::frame::FrameTransform;
+use super::render::{encode_png, render_map, RenderSettings};
+use super::report::{build_report, render_report, ReportSettings};
+use super::templates::{TemplateEnv, TemplateLibrary};
+use super::ExportFormat;
+
+/// What a target writes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetSource {
+    /// A library template, by id
+    Template(String),
+    /// A built-in format with its settings
+    Builtin(ExportFormat),
+    /// The plain waypoint list (YAML or JSON by file extension)
+    Data,
+    /// A PNG of the waypoints drawn over the map, in the map frame
+    Image(RenderSettings),
+    /// The mission report, SVG or PDF by file extension, in the map frame
+    Report(ReportSettings),
+}
+
+/// Which waypoints a target exports. A waypoint matches a node id when it is
+/// that node or was generated under it; `exclude` wins over `include`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeFilter {
+    /// Empty means every waypoint
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl NodeFilter {
+    fn matches(&self, lineage: &[&str]) -> bool {
+        let hit = |ids: &[String]| lineage.iter().any(|id| ids.iter().any(|i| i == id));
+        (self.include.is_empty() || hit(&self.include)) && !hit(&self.exclude)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportTarget {
+    pub source: TargetSource,
+    /// Output path relative to the export directory; it may not leave it.
+    /// `{project}`, `{profile}` and `{date}` (YYYYMMDD) are substituted.
+    pub path: String,
+    #[serde(default)]
+    pub nodes: NodeFilter,
+    #[serde(default, skip_serializing_if = "FrameTransform::is_default")]
+    pub transform: FrameTransform,
+}
+
+/// A named set of exports run together, stored in the project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportProfile {
+    pub id: String,
+    pub name: String,
+    pub targets: Vec<ExportTarget>,
+}
+
+/// Expand the placeholders in `pattern` and resolve it against `base_dir`.
+pub fn output_path(pattern: &str, base_dir: &Path, project_name: &str, profile: &ExportProfile, date: &str) -> Result<PathBuf, String> {
+    let placeholder = Regex::new(r"\{(\w*)\}").expect("valid regex");
+    let mut unknown = None;
+    let expanded = placeholder.replace_all(pattern, |caps: &regex::Captures| match &caps[1] {
+        "project" => project_name.to_string(),
+        "profile" => profile.name.clone(),
+        "date" => date.to_string(),
+        other => {
+            unknown.get_or_insert_with(|| other.to_string());
+            String::new()
+        }
+    });
+    if let Some(name) = unknown {
+        return Err(format!("Unknown placeholder {{{}}} in output path: {}", name, pattern));
+    }
+    if expanded.trim().is_empty() {
+        return Err("Output path must not be empty".to_string());
+    }
+    // Profiles arrive with shared project files, so they may only write below `base_dir`
+    let relative = Path::new(expanded.as_ref());
+    if relative.components().any(|c| matches!(c, Component::RootDir | Component::Prefix(_) | Component::ParentDir)) {
+        return Err(format!("Output path must stay inside the export folder: {}", expanded));
+    }
+    Ok(base_dir.join(relative))
+}
+
+/// The target's waypoints, filtered and renumbered from `index_start`, still in the map frame.
+fn filtered_route(project: &ProjectData, target: &ExportTarget, route_options: &RouteOptions) -> Vec<RouteWaypoint> {
+    let parents = parent_map(project);
+    let mut route: Vec<RouteWaypoint> = build_route(project, route_options)
+        .into_iter()
+        .filter(|wp| {
+            let mut lineage = vec![wp.id.as_str()];
+            lineage.extend(ancestors(&parents, &wp.id));
+            target.nodes.matches(&lineage)
+        })
+        .collect();
+    for (i, wp) in route.iter_mut().enumerate() {
+        wp.index = route_options.index_start + i;
+    }
+    route
+}
+
+/// The target's waypoints: filtered, renumbered from `index_start` and moved into its frame.
+pub fn target_route(project: &ProjectData, target: &ExportTarget, route_options: &RouteOptions) -> Result<Vec<RouteWaypoint>, String> {
+    let mut route = filtered_route(project, target, route_options);
+    target.transform.apply_all(&mut route)?;
+    Ok(route)
+}
+
+pub fn render_target(
+    project: &ProjectData,
+    target: &ExportTarget,
+    path: &Path,
+    route_options: &RouteOptions,
+    library: Option<&TemplateLibrary>,
+    env: &TemplateEnv,
+    project_name: &str,
+) -> Result<Vec<u8>, String> {
+    let path_str = path.to_string_lossy();
+    let frame_id = target.transform.frame_id.clone();
+    let content = match &target.source {
+        TargetSource::Template(id) => {
+            let template = library.ok_or("Template targets need a template library")?.get(id)?;
+            let meta = ExportMeta { frame_id, ..ExportMeta::new(Some(project_name), &path_str, Some(project)) };
+            let route = target_route(project, target, route_options)?;
+            super::render_template(&template.content, &route_to_values(&route)?, &env.for_template(&template), &meta)?
+        }
+        TargetSource::Builtin(format) => format.with_frame_id(frame_id.as_deref()).render(&target_route(project, target, route_options)?)?,
+        TargetSource::Data => super::render_data(&path_str, &route_to_values(&target_route(project, target, route_options)?)?)?,
+        TargetSource::Image(_) | TargetSource::Report(_) if !target.transform.is_default() => {
+            // The map is only known in its own frame
+            return Err("Image and report targets are drawn in the map frame and take no transform".to_string());
+        }
+        TargetSource::Image(settings) => {
+            return encode_png(&render_map(project, &filtered_route(project, target, route_options), settings)?);
+        }
+        TargetSource::Report(settings) => {
+            return render_report(path, &build_report(project, filtered_route(project, target, route_options), settings, project_name)?);
+        }
+    };
+    Ok(content.into_bytes())
+}
+
+/// Write every file or none: all contents go to temporary siblings first, existing
+/// files are moved aside while the new ones are renamed into place, and put back
+/// if any rename fails.
+fn write_all(files: &[(PathBuf, Vec<u8>)]) -> Result<(), String> {
+    let sibling = |path: &Path, kind: &str| {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.{}", std::process::id(), kind));
+        path.with_file_name(name)
+    };
+    let cleanup = |files: &[(PathBuf, Vec<u8>)]| {
+        for (path, _) in files {
+            let _ = fs::remove_file(sibling(path, "tmp"));
+        }
+    };
+
+    for (path, content) in files {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(sibling(path, "tmp"), content));
+        if let Err(e) = written {
+            cleanup(files);
+            return Err(format!("File write error ({}): {}", path.display(), e));
+        }
+    }
+
+    // (path, whether an earlier version was moved aside)
+    let mut placed: Vec<(&Path, bool)> = Vec::new();
+    let mut result = Ok(());
+    for (path, _) in files {
+        let had_original = path.exists();
+        let swapped = if had_original { fs::rename(path, sibling(path, "orig")) } else { Ok(()) }
+            .and_then(|_| {
+                fs::rename(sibling(path, "tmp"), path).inspect_err(|_| {
+                    if had_original {
+                        let _ = fs::rename(sibling(path, "orig"), path);
+                    }
+                })
+            });
+        if let Err(e) = swapped {
+            result = Err(format!("File write error ({}): {}", path.display(), e));
+            break;
+        }
+        placed.push((path, had_original));
+    }
+
+    for (path, had_original) in placed.into_iter().rev() {
+        let _ = match (result.is_ok(), had_original) {
+            (true, true) => fs::remove_file(sibling(path, "orig")),
+            (true, false) => Ok(()),
+            (false, true) => fs::rename(sibling(path, "orig"), path),
+            (false, false) => fs::remove_file(path),
+        };
+    }
+    cleanup(files);
+    result
+}
+
+/// Render every target of the profile, then write them all. Nothing is written
+/// if any target fails to render. Returns the written paths in target order.
+pub fn run_profile(
+    project: &ProjectData,
+    profile_id: &str,
+    base_dir: &Path,
+    library: Option<&TemplateLibrary>,
+    env: &TemplateEnv,
+    route_options: &RouteOptions,
+    project_name: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let profile = project
+        .export_profiles
+        .iter()
+        .flatten()
+        .find(|p| p.id == profile_id)
+        .ok_or_else(|| format!("Export profile not found: {}", profile_id))?;
+    if profile.targets.is_empty() {
+        return Err(format!("Export profile '{}' has no targets", profile.name));
+    }
+
+    let date = chrono::Local::now().format("%Y%m%d").to_string();
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for (i, target) in profile.targets.iter().enumerate() {
+        let path = output_path(&target.path, base_dir, project_name, profile, &date)?;
+        if !seen.insert(path.clone()) {
+            return Err(format!("Two targets write the same file: {}", path.display()));
+        }
+        let content = render_target(project, target, &path, route_options, library, env, project_name)
+            .map_err(|e| format!("Target {} ({}): {}", i + 1, target.path, e))?;
+        files.push((path, content));
+    }
+
+    write_all(&files)?;
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ExportTemplate;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn project(profile: serde_json::Value) -> ProjectData {
+        serde_json::from_value(json!({
+            "root_node_ids": ["a", "g"],
+            "nodes": {
+                "a": { "id": "a", "type": "manual", "transform": { "x": 1.0, "y": 0.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } },
+                "g": { "id": "g", "type": "grid", "children_ids": ["g1", "g2"] },
+                "g1": { "id": "g1", "type": "manual", "transform": { "x": 2.0, "y": 0.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } },
+                "g2": { "id": "g2", "type": "manual", "transform": { "x": 3.0, "y": 0.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } },
+            },
+            "export_profiles": [profile],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_output_path_placeholders() {
+        let profile = ExportProfile { id: "p".into(), name: "deploy".into(), targets: vec![] };
+        let path = output_path("{project}/{profile}_{date}.yaml", Path::new("/out"), "site", &profile, "20261018").unwrap();
+        assert_eq!(path, PathBuf::from("/out/site/deploy_20261018.yaml"));
+        assert!(output_path("{robot}.yaml", Path::new("/out"), "site", &profile, "d").is_err());
+        for escaping in ["../../.bashrc", "/etc/cron.d/x", "nav2/../../x.yaml"] {
+            assert!(output_path(escaping, Path::new("/out"), "site", &profile, "d").is_err(), "{}", escaping);
+        }
+        // Placeholder values are checked too
+        assert!(output_path("{project}/x.yaml", Path::new("/out"), "..", &profile, "d").is_err());
+    }
+
+    #[test]
+    fn test_node_filter_and_renumbering() {
+        let p = project(json!({ "id": "p", "name": "deploy", "targets": [] }));
+        let target: ExportTarget = serde_json::from_value(json!({
+            "source": "data",
+            "path": "x.json",
+            "nodes": { "include": ["g"], "exclude": ["g2"] },
+            "transform": { "x": 10.0 },
+        }))
+        .unwrap();
+        let route = target_route(&p, &target, &RouteOptions { index_start: 1 }).unwrap();
+        assert_eq!(route.len(), 1);
+        assert_eq!((route[0].id.as_str(), route[0].index, route[0].x), ("g1", 1, 12.0));
+    }
+
+    #[test]
+    fn test_run_profile_writes_all_targets_or_none() {
+        let dir = tempdir().unwrap();
+        let library = TemplateLibrary::new(dir.path().join("templates"));
+        library
+            .save(&ExportTemplate {
+                id: "ids".into(),
+                name: "ids".into(),
+                extension: "txt".into(),
+                suffix: String::new(),
+                content: "{{project.name}}:{{#each waypoints}}{{id}} {{/each}}".into(),
+                escape_html: true,
+            })
+            .unwrap();
+        let out = dir.path().join("out");
+        let env = TemplateEnv::default();
+
+        let p = project(json!({ "id": "p", "name": "deploy", "targets": [
+            { "source": { "template": "ids" }, "path": "{project}.txt" },
+            { "source": { "builtin": { "format": "csv" } }, "path": "plc/{profile}.csv", "nodes": { "include": ["a"] } },
+            { "source": "data", "path": "fleet.json" },
+            { "source": { "image": { "scale": 10.0 } }, "path": "{project}.png" },
+            { "source": { "report": { "title": "Sign-off", "scale": 10.0 } }, "path": "{project}_report.pdf", "nodes": { "include": ["g"] } },
+        ] }));
+        let written = run_profile(&p, "p", &out, Some(&library), &env, &RouteOptions::default(), "site").unwrap();
+        assert_eq!(written.len(), 5);
+        assert_eq!(fs::read_to_string(out.join("site.txt")).unwrap(), "site:a g1 g2 ");
+        assert_eq!(fs::read_to_string(out.join("plc/deploy.csv")).unwrap(), "index,id,x,y,yaw\n0,a,1,0,0\n");
+        assert!(fs::read_to_string(out.join("fleet.json")).unwrap().contains("\"g2\""));
+        assert!(fs::read(out.join("site.png")).unwrap().starts_with(b"\x89PNG"));
+        let report = fs::read(out.join("site_report.pdf")).unwrap();
+        assert!(String::from_utf8_lossy(&report).contains("(Waypoints: 2 "));
+
+        let broken = project(json!({ "id": "p", "name": "broken", "targets": [
+            { "source": "data", "path": "first.json" },
+            { "source": { "template": "missing" }, "path": "second.txt" },
+        ] }));
+        let err = run_profile(&broken, "p", &out, Some(&library), &env, &RouteOptions::default(), "site").unwrap_err();
+        assert!(err.contains("Target 2"), "{}", err);
+        assert!(!out.join("first.json").exists());
+        assert!(run_profile(&p, "nope", &out, Some(&library), &env, &RouteOptions::default(), "site").is_err());
+    }
+
+    #[test]
+    fn test_write_all_restores_earlier_files_when_a_rename_fails() {
+        let dir = tempdir().unwrap();
+        let first = dir.path().join("first.json");
+        let second = dir.path().join("second.json");
+        let third = dir.path().join("third.json");
+        fs::write(&first, "old first").unwrap();
+        fs::write(&second, "old second").unwrap();
+        // A non-empty directory where the old second file would be moved aside
+        let blocker = dir.path().join(format!("second.json.{}.orig", std::process::id()));
+        fs::create_dir_all(blocker.join("x")).unwrap();
+
+        let files = vec![
+            (first.clone(), b"new first".to_vec()),
+            (third.clone(), b"new third".to_vec()),
+            (second.clone(), b"new second".to_vec()),
+        ];
+        assert!(write_all(&files).is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "old first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "old second");
+        assert!(!third.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3, "no temporary files are left");
+
+        fs::remove_dir_all(&blocker).unwrap();
+        write_all(&files).unwrap();
+        assert_eq!(fs::read_to_string(&second).unwrap(), "new second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+}
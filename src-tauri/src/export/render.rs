@@ -0,0 +1,530 @@
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::map::decode_image_data_url;
+use crate::models::{ProjectData, ProjectMapLayer};
+use crate::project::route::RouteWaypoint;
+
+/// Largest width or height of a rendered image, in pixels.
+pub const MAX_IMAGE_SIZE: u32 = 16384;
+/// Longer image side when neither the settings nor a map layer give a scale.
+const FIT_SIZE: f64 = 1600.0;
+
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const PATH_COLOR: Rgba<u8> = Rgba([0x94, 0xa3, 0xb8, 255]);
+const GENERATED_COLOR: Rgba<u8> = Rgba([0x22, 0xc5, 0x5e, 255]);
+const GENERATED_FILL: Rgba<u8> = Rgba([0x4a, 0xde, 0x80, 255]);
+const WAYPOINT_COLOR: Rgba<u8> = Rgba([0xff, 0xa5, 0x00, 255]);
+const WAYPOINT_FILL: Rgba<u8> = Rgba([0xff, 0xd7, 0x00, 255]);
+const ZONE_COLOR: Rgba<u8> = Rgba([0xec, 0x48, 0x99, 255]);
+const TEXT_COLOR: Rgba<u8> = Rgba([0x1e, 0x29, 0x3b, 255]);
+
+/// An axis-aligned area of the map frame, in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    fn around(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        points.into_iter().fold(None, |b: Option<Self>, (x, y)| {
+            Some(match b {
+                None => Self { min_x: x, min_y: y, max_x: x, max_y: y },
+                Some(b) => Self { min_x: b.min_x.min(x), min_y: b.min_y.min(y), max_x: b.max_x.max(x), max_y: b.max_y.max(y) },
+            })
+        })
+    }
+
+    fn expand(self, margin: f64) -> Self {
+        Self { min_x: self.min_x - margin, min_y: self.min_y - margin, max_x: self.max_x + margin, max_y: self.max_y + margin }
+    }
+}
+
+/// What waypoints are labeled with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelStyle {
+    None,
+    #[default]
+    Id,
+    /// The exported index, e.g. to match a report table
+    Index,
+}
+
+fn default_margin() -> f64 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_text_size() -> u32 {
+    2
+}
+
+fn default_marker_size() -> f64 {
+    16.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// Pixels per meter; unset uses the native resolution of the finest map layer,
+    /// or fits the longer side to 1600 pixels without a map
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Area to draw; unset covers the map layers, or the waypoints and zones plus `margin` without a map
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<Bounds>,
+    /// Meters
+    #[serde(default = "default_margin")]
+    pub margin: f64,
+    #[serde(default = "default_true")]
+    pub show_map: bool,
+    #[serde(default = "default_true")]
+    pub show_path: bool,
+    #[serde(default = "default_true")]
+    pub show_zones: bool,
+    pub labels: LabelStyle,
+    /// Pixels per font dot (labels are 5x7 dots)
+    #[serde(default = "default_text_size")]
+    pub text_size: u32,
+    /// Waypoint arrow length in pixels
+    #[serde(default = "default_marker_size")]
+    pub marker_size: f64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            scale: None,
+            crop: None,
+            margin: default_margin(),
+            show_map: true,
+            show_path: true,
+            show_zones: true,
+            labels: LabelStyle::Id,
+            text_size: default_text_size(),
+            marker_size: default_marker_size(),
+        }
+    }
+}
+
+/// A decoded map layer placed in the map frame.
+struct PlacedLayer {
+    image: RgbaImage,
+    resolution: f64,
+    origin: [f64; 3],
+    opacity: f64,
+}
+
+impl PlacedLayer {
+    fn decode(layer: &ProjectMapLayer) -> Result<Option<Self>, String> {
+        let Some(info) = &layer.info else { return Ok(None) };
+        let Some(resolution) = info.get("resolution").and_then(Value::as_f64).filter(|r| *r > 0.0) else { return Ok(None) };
+        if layer.image_base64.is_empty() {
+            return Ok(None);
+        }
+        let bytes = decode_image_data_url(&layer.image_base64)?;
+        let image = image::load_from_memory(&bytes)
+            .map_err(|e| format!("Failed to decode map layer '{}': {}", layer.name, e))?
+            .to_rgba8();
+        let origin = info.get("origin").and_then(Value::as_array).map(|o| {
+            let at = |i: usize| o.get(i).and_then(Value::as_f64).unwrap_or(0.0);
+            [at(0), at(1), at(2)]
+        });
+        Ok(Some(Self { image, resolution, origin: origin.unwrap_or_default(), opacity: layer.opacity.clamp(0.0, 1.0) }))
+    }
+
+    fn corners(&self) -> [(f64, f64); 4] {
+        let (w, h) = (self.image.width() as f64 * self.resolution, self.image.height() as f64 * self.resolution);
+        let (s, c) = self.origin[2].sin_cos();
+        [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)].map(|(lx, ly)| (self.origin[0] + lx * c - ly * s, self.origin[1] + lx * s + ly * c))
+    }
+
+    /// The pixel covering a map-frame point (image row 0 is the top edge).
+    fn sample(&self, x: f64, y: f64) -> Option<Rgba<u8>> {
+        let (s, c) = self.origin[2].sin_cos();
+        let (dx, dy) = (x - self.origin[0], y - self.origin[1]);
+        let col = ((dx * c + dy * s) / self.resolution).floor();
+        let row = self.image.height() as f64 - 1.0 - ((-dx * s + dy * c) / self.resolution).floor();
+        let inside = col >= 0.0 && row >= 0.0 && col < self.image.width() as f64 && row < self.image.height() as f64;
+        inside.then(|| *self.image.get_pixel(col as u32, row as u32))
+    }
+}
+
+fn point(value: &Value) -> Option<(f64, f64)> {
+    Some((value.get("x")?.as_f64()?, value.get("y")?.as_f64()?))
+}
+
+/// Zone outline of one generator input: a rectangle (`center`, `width`, `height`, `yaw`)
+/// or a list of at least three points, bare or under `points`.
+fn zone_polygon(input: &Value) -> Option<Vec<(f64, f64)>> {
+    if let (Some(center), Some(w), Some(h)) = (input.get("center").and_then(point), input.get("width"), input.get("height")) {
+        let (half_w, half_h) = (w.as_f64()? / 2.0, h.as_f64()? / 2.0);
+        let (s, c) = input.get("yaw").and_then(Value::as_f64).unwrap_or(0.0).sin_cos();
+        let corners = [(-half_w, -half_h), (half_w, -half_h), (half_w, half_h), (-half_w, half_h)];
+        return Some(corners.iter().map(|(lx, ly)| (center.0 + lx * c - ly * s, center.1 + lx * s + ly * c)).collect());
+    }
+    let points: Vec<(f64, f64)> = input.get("points").unwrap_or(input).as_array()?.iter().filter_map(point).collect();
+    (points.len() >= 3).then_some(points)
+}
+
+/// Zones drawn as generator inputs, in node id order.
+pub fn zone_polygons(project: &ProjectData) -> Vec<Vec<(f64, f64)>> {
+    let mut ids: Vec<&String> = project.nodes.keys().collect();
+    ids.sort();
+    ids.iter()
+        .filter_map(|id| project.nodes[*id].generator_params.as_ref()?.get("interaction_data")?.as_object())
+        .flat_map(|inputs| inputs.values().filter_map(zone_polygon))
+        .collect()
+}
+
+/// 5x7 glyph rows, most significant of the low five bits leftmost. Lowercase letters
+/// use the capitals; characters without a glyph are drawn as `?`.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// An image covering `bounds` at `scale` pixels per meter, y up.
+struct Canvas {
+    image: RgbaImage,
+    bounds: Bounds,
+    scale: f64,
+}
+
+impl Canvas {
+    fn to_px(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        ((x - self.bounds.min_x) * self.scale, (self.bounds.max_y - y) * self.scale)
+    }
+
+    fn to_world(&self, px: u32, py: u32) -> (f64, f64) {
+        (self.bounds.min_x + (px as f64 + 0.5) / self.scale, self.bounds.max_y - (py as f64 + 0.5) / self.scale)
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: Rgba<u8>, alpha: f64) {
+        if x < 0 || y < 0 || x >= self.image.width() as i64 || y >= self.image.height() as i64 {
+            return;
+        }
+        let alpha = alpha * color.0[3] as f64 / 255.0;
+        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+        for i in 0..3 {
+            pixel.0[i] = (pixel.0[i] as f64 * (1.0 - alpha) + color.0[i] as f64 * alpha).round() as u8;
+        }
+    }
+
+    /// Even-odd scanline fill of a pixel-space polygon, sampled at pixel centers.
+    fn fill_polygon(&mut self, points: &[(f64, f64)], color: Rgba<u8>, alpha: f64) {
+        let Some(area) = Bounds::around(points.iter().copied()) else { return };
+        let rows = (area.min_y.floor().max(0.0) as i64)..=(area.max_y.ceil().min(self.image.height() as f64) as i64);
+        for row in rows {
+            let yc = row as f64 + 0.5;
+            let mut xs: Vec<f64> = points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .filter(|((_, y0), (_, y1))| (*y0 <= yc) != (*y1 <= yc))
+                .map(|((x0, y0), (x1, y1))| x0 + (yc - y0) / (y1 - y0) * (x1 - x0))
+                .collect();
+            xs.sort_by(f64::total_cmp);
+            for span in xs.chunks_exact(2) {
+                let first = (span[0] - 0.5).ceil().max(0.0) as i64;
+                let last = (span[1] - 0.5).floor().min(self.image.width() as f64) as i64;
+                for col in first..=last {
+                    self.blend(col, row, color, alpha);
+                }
+            }
+        }
+    }
+
+    fn disc(&mut self, (cx, cy): (f64, f64), radius: f64, color: Rgba<u8>) {
+        for row in (cy - radius).floor() as i64..=(cy + radius).ceil() as i64 {
+            for col in (cx - radius).floor() as i64..=(cx + radius).ceil() as i64 {
+                if (col as f64 + 0.5 - cx).hypot(row as f64 + 0.5 - cy) <= radius {
+                    self.blend(col, row, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// A pixel-space segment with round ends.
+    fn line(&mut self, a: (f64, f64), b: (f64, f64), width: f64, color: Rgba<u8>) {
+        let half = width / 2.0;
+        let len = (b.0 - a.0).hypot(b.1 - a.1);
+        if len > 0.0 {
+            let (nx, ny) = (-(b.1 - a.1) / len * half, (b.0 - a.0) / len * half);
+            self.fill_polygon(&[(a.0 + nx, a.1 + ny), (b.0 + nx, b.1 + ny), (b.0 - nx, b.1 - ny), (a.0 - nx, a.1 - ny)], color, 1.0);
+        }
+        self.disc(a, half, color);
+        self.disc(b, half, color);
+    }
+
+    /// Text with its top-left corner at `(x, y)`, on a white halo so it reads over the map.
+    fn text(&mut self, x: i64, y: i64, text: &str, size: u32, color: Rgba<u8>) {
+        let size = size.max(1) as i64;
+        for (halo, color) in [(true, BACKGROUND), (false, color)] {
+            for (i, c) in text.chars().enumerate() {
+                for (row, bits) in glyph(c).iter().enumerate() {
+                    for col in (0..5).filter(|col| bits & (0x10 >> col) != 0) {
+                        let (gx, gy) = (x + (i as i64 * 6 + col) * size, y + row as i64 * size);
+                        let pad = if halo { size } else { 0 };
+                        for dy in -pad..size + pad {
+                            for dx in -pad..size + pad {
+                                self.blend(gx + dx, gy + dy, color, 1.0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What gets drawn and where, worked out before any drawing.
+struct Scene {
+    layers: Vec<PlacedLayer>,
+    zones: Vec<Vec<(f64, f64)>>,
+    bounds: Bounds,
+    scale: f64,
+    width: u32,
+    height: u32,
+}
+
+impl Scene {
+    fn new(project: &ProjectData, route: &[RouteWaypoint], settings: &RenderSettings) -> Result<Self, String> {
+        let mut visible: Vec<&ProjectMapLayer> = project.map_layers.iter().flatten().filter(|l| l.visible).collect();
+        visible.sort_by_key(|l| l.z_index);
+        let mut layers = Vec::new();
+        if settings.show_map {
+            for layer in visible {
+                layers.extend(PlacedLayer::decode(layer)?);
+            }
+        }
+        let zones = if settings.show_zones { zone_polygons(project) } else { Vec::new() };
+
+        let bounds = match settings.crop {
+            Some(crop) if crop.max_x > crop.min_x && crop.max_y > crop.min_y => crop,
+            Some(crop) => return Err(format!("Crop must have max_x > min_x and max_y > min_y: {:?}", crop)),
+            None => Bounds::around(layers.iter().flat_map(PlacedLayer::corners))
+                .or_else(|| {
+                    let points = route.iter().map(|wp| (wp.x, wp.y)).chain(zones.iter().flatten().copied());
+                    Bounds::around(points).map(|b| b.expand(settings.margin.max(0.0)))
+                })
+                .ok_or("Nothing to render: the project has no map layers or waypoints")?,
+        };
+        let (extent_x, extent_y) = (bounds.max_x - bounds.min_x, bounds.max_y - bounds.min_y);
+        let finest = layers.iter().map(|l| l.resolution).fold(f64::INFINITY, f64::min);
+        let scale = settings.scale.unwrap_or(if finest.is_finite() { 1.0 / finest } else { FIT_SIZE / extent_x.max(extent_y).max(1.0) });
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(format!("Scale must be a positive number: {}", scale));
+        }
+        // A lone waypoint with no margin still gets one pixel
+        let (width, height) = ((extent_x * scale).ceil().max(1.0), (extent_y * scale).ceil().max(1.0));
+        if width > MAX_IMAGE_SIZE as f64 || height > MAX_IMAGE_SIZE as f64 {
+            return Err(format!("Image would be {}x{} pixels; the limit is {} per side. Lower the scale or crop.", width, height, MAX_IMAGE_SIZE));
+        }
+        Ok(Self { layers, zones, bounds, scale, width: width as u32, height: height as u32 })
+    }
+}
+
+/// Width and height in pixels that `render_map` would produce.
+pub fn map_size(project: &ProjectData, route: &[RouteWaypoint], settings: &RenderSettings) -> Result<(u32, u32), String> {
+    Scene::new(project, route, settings).map(|scene| (scene.width, scene.height))
+}
+
+/// Draw the visible map layers, zones, the route polyline and a labeled arrow per
+/// waypoint. Everything is in the map frame; `route` is drawn as given.
+pub fn render_map(project: &ProjectData, route: &[RouteWaypoint], settings: &RenderSettings) -> Result<RgbaImage, String> {
+    let Scene { layers, zones, bounds, scale, width, height } = Scene::new(project, route, settings)?;
+    let mut canvas = Canvas { image: RgbaImage::from_pixel(width, height, BACKGROUND), bounds, scale };
+    for py in 0..canvas.image.height() {
+        for px in 0..canvas.image.width() {
+            let (x, y) = canvas.to_world(px, py);
+            for layer in &layers {
+                if let Some(color) = layer.sample(x, y) {
+                    canvas.blend(px as i64, py as i64, color, layer.opacity);
+                }
+            }
+        }
+    }
+
+    for zone in &zones {
+        let outline: Vec<(f64, f64)> = zone.iter().map(|&p| canvas.to_px(p)).collect();
+        canvas.fill_polygon(&outline, ZONE_COLOR, 0.1);
+        for (a, b) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+            canvas.line(*a, *b, 2.0, ZONE_COLOR);
+        }
+    }
+
+    let marker = settings.marker_size.max(4.0);
+    if settings.show_path {
+        for pair in route.windows(2) {
+            let color = if pair[0].parent_id.is_some() && pair[0].parent_id == pair[1].parent_id { GENERATED_COLOR } else { PATH_COLOR };
+            canvas.line(canvas.to_px((pair[0].x, pair[0].y)), canvas.to_px((pair[1].x, pair[1].y)), (marker / 8.0).max(1.0), color);
+        }
+    }
+
+    for wp in route {
+        let (outline, fill) = if wp.parent_id.is_some() { (GENERATED_COLOR, GENERATED_FILL) } else { (WAYPOINT_COLOR, WAYPOINT_FILL) };
+        let (px, py) = canvas.to_px((wp.x, wp.y));
+        // Pixel rows grow downwards, so the heading's y flips
+        let (dx, dy) = (wp.yaw.cos(), -wp.yaw.sin());
+        let tip = (px + dx * marker, py + dy * marker);
+        let back = (px + dx * marker * 0.55, py + dy * marker * 0.55);
+        let wing = marker * 0.25;
+        canvas.line((px, py), back, (marker / 8.0).max(1.0), outline);
+        canvas.fill_polygon(&[tip, (back.0 - dy * wing, back.1 + dx * wing), (back.0 + dy * wing, back.1 - dx * wing)], outline, 1.0);
+        canvas.disc((px, py), marker * 0.3, outline);
+        canvas.disc((px, py), marker * 0.3 - (marker / 10.0).max(1.0), fill);
+    }
+
+    if settings.labels != LabelStyle::None {
+        for wp in route {
+            let label = match settings.labels {
+                LabelStyle::Index => wp.index.to_string(),
+                _ => wp.id.clone(),
+            };
+            let (px, py) = canvas.to_px((wp.x, wp.y));
+            let offset = marker * 0.4;
+            let text_height = 7 * settings.text_size.max(1) as i64;
+            canvas.text((px + offset).round() as i64, (py - offset).round() as i64 - text_height, &label, settings.text_size, TEXT_COLOR);
+        }
+    }
+    Ok(canvas.image)
+}
+
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image to PNG: {}", e))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::encode_image_data_url;
+    use crate::project::route::{build_route, RouteOptions};
+    use serde_json::json;
+
+    fn project(map_layers: Value) -> ProjectData {
+        serde_json::from_value(json!({
+            "root_node_ids": ["a", "b", "g"],
+            "nodes": {
+                "a": { "id": "a", "type": "manual", "transform": { "x": 1.0, "y": 1.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } },
+                "b": { "id": "b", "type": "manual", "transform": { "x": 4.0, "y": 1.0, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } },
+                "g": { "id": "g", "type": "generator", "children_ids": [], "generator_params": {
+                    "properties": {},
+                    "interaction_data": {
+                        "area": { "center": { "x": 2.5, "y": 3.0 }, "width": 2.0, "height": 1.0, "yaw": 0.0 },
+                        "start_point": { "x": 0.0, "y": 0.0 },
+                    },
+                } },
+            },
+            "map_layers": map_layers,
+        }))
+        .unwrap()
+    }
+
+    fn map_layer(color: [u8; 4]) -> Value {
+        // 50x40 pixels at 0.1 m: x in [0, 5], y in [0, 4]
+        let image = image::DynamicImage::ImageRgba8(RgbaImage::from_pixel(50, 40, Rgba(color)));
+        let (data_url, _, _) = encode_image_data_url(&image).unwrap();
+        json!({ "id": "m", "name": "m", "info": { "image": "m.png", "resolution": 0.1, "origin": [0.0, 0.0, 0.0] }, "image_base64": data_url, "visible": true, "opacity": 1.0, "z_index": 0 })
+    }
+
+    #[test]
+    fn test_zone_polygons_from_generator_inputs() {
+        let mut p = project(json!([]));
+        let zones = zone_polygons(&p);
+        assert_eq!(zones, vec![vec![(1.5, 2.5), (3.5, 2.5), (3.5, 3.5), (1.5, 3.5)]]);
+
+        let params = p.nodes.get_mut("g").unwrap().generator_params.as_mut().unwrap();
+        params.insert("interaction_data".into(), json!({ "fence": { "points": [{ "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }, { "x": 0.0, "y": 1.0 }] } }));
+        assert_eq!(zone_polygons(&p)[0].len(), 3);
+    }
+
+    #[test]
+    fn test_map_layer_sets_extent_and_native_scale() {
+        let p = project(json!([map_layer([200, 200, 200, 255])]));
+        let route = build_route(&p, &RouteOptions::default());
+        let image = render_map(&p, &route, &RenderSettings { labels: LabelStyle::None, ..Default::default() }).unwrap();
+        assert_eq!(image.dimensions(), (50, 40));
+        // Map pixel far from any drawing, the zone fill and the first waypoint's marker
+        assert_eq!(*image.get_pixel(48, 20), Rgba([200, 200, 200, 255]));
+        assert_ne!(*image.get_pixel(25, 10), Rgba([200, 200, 200, 255]));
+        assert_eq!(*image.get_pixel(10, 29), WAYPOINT_FILL);
+
+        let doubled = render_map(&p, &route, &RenderSettings { scale: Some(20.0), ..Default::default() }).unwrap();
+        assert_eq!(doubled.dimensions(), (100, 80));
+        let cropped = RenderSettings { crop: Some(Bounds { min_x: 0.0, min_y: 0.0, max_x: 2.0, max_y: 2.0 }), ..Default::default() };
+        assert_eq!(render_map(&p, &route, &cropped).unwrap().dimensions(), (20, 20));
+    }
+
+    #[test]
+    fn test_without_map_frames_waypoints_and_labels() {
+        let p = project(json!([]));
+        let route = build_route(&p, &RouteOptions::default());
+        let settings = RenderSettings { scale: Some(10.0), show_zones: false, labels: LabelStyle::None, ..Default::default() };
+        let plain = render_map(&p, &route, &settings).unwrap();
+        // Waypoints span x 1..4 at y 1, plus a 1 m margin
+        assert_eq!(plain.dimensions(), (50, 20));
+        let labeled = render_map(&p, &route, &RenderSettings { labels: LabelStyle::Index, ..settings.clone() }).unwrap();
+        assert!(labeled.pixels().filter(|&&px| px == TEXT_COLOR).count() > plain.pixels().filter(|&&px| px == TEXT_COLOR).count());
+
+        let empty: ProjectData = serde_json::from_str(r#"{"root_node_ids":[],"nodes":{}}"#).unwrap();
+        assert!(render_map(&empty, &[], &RenderSettings::default()).is_err());
+        assert!(render_map(&p, &route, &RenderSettings { scale: Some(10000.0), ..settings }).is_err());
+    }
+}
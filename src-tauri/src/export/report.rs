@@ -0,0 +1,759 @@
+use base64::{Engine as _, engine::general_purpose};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::path::Path;
+use crate::models::validation::{validate_project_options, Severity};
+use crate::models::ProjectData;
+use crate::project::integrity::validate_project;
+use crate::project::route::RouteWaypoint;
+use super::render::{encode_png, map_size, render_map, LabelStyle, RenderSettings};
+
+/// A4 portrait, in points
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+const BODY_SIZE: f64 = 9.0;
+const LINE_HEIGHT: f64 = 12.0;
+/// Map pixels per printed point that the default label and marker sizes are meant for
+const PRINT_DENSITY: f64 = 2.0;
+/// Gap between pages when the SVG stacks them
+const SVG_PAGE_GAP: f64 = 20.0;
+
+fn default_title() -> String {
+    "Mission Report".to_string()
+}
+
+fn default_speed() -> f64 {
+    0.5
+}
+
+fn default_speed_option() -> String {
+    "speed".to_string()
+}
+
+/// Report options. The map settings sit at the top level, so a render settings
+/// file works as a report settings file. Waypoints are always labeled by index, and
+/// `text_size` and `marker_size` are scaled so they print at the same size on any map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportSettings {
+    #[serde(default = "default_title")]
+    pub title: String,
+    /// m/s for legs whose destination has no speed option
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    /// Waypoint option holding the speed (m/s) of the leg arriving at it
+    #[serde(default = "default_speed_option")]
+    pub speed_option: String,
+    #[serde(flatten)]
+    pub map: RenderSettings,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self { title: default_title(), speed: default_speed(), speed_option: default_speed_option(), map: RenderSettings::default() }
+    }
+}
+
+/// One leg between consecutive waypoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub from: usize,
+    pub to: usize,
+    /// Meters, 3D
+    pub distance: f64,
+    /// m/s
+    pub speed: f64,
+    /// Seconds
+    pub time: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MissionReport {
+    pub title: String,
+    pub project_name: String,
+    pub generated_at: String,
+    pub route: Vec<RouteWaypoint>,
+    pub legs: Vec<Leg>,
+    pub warnings: Vec<String>,
+    /// `None` when there is neither a map layer nor a waypoint to draw
+    pub map: Option<RgbaImage>,
+}
+
+/// Legs of the route, timed with the destination's `speed_option` or the default speed.
+pub fn plan_legs(route: &[RouteWaypoint], settings: &ReportSettings) -> Result<Vec<Leg>, String> {
+    if !settings.speed.is_finite() || settings.speed <= 0.0 {
+        return Err(format!("Speed must be a positive number: {}", settings.speed));
+    }
+    Ok(route
+        .windows(2)
+        .map(|pair| {
+            let (a, b) = (&pair[0], &pair[1]);
+            let distance = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2) + (b.z - a.z).powi(2)).sqrt();
+            let speed = b.options.get(&settings.speed_option).and_then(Value::as_f64).filter(|s| *s > 0.0).unwrap_or(settings.speed);
+            Leg { from: a.index, to: b.index, distance, speed, time: distance / speed }
+        })
+        .collect())
+}
+
+/// Structural problems and option diagnostics, one line each.
+fn collect_warnings(project: &ProjectData) -> Vec<String> {
+    let structure = validate_project(project).issues.into_iter().map(|issue| format!("Structure: {}", issue));
+    let options = validate_project_options(project).diagnostics.into_iter().map(|d| {
+        let level = if d.severity == Severity::Error { "Error" } else { "Warning" };
+        format!("{}: {} / {}: {}", level, d.node_id, d.option, d.issue)
+    });
+    structure.chain(options).collect()
+}
+
+/// Collect everything the report shows about `route`, drawing its map.
+pub fn build_report(project: &ProjectData, route: Vec<RouteWaypoint>, settings: &ReportSettings, project_name: &str) -> Result<MissionReport, String> {
+    let legs = plan_legs(&route, settings)?;
+    let has_layers = project.map_layers.as_ref().is_some_and(|layers| !layers.is_empty());
+    let map = if route.is_empty() && !has_layers {
+        None
+    } else {
+        let mut map_settings = RenderSettings { labels: LabelStyle::Index, ..settings.map.clone() };
+        let (width, height) = map_size(project, &route, &map_settings)?;
+        let (printed_width, _) = printed_size(width, height);
+        let zoom = width as f64 / printed_width / PRINT_DENSITY;
+        map_settings.text_size = ((map_settings.text_size as f64 * zoom).round() as u32).max(1);
+        map_settings.marker_size *= zoom;
+        Some(render_map(project, &route, &map_settings)?)
+    };
+    Ok(MissionReport {
+        title: settings.title.clone(),
+        project_name: project_name.to_string(),
+        generated_at: chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+        route,
+        legs,
+        warnings: collect_warnings(project),
+        map,
+    })
+}
+
+/// Size of the map on the page in points: the content width and at most half a
+/// page tall, keeping the aspect ratio.
+fn printed_size(width: u32, height: u32) -> (f64, f64) {
+    let (max_w, max_h) = (PAGE_WIDTH - 2.0 * MARGIN, PAGE_HEIGHT / 2.0);
+    let fit = (max_w / width as f64).min(max_h / height as f64);
+    (width as f64 * fit, height as f64 * fit)
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (h, m, s) = (total / 3600, total / 60 % 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn format_option(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(format_option).collect::<Vec<_>>().join("|"),
+        other => other.to_string(),
+    }
+}
+
+/// Approximate advance in ems: Helvetica averages about 0.56, Japanese glyphs are square.
+fn char_width(c: char) -> f64 {
+    if is_latin(c) { 0.56 } else { 1.0 }
+}
+
+/// Greedy word wrap to roughly `width` points; long words are split.
+fn wrap(text: &str, width: f64, size: f64) -> Vec<String> {
+    let max = width / size;
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0.0;
+    for word in text.split(' ') {
+        let word_width: f64 = word.chars().map(char_width).sum();
+        if !line.is_empty() && line_width + char_width(' ') + word_width > max {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0.0;
+        }
+        if !line.is_empty() {
+            line.push(' ');
+            line_width += char_width(' ');
+        }
+        for c in word.chars() {
+            if !line.is_empty() && line_width + char_width(c) > max {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+            }
+            line.push(c);
+            line_width += char_width(c);
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Page content in points from the top-left corner; text `y` is the baseline.
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Text { x: f64, y: f64, size: f64, bold: bool, text: String },
+    Line { x1: f64, y1: f64, x2: f64, y2: f64 },
+    /// Filled with a gray level (0 black, 1 white)
+    Rect { x: f64, y: f64, w: f64, h: f64, gray: f64 },
+    /// The report map
+    Image { x: f64, y: f64, w: f64, h: f64 },
+}
+
+/// Flows content down the pages, starting a new one when it runs out of room.
+struct Layout {
+    pages: Vec<Vec<Op>>,
+    y: f64,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self { pages: vec![Vec::new()], y: MARGIN }
+    }
+
+    fn push(&mut self, op: Op) {
+        self.pages.last_mut().expect("at least one page").push(op);
+    }
+
+    /// Start a new page unless `height` still fits. Returns whether it did.
+    fn ensure(&mut self, height: f64) -> bool {
+        // Leave room for the footer
+        if self.y + height <= PAGE_HEIGHT - MARGIN - LINE_HEIGHT {
+            return false;
+        }
+        self.pages.push(Vec::new());
+        self.y = MARGIN;
+        true
+    }
+
+    fn text(&mut self, x: f64, size: f64, bold: bool, text: &str) {
+        self.y += size * 1.3;
+        self.push(Op::Text { x, y: self.y, size, bold, text: text.to_string() });
+        self.y += size * 0.4;
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.ensure(60.0);
+        self.y += 10.0;
+        self.text(MARGIN, 13.0, true, text);
+        self.y += 4.0;
+    }
+
+    fn table(&mut self, columns: &[(&str, f64)], rows: &[Vec<String>]) {
+        let header = |layout: &mut Layout| {
+            let width = columns.iter().map(|c| c.1).sum();
+            layout.push(Op::Rect { x: MARGIN, y: layout.y, w: width, h: LINE_HEIGHT + 4.0, gray: 0.9 });
+            let mut x = MARGIN;
+            for (title, w) in columns {
+                layout.push(Op::Text { x: x + 2.0, y: layout.y + LINE_HEIGHT - 1.0, size: BODY_SIZE, bold: true, text: title.to_string() });
+                x += w;
+            }
+            layout.y += LINE_HEIGHT + 4.0;
+        };
+        self.ensure(3.0 * LINE_HEIGHT);
+        header(self);
+        for row in rows {
+            let cells: Vec<Vec<String>> = row.iter().zip(columns).map(|(text, (_, w))| wrap(text, w - 4.0, BODY_SIZE)).collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1) as f64 * LINE_HEIGHT + 4.0;
+            if self.ensure(height) {
+                header(self);
+            }
+            let mut x = MARGIN;
+            for (lines, (_, w)) in cells.iter().zip(columns) {
+                for (i, line) in lines.iter().enumerate() {
+                    self.push(Op::Text { x: x + 2.0, y: self.y + (i + 1) as f64 * LINE_HEIGHT - 1.0, size: BODY_SIZE, bold: false, text: line.clone() });
+                }
+                x += w;
+            }
+            self.y += height;
+            self.push(Op::Line { x1: MARGIN, y1: self.y, x2: x, y2: self.y });
+        }
+    }
+}
+
+impl MissionReport {
+    pub fn total_distance(&self) -> f64 {
+        self.legs.iter().map(|l| l.distance).sum()
+    }
+
+    pub fn total_time(&self) -> f64 {
+        self.legs.iter().map(|l| l.time).sum()
+    }
+
+    fn layout(&self) -> Vec<Vec<Op>> {
+        let mut layout = Layout::new();
+        layout.text(MARGIN, 18.0, true, &self.title);
+        layout.y += 4.0;
+        layout.text(MARGIN, 10.0, false, &format!("Project: {}", self.project_name));
+        layout.text(MARGIN, 10.0, false, &format!("Generated: {}", self.generated_at));
+        layout.text(
+            MARGIN,
+            10.0,
+            false,
+            &format!(
+                "Waypoints: {}    Total distance: {:.2} m    Estimated time: {}",
+                self.route.len(),
+                self.total_distance(),
+                format_duration(self.total_time())
+            ),
+        );
+        layout.y += 8.0;
+
+        if let Some(map) = &self.map {
+            let (w, h) = printed_size(map.width(), map.height());
+            layout.push(Op::Image { x: (PAGE_WIDTH - w) / 2.0, y: layout.y, w, h });
+            layout.y += h + 4.0;
+        }
+
+        layout.heading("Waypoints");
+        let rows: Vec<Vec<String>> = self
+            .route
+            .iter()
+            .map(|wp| {
+                let options: Vec<String> = wp.options.iter().map(|(k, v)| format!("{}={}", k, format_option(v))).collect();
+                vec![
+                    wp.index.to_string(),
+                    wp.id.clone(),
+                    wp.node_type.clone(),
+                    format!("{:.3}", wp.x),
+                    format!("{:.3}", wp.y),
+                    format!("{:.1}", wp.yaw.to_degrees()),
+                    options.join(", "),
+                ]
+            })
+            .collect();
+        let columns = [("No.", 30.0), ("ID", 110.0), ("Type", 60.0), ("X [m]", 50.0), ("Y [m]", 50.0), ("Yaw [deg]", 50.0), ("Options", 165.0)];
+        layout.table(&columns, &rows);
+
+        layout.heading("Legs");
+        let mut elapsed = 0.0;
+        let rows: Vec<Vec<String>> = self
+            .legs
+            .iter()
+            .enumerate()
+            .map(|(i, leg)| {
+                elapsed += leg.time;
+                vec![
+                    (i + 1).to_string(),
+                    leg.from.to_string(),
+                    leg.to.to_string(),
+                    format!("{:.2}", leg.distance),
+                    format!("{:.2}", leg.speed),
+                    format_duration(leg.time),
+                    format_duration(elapsed),
+                ]
+            })
+            .collect();
+        let columns = [("Leg", 30.0), ("From", 80.0), ("To", 80.0), ("Distance [m]", 90.0), ("Speed [m/s]", 80.0), ("Time", 75.0), ("Elapsed", 80.0)];
+        layout.table(&columns, &rows);
+
+        layout.heading("Validation");
+        if self.warnings.is_empty() {
+            layout.text(MARGIN, BODY_SIZE, false, "No warnings.");
+        }
+        for warning in &self.warnings {
+            for line in wrap(warning, PAGE_WIDTH - 2.0 * MARGIN, BODY_SIZE) {
+                layout.ensure(LINE_HEIGHT);
+                layout.text(MARGIN, BODY_SIZE, false, &line);
+            }
+        }
+
+        layout.heading("Sign-off");
+        for label in ["Approved by", "Date", "Signature"] {
+            layout.ensure(2.0 * LINE_HEIGHT + 8.0);
+            layout.y += 18.0;
+            layout.push(Op::Text { x: MARGIN, y: layout.y, size: 10.0, bold: false, text: format!("{}:", label) });
+            layout.push(Op::Line { x1: MARGIN + 80.0, y1: layout.y + 2.0, x2: MARGIN + 330.0, y2: layout.y + 2.0 });
+            layout.y += 6.0;
+        }
+
+        let count = layout.pages.len();
+        for (i, page) in layout.pages.iter_mut().enumerate() {
+            let text = format!("{} - {} - page {} / {}", self.title, self.project_name, i + 1, count);
+            page.push(Op::Text { x: MARGIN, y: PAGE_HEIGHT - MARGIN / 2.0, size: 8.0, bold: false, text });
+        }
+        layout.pages
+    }
+
+    /// All pages stacked vertically in one SVG, with the map embedded as a PNG.
+    pub fn to_svg(&self) -> Result<String, String> {
+        let map_href = match &self.map {
+            Some(map) => format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(encode_png(map)?)),
+            None => String::new(),
+        };
+        let pages = self.layout();
+        let height = pages.len() as f64 * (PAGE_HEIGHT + SVG_PAGE_GAP) - SVG_PAGE_GAP;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="Helvetica, Arial, 'Hiragino Sans', 'Yu Gothic', 'Noto Sans CJK JP', sans-serif">"#,
+            w = PAGE_WIDTH,
+            h = height
+        );
+        let _ = writeln!(svg, "<title>{}</title>", xml_escape(&self.title));
+        for (i, page) in pages.iter().enumerate() {
+            let _ = writeln!(svg, r#"<g transform="translate(0 {})">"#, i as f64 * (PAGE_HEIGHT + SVG_PAGE_GAP));
+            let _ = writeln!(svg, r##"<rect width="{}" height="{}" fill="#ffffff" stroke="#cccccc"/>"##, PAGE_WIDTH, PAGE_HEIGHT);
+            for op in page {
+                let _ = match op {
+                    Op::Text { x, y, size, bold, text } => writeln!(
+                        svg,
+                        r#"<text x="{:.1}" y="{:.1}" font-size="{}"{}>{}</text>"#,
+                        x,
+                        y,
+                        size,
+                        if *bold { r#" font-weight="bold""# } else { "" },
+                        xml_escape(text)
+                    ),
+                    Op::Line { x1, y1, x2, y2 } => writeln!(svg, r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#999999" stroke-width="0.5"/>"##, x1, y1, x2, y2),
+                    Op::Rect { x, y, w, h, gray } => {
+                        let level = (gray * 255.0).round() as u8;
+                        writeln!(svg, r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#{:02x}{:02x}{:02x}"/>"##, x, y, w, h, level, level, level)
+                    }
+                    Op::Image { x, y, w, h } => writeln!(svg, r#"<image x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" href="{}"/>"#, x, y, w, h, map_href),
+                };
+            }
+            let _ = writeln!(svg, "</g>");
+        }
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    /// A PDF 1.4 document with the map as a JPEG. Text uses the standard Helvetica fonts and,
+    /// for Japanese, the standard Adobe-Japan1 gothic font; like Helvetica it is supplied by
+    /// the viewer. Text in other scripts is an error rather than printed as `?`.
+    pub fn to_pdf(&self) -> Result<Vec<u8>, String> {
+        let pages = self.layout();
+        let mut japanese = false;
+        for text in pages.iter().flatten().filter_map(|op| match op {
+            Op::Text { text, .. } => Some(text),
+            _ => None,
+        }) {
+            if let Some(c) = text.chars().find(|&c| !is_latin(c) && !is_japanese(c)) {
+                return Err(format!("The PDF report cannot print {:?} in {:?}; export the report as SVG instead", c, text));
+            }
+            japanese |= text.chars().any(is_japanese);
+        }
+
+        let mut pdf = PdfWriter::new();
+        let font = pdf.add(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec());
+        let bold = pdf.add(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec());
+        let japanese_font = japanese.then(|| {
+            let descriptor = pdf.add(
+                format!(
+                    "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [-92 -250 1010 922] /ItalicAngle 0 /Ascent 752 /Descent -221 /CapHeight 737 /StemV 114 >>",
+                    JAPANESE_FONT
+                )
+                .into_bytes(),
+            );
+            // Half-width glyphs (CIDs 231-389) are 500 units, the rest 1000
+            let cid_font = pdf.add(
+                format!(
+                    "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 2 >> /FontDescriptor {} 0 R /DW 1000 /W [231 389 500] >>",
+                    JAPANESE_FONT, descriptor
+                )
+                .into_bytes(),
+            );
+            pdf.add(
+                format!("<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /UniJIS-UCS2-H /DescendantFonts [{} 0 R] >>", JAPANESE_FONT, cid_font)
+                    .into_bytes(),
+            )
+        });
+        let image = match &self.map {
+            Some(map) => {
+                let rgb = image::DynamicImage::ImageRgba8(map.clone()).to_rgb8();
+                let mut jpeg = Vec::new();
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 90)
+                    .encode_image(&rgb)
+                    .map_err(|e| format!("Failed to encode image to JPEG: {}", e))?;
+                let mut object = format!(
+                    "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                    rgb.width(),
+                    rgb.height(),
+                    jpeg.len()
+                )
+                .into_bytes();
+                object.extend(jpeg);
+                object.extend(b"\nendstream");
+                Some(pdf.add(object))
+            }
+            None => None,
+        };
+
+        // Pages are written after their parent, whose id is reserved up front
+        let pages_id = pdf.reserve();
+        let mut page_ids = Vec::new();
+        for page in pages {
+            let content = page_content(&page);
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend(content);
+            stream.extend(b"\nendstream");
+            let content_id = pdf.add(stream);
+            let xobject = image.map(|id| format!(" /XObject << /Im1 {} 0 R >>", id)).unwrap_or_default();
+            let f3 = japanese_font.map(|id| format!(" /F3 {} 0 R", id)).unwrap_or_default();
+            page_ids.push(pdf.add(
+                format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 {} 0 R /F2 {} 0 R{} >>{} >> /Contents {} 0 R >>",
+                    pages_id, PAGE_WIDTH, PAGE_HEIGHT, font, bold, f3, xobject, content_id
+                )
+                .into_bytes(),
+            ));
+        }
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        pdf.set(pages_id, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()).into_bytes());
+        let catalog = pdf.add(format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id).into_bytes());
+        let info = pdf.add(format!("<< /Title {} /Producer (waypoint-tool) >>", pdf_text_string(&self.title)).into_bytes());
+        Ok(pdf.finish(catalog, info))
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Printed with Helvetica's WinAnsi encoding, limited here to its Latin-1 part.
+fn is_latin(c: char) -> bool {
+    matches!(c as u32, 0x20..=0x7E | 0xA0..=0xFF)
+}
+
+/// Printed with the Japanese font: CJK punctuation, kana, kanji and full/half-width forms.
+fn is_japanese(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x30FF | 0x31F0..=0x31FF | 0x4E00..=0x9FFF | 0xFF01..=0xFF9F)
+}
+
+/// Standard Adobe-Japan1 font that PDF viewers provide without embedding
+const JAPANESE_FONT: &str = "HeiseiKakuGo-W5";
+
+/// `text` split into runs of Latin (`false`) and Japanese (`true`) characters.
+fn script_runs(text: &str) -> Vec<(bool, String)> {
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for c in text.chars() {
+        let japanese = !is_latin(c);
+        match runs.last_mut() {
+            Some((j, run)) if *j == japanese => run.push(c),
+            _ => runs.push((japanese, c.to_string())),
+        }
+    }
+    runs
+}
+
+/// A hex string of UCS-2 codes for the `UniJIS-UCS2-H` encoded font.
+fn pdf_ucs2_string(text: &str) -> String {
+    let hex: String = text.encode_utf16().map(|u| format!("{:04X}", u)).collect();
+    format!("<{}>", hex)
+}
+
+/// A document text string (e.g. the title), UTF-16 with a byte order mark when not Latin-1.
+fn pdf_text_string(text: &str) -> String {
+    if text.chars().all(is_latin) {
+        pdf_string(text)
+    } else {
+        format!("<FEFF{}>", &pdf_ucs2_string(text)[1..])
+    }
+}
+
+/// A literal PDF string in WinAnsi (Latin-1) bytes.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            // Octal escapes keep the content stream ASCII
+            c if (c as u32) >= 0xA0 && (c as u32) <= 0xFF => {
+                let _ = write!(out, "\\{:03o}", c as u32);
+            }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+fn page_content(ops: &[Op]) -> Vec<u8> {
+    // PDF's origin is the bottom-left corner
+    let flip = |y: f64| PAGE_HEIGHT - y;
+    let mut content = String::new();
+    for op in ops {
+        let _ = match op {
+            Op::Text { x, y, size, bold, text } => {
+                // Each Tj advances past its run, so the fonts can alternate within one line
+                let runs: String = script_runs(text)
+                    .iter()
+                    .map(|(japanese, run)| match (japanese, bold) {
+                        (true, _) => format!("/F3 {} Tf {} Tj ", size, pdf_ucs2_string(run)),
+                        (false, true) => format!("/F2 {} Tf {} Tj ", size, pdf_string(run)),
+                        (false, false) => format!("/F1 {} Tf {} Tj ", size, pdf_string(run)),
+                    })
+                    .collect();
+                writeln!(content, "BT {:.2} {:.2} Td {}ET", x, flip(*y), runs)
+            }
+            Op::Line { x1, y1, x2, y2 } => writeln!(content, "0.6 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S 0 G", x1, flip(*y1), x2, flip(*y2)),
+            Op::Rect { x, y, w, h, gray } => writeln!(content, "{:.2} g {:.2} {:.2} {:.2} {:.2} re f 0 g", gray, x, flip(y + h), w, h),
+            Op::Image { x, y, w, h } => writeln!(content, "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im1 Do Q", w, h, x, flip(y + h)),
+        };
+    }
+    content.into_bytes()
+}
+
+/// Numbered objects and the cross-reference table of a PDF file.
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self { objects: Vec::new() }
+    }
+
+    fn add(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    fn reserve(&mut self) -> usize {
+        self.add(Vec::new())
+    }
+
+    fn set(&mut self, id: usize, body: Vec<u8>) {
+        self.objects[id - 1] = body;
+    }
+
+    fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend(body);
+            out.extend(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            info,
+            xref
+        );
+        out.extend(table.as_bytes());
+        out
+    }
+}
+
+/// The report as SVG or PDF, chosen by the extension of `path`.
+pub fn render_report(path: &Path, report: &MissionReport) -> Result<Vec<u8>, String> {
+    match path.extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("svg") => Ok(report.to_svg()?.into_bytes()),
+        Some("pdf") => report.to_pdf(),
+        _ => Err(format!("Report path must end in .svg or .pdf: {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::route::{build_route, RouteOptions};
+    use serde_json::json;
+
+    fn project(count: usize) -> ProjectData {
+        let mut nodes = serde_json::Map::new();
+        let ids: Vec<String> = (0..count).map(|i| format!("wp<{}>", i)).collect();
+        for (i, id) in ids.iter().enumerate() {
+            nodes.insert(id.clone(), json!({ "id": id, "type": "manual", "transform": { "x": i as f64 * 3.0, "y": i as f64 * 4.0 } }));
+        }
+        // The second waypoint is approached at 1 m/s
+        nodes[&ids[1]]["options"] = json!({ "speed": 1.0, "dock": "A" });
+        serde_json::from_value(json!({ "root_node_ids": ids, "nodes": nodes, "options_schema": { "options": [{ "name": "speed", "type": "float", "max": 2.0 }] } })).unwrap()
+    }
+
+    #[test]
+    fn test_legs_times_and_warnings() {
+        let mut p = project(3);
+        p.root_node_ids.push("ghost".into());
+        let report = build_report(&p, build_route(&p, &RouteOptions { index_start: 1 }), &ReportSettings::default(), "site").unwrap();
+        assert_eq!(report.legs.len(), 2);
+        assert_eq!((report.legs[0].from, report.legs[0].to, report.legs[0].distance), (1, 2, 5.0));
+        assert_eq!((report.legs[0].speed, report.legs[0].time), (1.0, 5.0));
+        assert_eq!((report.legs[1].speed, report.legs[1].time), (0.5, 10.0));
+        assert_eq!((report.total_distance(), format_duration(report.total_time())), (10.0, "0:15".to_string()));
+        assert_eq!(format_duration(3725.0), "1:02:05");
+        assert!(report.warnings.iter().any(|w| w == "Structure: root 'ghost' has no node"), "{:?}", report.warnings);
+        assert!(report.warnings.iter().any(|w| w.contains("dock")), "{:?}", report.warnings);
+        assert!(build_report(&p, build_route(&p, &RouteOptions::default()), &ReportSettings { speed: 0.0, ..Default::default() }, "site").is_err());
+    }
+
+    #[test]
+    fn test_svg_report_embeds_map_and_escapes_text() {
+        let p = project(3);
+        let report = build_report(&p, build_route(&p, &RouteOptions::default()), &ReportSettings { title: "Site A & B".into(), ..Default::default() }, "site").unwrap();
+        let svg = report.to_svg().unwrap();
+        assert!(svg.contains("<title>Site A &amp; B</title>"));
+        assert!(svg.contains("href=\"data:image/png;base64,"));
+        assert!(svg.contains("wp&lt;1&gt;"));
+        assert!(svg.contains("dock=A, speed=1"));
+        assert!(svg.contains("No warnings.") == report.warnings.is_empty());
+        assert!(render_report(Path::new("report.txt"), &report).is_err());
+    }
+
+    #[test]
+    fn test_pdf_report_structure_and_pagination() {
+        let settings = ReportSettings { map: RenderSettings { scale: Some(1.0), ..Default::default() }, ..Default::default() };
+        let p = project(120);
+        let report = build_report(&p, build_route(&p, &RouteOptions::default()), &settings, "site").unwrap();
+        let pdf = render_report(Path::new("report.PDF"), &report).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Filter /DCTDecode"));
+
+        // Every xref entry points at its object
+        let xref = text.rfind("startxref\n").unwrap();
+        let start: usize = text[xref + 10..].lines().next().unwrap().parse().unwrap();
+        let entries: Vec<&str> = text[start..].lines().skip(3).take_while(|l| l.ends_with(" n ")).collect();
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()), "object {}", i + 1);
+        }
+        let pages = text.matches("/Type /Page ").count();
+        assert!(pages >= 3, "{}", pages);
+        assert!(text.contains(&format!("/Count {}", pages)));
+        assert!(text.contains(&format!("page {} / {}", pages, pages)));
+        assert_eq!(pdf_string("a(b)\\ é"), "(a\\(b\\)\\\\ \\351)");
+        assert!(!text.contains("/F3"), "no Japanese font without Japanese text");
+    }
+
+    #[test]
+    fn test_pdf_report_prints_japanese_and_rejects_other_scripts() {
+        let p = project(2);
+        let settings = ReportSettings { title: "点検ルート".into(), ..Default::default() };
+        let report = build_report(&p, build_route(&p, &RouteOptions::default()), &settings, "倉庫A").unwrap();
+        let pdf = report.to_pdf().unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Encoding /UniJIS-UCS2-H"));
+        // "Project: " in Helvetica, then 倉庫 in the Japanese font, then "A"
+        assert!(text.contains("/F1 10 Tf (Project: ) Tj /F3 10 Tf <50095EAB> Tj /F1 10 Tf (A) Tj"));
+        assert!(text.contains("/Title <FEFF70B9691C30EB30FC30C8>"));
+        assert_eq!(wrap("倉庫倉庫倉庫", 3.0 * BODY_SIZE, BODY_SIZE), vec!["倉庫倉", "庫倉庫"]);
+
+        let report = build_report(&p, build_route(&p, &RouteOptions::default()), &ReportSettings::default(), "창고").unwrap();
+        let err = report.to_pdf().unwrap_err();
+        assert!(err.contains("'창'") && err.contains("SVG"), "{}", err);
+        assert!(report.to_svg().unwrap().contains("창고"));
+    }
+}
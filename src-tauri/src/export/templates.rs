@@ -0,0 +1,369 @@
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::io::write_atomic;
+use crate::models::ExportTemplate;
+
+pub const TEMPLATE_EXTENSION: &str = "hbs";
+const PARTIALS_DIR: &str = "partials";
+
+/// Helpers, partials and escaping for a template render.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateEnv {
+    /// User helpers: name -> Handlebars snippet (see `helpers::TemplateHelper`)
+    pub helpers: BTreeMap<String, String>,
+    /// Partials included with `{{> name}}`
+    pub partials: BTreeMap<String, String>,
+    /// The rendered template's `ExportTemplate::escape_html`
+    pub escape_html: bool,
+}
+
+impl Default for TemplateEnv {
+    fn default() -> Self {
+        TemplateEnv { helpers: BTreeMap::new(), partials: BTreeMap::new(), escape_html: crate::models::default_escape_html() }
+    }
+}
+
+impl TemplateEnv {
+    /// This environment with the escaping `template` asks for.
+    pub fn for_template(&self, template: &ExportTemplate) -> TemplateEnv {
+        TemplateEnv { escape_html: template.escape_html, ..self.clone() }
+    }
+
+    pub fn registry(&self) -> Result<Handlebars<'static>, String> {
+        let mut reg = super::helpers::export_registry(&self.helpers, self.escape_html)?;
+        for (name, content) in &self.partials {
+            reg.register_partial(name, content)
+                .map_err(|e| format!("Partial '{}' template error: {}", name, e))?;
+        }
+        Ok(reg)
+    }
+}
+
+/// Metadata at the top of a template file, between `---` lines.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FrontMatter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extension: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    escape_html: Option<bool>,
+}
+
+/// Split off a YAML front-matter block. Files without one are all body.
+fn split_front_matter(content: &str) -> Result<(FrontMatter, &str), String> {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return Ok((FrontMatter::default(), content));
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let meta = serde_yaml::from_str::<Option<FrontMatter>>(&rest[..offset])
+                .map_err(|e| format!("Front matter error: {}", e))?
+                .unwrap_or_default();
+            return Ok((meta, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Err("Front matter error: missing closing '---'".to_string())
+}
+
+/// Parse a template file. `stem` (the file name) is the fallback id and name.
+pub fn parse_template_file(content: &str, stem: &str) -> Result<ExportTemplate, String> {
+    let (meta, body) = split_front_matter(content)?;
+    let id = meta.id.unwrap_or_else(|| stem.to_string());
+    Ok(ExportTemplate {
+        name: meta.name.unwrap_or_else(|| id.clone()),
+        id,
+        extension: meta.extension.unwrap_or_else(|| "txt".to_string()),
+        suffix: meta.suffix.unwrap_or_default(),
+        content: body.to_string(),
+        escape_html: meta.escape_html.unwrap_or_else(crate::models::default_escape_html),
+    })
+}
+
+pub fn render_template_file(template: &ExportTemplate) -> Result<String, String> {
+    let meta = FrontMatter {
+        id: Some(template.id.clone()),
+        name: Some(template.name.clone()),
+        extension: Some(template.extension.clone()),
+        suffix: Some(template.suffix.clone()),
+        // Only the non-default setting is written, keeping older files unchanged
+        escape_html: (!template.escape_html).then_some(false),
+    };
+    let yaml = serde_yaml::to_string(&meta).map_err(|e| format!("YAML serialization error: {}", e))?;
+    Ok(format!("---\n{}---\n{}", yaml, template.content))
+}
+
+fn hbs_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Directory read error: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == TEMPLATE_EXTENSION))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// File name (without extension) for a template id or partial name. Characters
+/// outside letters, digits, `-`, `_` and `.` become `_`.
+fn file_stem(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+fn stem_of(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// A library file that could not be read as a template.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedTemplate {
+    pub file: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TemplateListing {
+    /// Sorted by name
+    pub templates: Vec<ExportTemplate>,
+    pub skipped: Vec<SkippedTemplate>,
+}
+
+/// Export templates stored as `<id>.hbs` files with front matter, plus
+/// `partials/<name>.hbs` available to every template as `{{> name}}`.
+pub struct TemplateLibrary {
+    dir: PathBuf,
+}
+
+impl TemplateLibrary {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn template_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", file_stem(id), TEMPLATE_EXTENSION))
+    }
+
+    /// File holding the template with `id`. Files copied in by hand may have a stem
+    /// other than their front-matter id.
+    fn find_file(&self, id: &str) -> Result<Option<PathBuf>, String> {
+        Ok(hbs_files(&self.dir)?.into_iter().find(|path| {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|content| parse_template_file(&content, &stem_of(path)).ok())
+                .is_some_and(|t| t.id == id)
+        }))
+    }
+
+    fn partial_path(&self, name: &str) -> PathBuf {
+        self.dir.join(PARTIALS_DIR).join(format!("{}.{}", file_stem(name), TEMPLATE_EXTENSION))
+    }
+
+    fn ensure_dir(dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create template directory: {}", e))
+    }
+
+    /// All templates, sorted by name. Broken files are returned in `skipped` so one
+    /// bad template doesn't hide the rest.
+    pub fn list(&self) -> Result<TemplateListing, String> {
+        let mut listing = TemplateListing::default();
+        for path in hbs_files(&self.dir)? {
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| format!("File read error: {}", e))
+                .and_then(|content| parse_template_file(&content, &stem_of(&path)));
+            match parsed {
+                Ok(template) => listing.templates.push(template),
+                Err(error) => listing.skipped.push(SkippedTemplate { file: path.display().to_string(), error }),
+            }
+        }
+        listing.templates.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        Ok(listing)
+    }
+
+    pub fn get(&self, id: &str) -> Result<ExportTemplate, String> {
+        self.list()?
+            .templates
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Template not found: {}", id))
+    }
+
+    pub fn save(&self, template: &ExportTemplate) -> Result<(), String> {
+        if template.id.trim().is_empty() {
+            return Err("Template id must not be empty".to_string());
+        }
+        // Otherwise two ids could share a file name and overwrite each other
+        if file_stem(&template.id) != template.id {
+            return Err(format!("Template id may only contain letters, digits, '-', '_' and '.': {:?}", template.id));
+        }
+        let path = match self.find_file(&template.id)? {
+            Some(path) => path,
+            None => {
+                let path = self.template_path(&template.id);
+                if path.exists() {
+                    return Err(format!("{} already holds another template", path.display()));
+                }
+                path
+            }
+        };
+        Self::ensure_dir(&self.dir)?;
+        write_atomic(&path, render_template_file(template)?.as_bytes())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        if let Some(path) = self.find_file(id)? {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete template: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Copy a template file into the library. Its front matter (or file name) supplies the metadata.
+    pub fn import(&self, path: &Path) -> Result<ExportTemplate, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("File read error: {}", e))?;
+        let template = parse_template_file(&content, &stem_of(path))?;
+        super::preview::check_template(&template.content).map_err(|e| {
+            format!("Template error at {}:{}: {}", e.line.unwrap_or(0), e.column.unwrap_or(0), e.message)
+        })?;
+        self.save(&template)?;
+        Ok(template)
+    }
+
+    /// Write a template with its front matter so it can be imported elsewhere.
+    pub fn export(&self, id: &str, dest: &Path) -> Result<(), String> {
+        let template = self.get(id)?;
+        write_atomic(dest, render_template_file(&template)?.as_bytes())
+    }
+
+    pub fn partials(&self) -> Result<BTreeMap<String, String>, String> {
+        hbs_files(&self.dir.join(PARTIALS_DIR))?
+            .into_iter()
+            .map(|path| {
+                let content = fs::read_to_string(&path).map_err(|e| format!("File read error: {}", e))?;
+                Ok((stem_of(&path), content))
+            })
+            .collect()
+    }
+
+    pub fn save_partial(&self, name: &str, content: &str) -> Result<(), String> {
+        if name.is_empty() || file_stem(name) != name {
+            return Err(format!("Invalid partial name: {:?}", name));
+        }
+        handlebars::Template::compile(content).map_err(|e| format!("Partial '{}' template error: {}", name, e))?;
+        Self::ensure_dir(&self.dir.join(PARTIALS_DIR))?;
+        write_atomic(&self.partial_path(name), content.as_bytes())
+    }
+
+    pub fn delete_partial(&self, name: &str) -> Result<(), String> {
+        let path = self.partial_path(name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete partial: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Library partials together with the given user helpers.
+    pub fn env(&self, helpers: BTreeMap<String, String>) -> Result<TemplateEnv, String> {
+        Ok(TemplateEnv { helpers, partials: self.partials()?, ..Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn template(id: &str, content: &str) -> ExportTemplate {
+        ExportTemplate {
+            id: id.to_string(),
+            name: format!("{} template", id),
+            extension: "yaml".to_string(),
+            suffix: "_nav".to_string(),
+            content: content.to_string(),
+            escape_html: true,
+        }
+    }
+
+    #[test]
+    fn test_front_matter_roundtrip() {
+        let t = template("nav", "---\n{{#each waypoints}}- {{id}}\n{{/each}}");
+        let file = render_template_file(&t).unwrap();
+        assert!(file.starts_with("---\nid: nav\n"));
+        let parsed = parse_template_file(&file, "ignored").unwrap();
+        assert_eq!(parsed.id, "nav");
+        assert_eq!(parsed.suffix, "_nav");
+        assert_eq!(parsed.content, t.content);
+        assert!(!file.contains("escape_html") && parsed.escape_html);
+
+        let raw = ExportTemplate { escape_html: false, ..t };
+        let file = render_template_file(&raw).unwrap();
+        assert!(file.contains("escape_html: false\n"));
+        assert!(!parse_template_file(&file, "ignored").unwrap().escape_html);
+
+        let plain = parse_template_file("{{id}}", "robot_a").unwrap();
+        assert_eq!((plain.id.as_str(), plain.name.as_str(), plain.extension.as_str()), ("robot_a", "robot_a", "txt"));
+        assert!(plain.escape_html, "templates escape like the baseline unless they opt out");
+        assert!(parse_template_file("---\nname: x\n{{id}}", "t").is_err());
+    }
+
+    #[test]
+    fn test_library_save_list_import_export() {
+        let dir = tempdir().unwrap();
+        let lib = TemplateLibrary::new(dir.path().join("templates"));
+        assert!(lib.list().unwrap().templates.is_empty());
+
+        lib.save(&template("b", "B")).unwrap();
+        lib.save(&template("a_.._x", "A")).unwrap();
+        // Would share a_.._x.hbs
+        assert!(lib.save(&template("a/../x", "A")).is_err());
+        fs::write(dir.path().join("templates").join("broken.hbs"), "---\nname: [\n---\n").unwrap();
+        // Copied in by hand under another name
+        fs::write(dir.path().join("templates").join("copy.hbs"), "---\nid: c\n---\nC").unwrap();
+        let listing = lib.list().unwrap();
+        let ids: Vec<String> = listing.templates.into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec!["a_.._x", "b", "c"]);
+        assert_eq!(listing.skipped.len(), 1);
+        assert!(listing.skipped[0].file.ends_with("broken.hbs"));
+
+        let shared = dir.path().join("shared.hbs");
+        lib.export("b", &shared).unwrap();
+        lib.delete("b").unwrap();
+        assert!(lib.get("b").is_err());
+        assert_eq!(lib.import(&shared).unwrap().content, "B");
+        assert_eq!(lib.get("b").unwrap().suffix, "_nav");
+
+        fs::write(&shared, "{{#each waypoints}}").unwrap();
+        assert!(lib.import(&shared).is_err());
+
+        lib.save(&template("c", "C2")).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("templates").join("copy.hbs")).unwrap().lines().last(), Some("C2"));
+        lib.delete("c").unwrap();
+        assert!(lib.get("c").is_err());
+    }
+
+    #[test]
+    fn test_partials_are_included() {
+        let dir = tempdir().unwrap();
+        let lib = TemplateLibrary::new(dir.path());
+        lib.save_partial("header", "# frame: {{frame}}\n").unwrap();
+        assert!(lib.save_partial("../evil", "x").is_err());
+
+        let reg = lib.env(BTreeMap::new()).unwrap().registry().unwrap();
+        let out = reg.render_template("{{> header frame=\"map\"}}{{#each waypoints}}{{id}}{{/each}}", &json!({ "waypoints": [{ "id": "a" }] })).unwrap();
+        assert_eq!(out, "# frame: map\na");
+    }
+}
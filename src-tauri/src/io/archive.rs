@@ -0,0 +1,534 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::map;
+use crate::models::options::OptionsSchema;
+use crate::models::{ExportTemplate, MapInfo, ProjectData};
+use crate::plugins::manager::PluginManager;
+use crate::plugins::models::PluginInstance;
+
+/// Layout version of the `.wptz` container itself (independent of the project format version).
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const PROJECT_ENTRY: &str = "project.wptroj";
+const SCHEMA_ENTRY: &str = "options_schema.yaml";
+
+// Folders that are build or cache output rather than part of a plugin
+const SKIPPED_PLUGIN_DIRS: &[&str] = &["__pycache__", ".git", "target", "node_modules"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveManifest {
+    format_version: u32,
+    /// Map layer id -> YAML entry inside the archive
+    #[serde(default)]
+    maps: BTreeMap<String, String>,
+    #[serde(default)]
+    templates: Vec<String>,
+    /// Plugin ids, each stored under `plugins/<entry_name(id)>/`
+    #[serde(default)]
+    plugins: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ArchiveSaveOptions {
+    #[serde(default)]
+    pub options_schema: Option<OptionsSchema>,
+    #[serde(default)]
+    pub templates: Vec<ExportTemplate>,
+    /// Original map YAML path per layer id. Layers without one are rebuilt from the embedded image.
+    #[serde(default)]
+    pub map_sources: HashMap<String, String>,
+    /// Installed plugins available for pinning. Only those used by generator nodes are copied.
+    #[serde(default)]
+    pub plugins: Vec<PluginInstance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadedArchive {
+    pub project: ProjectData,
+    pub options_schema: Option<OptionsSchema>,
+    pub templates: Vec<ExportTemplate>,
+    /// Ids of the plugins pinned in the archive
+    pub pinned_plugins: Vec<String>,
+    /// Pinned plugins extracted from the archive; empty unless extraction was requested
+    pub plugins: Vec<PluginInstance>,
+}
+
+pub(crate) fn entry_name(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+/// `name`, or `name-2`, `name-3`, ... when an earlier entry already took it.
+fn unique_entry(used: &mut HashSet<String>, name: String) -> String {
+    let mut unique = name.clone();
+    let mut n = 1;
+    while !used.insert(unique.clone()) {
+        n += 1;
+        unique = format!("{}-{}", name, n);
+    }
+    unique
+}
+
+struct ArchiveWriter {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    options: SimpleFileOptions,
+}
+
+impl ArchiveWriter {
+    fn new() -> Self {
+        Self {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            options: SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+        }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        self.zip.start_file(name, self.options)
+            .map_err(|e| format!("Archive write error: {}", e))?;
+        self.zip.write_all(data)
+            .map_err(|e| format!("Archive write error: {}", e))
+    }
+
+    fn add_dir_recursive(&mut self, dir: &Path, prefix: &str) -> Result<(), String> {
+        let mut entries: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .flatten()
+            .collect();
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if path.is_dir() {
+                if !SKIPPED_PLUGIN_DIRS.contains(&name.as_str()) {
+                    self.add_dir_recursive(&path, &format!("{}{}/", prefix, name))?;
+                }
+            } else {
+                let data = fs::read(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                self.add(&format!("{}{}", prefix, name), &data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<u8>, String> {
+        let cursor = self.zip.finish().map_err(|e| format!("Archive write error: {}", e))?;
+        Ok(cursor.into_inner())
+    }
+}
+
+/// Store an original map YAML and its image under `dir`, returning the YAML entry name.
+fn add_map_from_source(writer: &mut ArchiveWriter, dir: &str, yaml_path: &str) -> Result<String, String> {
+    let yaml_path = Path::new(yaml_path);
+    let yaml_content = fs::read_to_string(yaml_path)
+        .map_err(|e| format!("Failed to read map YAML {}: {}", yaml_path.display(), e))?;
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        .map_err(|e| format!("Failed to parse map YAML {}: {}", yaml_path.display(), e))?;
+    let info: MapInfo = serde_yaml::from_value(yaml.clone())
+        .map_err(|e| format!("Failed to parse map YAML {}: {}", yaml_path.display(), e))?;
+
+    let image_path = yaml_path.parent().unwrap_or(Path::new("")).join(&info.image);
+    let image_name = image_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let image_data = fs::read(&image_path)
+        .map_err(|e| format!("Failed to read map image {}: {}", image_path.display(), e))?;
+
+    // Keep the YAML byte-for-byte unless the image lives outside the YAML's folder
+    let yaml_bytes = if info.image == image_name {
+        yaml_content.into_bytes()
+    } else {
+        yaml["image"] = serde_yaml::Value::from(image_name.clone());
+        serde_yaml::to_string(&yaml)
+            .map_err(|e| format!("YAML serialization error: {}", e))?
+            .into_bytes()
+    };
+
+    let yaml_name = yaml_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let yaml_entry = format!("{}{}", dir, entry_name(&yaml_name));
+    writer.add(&yaml_entry, &yaml_bytes)?;
+    writer.add(&format!("{}{}", dir, image_name), &image_data)?;
+    Ok(yaml_entry)
+}
+
+/// Rebuild map files from a layer's embedded `info` and base64 image.
+fn add_map_from_layer(writer: &mut ArchiveWriter, dir: &str, info: &serde_json::Value, image_b64: &str) -> Result<String, String> {
+    let image_data = map::decode_image_data_url(image_b64)?;
+    let mut info = info.clone();
+    info["image"] = serde_json::Value::from("map.png");
+    let yaml = serde_yaml::to_string(&info)
+        .map_err(|e| format!("YAML serialization error: {}", e))?;
+
+    let yaml_entry = format!("{}map.yaml", dir);
+    writer.add(&yaml_entry, yaml.as_bytes())?;
+    writer.add(&format!("{}map.png", dir), &image_data)?;
+    Ok(yaml_entry)
+}
+
+pub fn save_project_archive(path: &str, project: &ProjectData, options: &ArchiveSaveOptions) -> Result<(), String> {
+    let mut writer = ArchiveWriter::new();
+    let mut manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        maps: BTreeMap::new(),
+        templates: Vec::new(),
+        plugins: Vec::new(),
+    };
+    let mut project_json = serde_json::to_value(project)
+        .map_err(|e| format!("Serialization error: {}", e))?;
+
+    // Distinct ids can sanitize to the same entry name; the manifest maps each id to its own entry
+    let mut map_dirs = HashSet::new();
+    for (i, layer) in project.map_layers.iter().flatten().enumerate() {
+        let dir = format!("maps/{}/", unique_entry(&mut map_dirs, entry_name(&layer.id)));
+        let yaml_entry = if let Some(source) = options.map_sources.get(&layer.id) {
+            add_map_from_source(&mut writer, &dir, source)?
+        } else if let (Some(info), false) = (&layer.info, layer.image_base64.is_empty()) {
+            add_map_from_layer(&mut writer, &dir, info, &layer.image_base64)?
+        } else {
+            // Nothing to store as files; the layer stays inline in the project JSON
+            continue;
+        };
+        manifest.maps.insert(layer.id.clone(), yaml_entry);
+        project_json["map_layers"][i]["image_base64"] = serde_json::Value::from("");
+    }
+
+    // The project's own schema also travels as a standalone YAML for sharing
+    if let Some(schema) = options.options_schema.as_ref().or(project.options_schema.as_ref()) {
+        let yaml = serde_yaml::to_string(schema)
+            .map_err(|e| format!("YAML serialization error: {}", e))?;
+        writer.add(SCHEMA_ENTRY, yaml.as_bytes())?;
+    }
+
+    let mut template_names = HashSet::new();
+    for template in &options.templates {
+        let entry = format!("templates/{}.json", unique_entry(&mut template_names, entry_name(&template.id)));
+        let json = serde_json::to_string_pretty(template)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+        writer.add(&entry, json.as_bytes())?;
+        manifest.templates.push(entry);
+    }
+
+    let used_plugins: HashSet<&String> = project.nodes.values()
+        .filter_map(|n| n.plugin_id.as_ref())
+        .collect();
+    let mut plugin_entries: HashMap<String, &String> = HashMap::new();
+    for plugin in options.plugins.iter().filter(|p| used_plugins.contains(&p.id)) {
+        let entry = entry_name(&plugin.id);
+        if let Some(other) = plugin_entries.insert(entry.clone(), &plugin.id) {
+            return Err(format!("Plugins {} and {} would share the archive folder plugins/{}/", other, plugin.id, entry));
+        }
+        writer.add_dir_recursive(Path::new(&plugin.folder_path), &format!("plugins/{}/", entry))?;
+        manifest.plugins.push(plugin.id.clone());
+    }
+
+    let project_str = super::canonical::to_canonical_string(&project_json)?;
+    writer.add(PROJECT_ENTRY, project_str.as_bytes())?;
+
+    let manifest_str = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Serialization error: {}", e))?;
+    writer.add(MANIFEST_ENTRY, manifest_str.as_bytes())?;
+
+    super::write_atomic(Path::new(path), &writer.finish()?)
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<Vec<u8>, String> {
+    let mut file = archive.by_name(name)
+        .map_err(|e| format!("Archive entry {} is missing: {}", name, e))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)
+        .map_err(|e| format!("Archive read error ({}): {}", name, e))?;
+    Ok(data)
+}
+
+fn read_entry_string(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<String, String> {
+    String::from_utf8(read_entry(archive, name)?)
+        .map_err(|e| format!("Archive entry {} is not UTF-8: {}", name, e))
+}
+
+/// Extract everything under `plugins/<entry_name(id)>/` into `target_dir/<id>/`.
+/// The folder name is the plugin id, as for installed plugins.
+fn extract_plugin(archive: &mut ZipArchive<Cursor<Vec<u8>>>, id: &str, target_dir: &Path) -> Result<(), String> {
+    let mut components = Path::new(id).components();
+    if !matches!((components.next(), components.next()), (Some(std::path::Component::Normal(_)), None)) {
+        return Err(format!("Invalid pinned plugin id: {:?}", id));
+    }
+    let plugin_dir = target_dir.join(id);
+    if plugin_dir.exists() {
+        fs::remove_dir_all(&plugin_dir)
+            .map_err(|e| format!("Failed to replace pinned plugin {}: {}", id, e))?;
+    }
+
+    let prefix = Path::new("plugins").join(entry_name(id));
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| format!("Archive read error: {}", e))?;
+        // `enclosed_name` rejects absolute paths and `..` components
+        let Some(name) = file.enclosed_name() else { continue };
+        let Ok(relative) = name.strip_prefix(&prefix) else { continue };
+        if file.is_dir() {
+            continue;
+        }
+        let out_path = plugin_dir.join(relative);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).map_err(|e| format!("Archive read error: {}", e))?;
+        fs::write(&out_path, data)
+            .map_err(|e| format!("Failed to write {}: {}", out_path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Open a `.wptz` package. Pinned plugins are executable code, so they are only
+/// extracted (below `plugin_extract_dir`) when the caller passes one.
+pub fn load_project_archive(path: &str, plugin_extract_dir: Option<&Path>) -> Result<LoadedArchive, String> {
+    let bytes = fs::read(path).map_err(|e| format!("File read error: {}", e))?;
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("Not a valid project archive: {}", e))?;
+
+    let manifest: ArchiveManifest = serde_json::from_str(&read_entry_string(&mut archive, MANIFEST_ENTRY)?)
+        .map_err(|e| format!("Failed to parse archive manifest: {}", e))?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "Archive format version {} is newer than the supported version {}. Please update the tool to open this file.",
+            manifest.format_version, ARCHIVE_FORMAT_VERSION
+        ));
+    }
+
+    let mut project = super::parse_project(&read_entry_string(&mut archive, PROJECT_ENTRY)?)?;
+
+    for layer in project.map_layers.iter_mut().flatten() {
+        let Some(yaml_entry) = manifest.maps.get(&layer.id) else { continue };
+        let yaml = read_entry_string(&mut archive, yaml_entry)?;
+        let info: MapInfo = serde_yaml::from_str(&yaml)
+            .map_err(|e| format!("Failed to parse map YAML {}: {}", yaml_entry, e))?;
+        let dir = yaml_entry.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+        let image_data = read_entry(&mut archive, &format!("{}/{}", dir, info.image))?;
+        let img = image::load_from_memory(&image_data)
+            .map_err(|e| format!("Failed to decode map image for layer {}: {}", layer.id, e))?;
+        let (data_url, _, _) = map::encode_image_data_url(&img)?;
+        layer.image_base64 = data_url;
+    }
+
+    let options_schema: Option<OptionsSchema> = if archive.index_for_name(SCHEMA_ENTRY).is_some() {
+        let yaml = read_entry_string(&mut archive, SCHEMA_ENTRY)?;
+        Some(serde_yaml::from_str(&yaml).map_err(|e| format!("Failed to parse schema YAML: {}", e))?)
+    } else {
+        None
+    };
+    // Archives written before schemas lived in the project only carry the YAML entry
+    if project.options_schema.is_none() {
+        project.options_schema = options_schema.clone();
+    }
+
+    let mut templates = Vec::new();
+    for entry in &manifest.templates {
+        let template: ExportTemplate = serde_json::from_str(&read_entry_string(&mut archive, entry)?)
+            .map_err(|e| format!("Failed to parse template {}: {}", entry, e))?;
+        templates.push(template);
+    }
+
+    let mut plugins = Vec::new();
+    if let (Some(extract_dir), false) = (plugin_extract_dir, manifest.plugins.is_empty()) {
+        for id in &manifest.plugins {
+            extract_plugin(&mut archive, id, extract_dir)?;
+        }
+        plugins = PluginManager::scan_plugins_in_dir(extract_dir, false)
+            .into_iter()
+            .filter(|p| manifest.plugins.contains(&p.id))
+            .collect();
+    }
+
+    Ok(LoadedArchive { project, options_schema, templates, pinned_plugins: manifest.plugins, plugins })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectMapLayer, WaypointNode};
+    use image::{ImageBuffer, Luma};
+    use tempfile::tempdir;
+
+    fn sample_project() -> ProjectData {
+        let mut nodes = HashMap::new();
+        nodes.insert("gen".to_string(), WaypointNode {
+            id: "gen".to_string(),
+            node_type: "generator".to_string(),
+            children_ids: Some(vec![]),
+            plugin_id: Some("line_generator".to_string()),
+            ..Default::default()
+        });
+        ProjectData {
+            root_node_ids: vec!["gen".to_string()],
+            nodes,
+            map_layers: Some(vec![ProjectMapLayer {
+                id: "layer-1".to_string(),
+                name: "Floor".to_string(),
+                info: None,
+                image_base64: String::new(),
+                image_file: None,
+                visible: true,
+                opacity: 1.0,
+                z_index: 0,
+            }]),
+            ..Default::default()
+        }
+    }
+
+    fn write_map(dir: &Path) -> String {
+        let img = ImageBuffer::from_pixel(4, 3, Luma([200u8]));
+        img.save(dir.join("floor.pgm")).unwrap();
+        let yaml_path = dir.join("floor.yaml");
+        fs::write(&yaml_path, "image: floor.pgm\nresolution: 0.05\norigin: [0.0, 0.0, 0.0]\nnegate: 0\noccupied_thresh: 0.65\nfree_thresh: 0.196\n").unwrap();
+        yaml_path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_archive_roundtrip_with_maps_schema_templates_and_plugins() {
+        let dir = tempdir().unwrap();
+        let plugin_dir = dir.path().join("installed").join("line_generator");
+        fs::create_dir_all(plugin_dir.join("__pycache__")).unwrap();
+        fs::write(plugin_dir.join("manifest.json"), r#"{"name":"Line","type":"python","executable":"main.py"}"#).unwrap();
+        fs::write(plugin_dir.join("main.py"), "print('hi')").unwrap();
+        fs::write(plugin_dir.join("__pycache__").join("main.pyc"), "cache").unwrap();
+
+        let options = ArchiveSaveOptions {
+            options_schema: Some(serde_yaml::from_str("options:\n  - name: speed\n    label: Speed\n    type: float\n").unwrap()),
+            templates: vec![ExportTemplate {
+                id: "t1".to_string(),
+                name: "CSV".to_string(),
+                extension: "csv".to_string(),
+                suffix: String::new(),
+                content: "{{#each waypoints}}{{x}}\n{{/each}}".to_string(),
+                escape_html: false,
+            }],
+            map_sources: HashMap::from([("layer-1".to_string(), write_map(dir.path()))]),
+            plugins: vec![PluginInstance {
+                id: "line_generator".to_string(),
+                manifest: serde_json::from_str(r#"{"name":"Line","type":"python","executable":"main.py"}"#).unwrap(),
+                folder_path: plugin_dir.to_string_lossy().to_string(),
+                is_builtin: true,
+                sdk_version: None,
+            }],
+        };
+
+        let archive_path = dir.path().join("site.wptz");
+        save_project_archive(archive_path.to_str().unwrap(), &sample_project(), &options).unwrap();
+
+        let extract_dir = dir.path().join("pinned");
+        let loaded = load_project_archive(archive_path.to_str().unwrap(), Some(&extract_dir)).unwrap();
+
+        assert_eq!(loaded.project.root_node_ids, vec!["gen".to_string()]);
+        let layer = &loaded.project.map_layers.as_ref().unwrap()[0];
+        assert!(layer.image_base64.starts_with("data:image/png;base64,"));
+        assert_eq!(loaded.options_schema.unwrap().options[0].name, "speed");
+        assert_eq!(loaded.project.options_schema.unwrap().options[0].name, "speed");
+        assert_eq!(loaded.templates[0].id, "t1");
+        assert_eq!(loaded.plugins.len(), 1);
+        assert!(extract_dir.join("line_generator").join("main.py").exists());
+        assert!(!extract_dir.join("line_generator").join("__pycache__").exists());
+    }
+
+    #[test]
+    fn test_archive_rebuilds_map_files_from_embedded_layer() {
+        let dir = tempdir().unwrap();
+        let img = image::DynamicImage::ImageLuma8(ImageBuffer::from_pixel(2, 2, Luma([0u8])));
+        let (data_url, _, _) = map::encode_image_data_url(&img).unwrap();
+
+        let mut project = sample_project();
+        let layer = &mut project.map_layers.as_mut().unwrap()[0];
+        layer.image_base64 = data_url;
+        layer.info = Some(serde_json::json!({
+            "image": "floor.pgm", "resolution": 0.1, "origin": [1.0, 2.0, 0.0],
+            "negate": 0, "occupied_thresh": 0.65, "free_thresh": 0.196
+        }));
+
+        let archive_path = dir.path().join("embedded.wptz");
+        save_project_archive(archive_path.to_str().unwrap(), &project, &ArchiveSaveOptions::default()).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(fs::read(&archive_path).unwrap())).unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&read_entry_string(&mut archive, PROJECT_ENTRY).unwrap()).unwrap();
+        assert_eq!(stored["map_layers"][0]["image_base64"], "");
+        assert!(archive.index_for_name("maps/layer-1/map.png").is_some());
+
+        let loaded = load_project_archive(archive_path.to_str().unwrap(), None).unwrap();
+        assert!(loaded.project.map_layers.unwrap()[0].image_base64.starts_with("data:image/png;base64,"));
+        assert!(loaded.plugins.is_empty());
+    }
+
+    #[test]
+    fn test_archive_keeps_ids_that_entry_names_would_merge() {
+        let dir = tempdir().unwrap();
+        let plugin_dir = dir.path().join("installed").join("line gen");
+        fs::create_dir_all(&plugin_dir).unwrap();
+        fs::write(plugin_dir.join("manifest.json"), r#"{"name":"Line","type":"python","executable":"main.py"}"#).unwrap();
+        fs::write(plugin_dir.join("main.py"), "print('hi')").unwrap();
+
+        let img = image::DynamicImage::ImageLuma8(ImageBuffer::from_pixel(2, 2, Luma([0u8])));
+        let (data_url, _, _) = map::encode_image_data_url(&img).unwrap();
+        let mut project = sample_project();
+        project.nodes.get_mut("gen").unwrap().plugin_id = Some("line gen".to_string());
+        let base = project.map_layers.as_ref().unwrap()[0].clone();
+        project.map_layers = Some(["floor 1", "floor_1"].iter().enumerate().map(|(i, id)| ProjectMapLayer {
+            id: id.to_string(),
+            info: Some(serde_json::json!({
+                "image": "map.pgm", "resolution": 0.1 * (i + 1) as f64, "origin": [0.0, 0.0, 0.0],
+                "negate": 0, "occupied_thresh": 0.65, "free_thresh": 0.196
+            })),
+            image_base64: data_url.clone(),
+            ..base.clone()
+        }).collect());
+        let options = ArchiveSaveOptions {
+            plugins: vec![PluginInstance {
+                id: "line gen".to_string(),
+                manifest: serde_json::from_str(r#"{"name":"Line","type":"python","executable":"main.py"}"#).unwrap(),
+                folder_path: plugin_dir.to_string_lossy().to_string(),
+                is_builtin: false,
+                sdk_version: None,
+            }],
+            ..Default::default()
+        };
+        let archive_path = dir.path().join("site.wptz");
+        save_project_archive(archive_path.to_str().unwrap(), &project, &options).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(fs::read(&archive_path).unwrap())).unwrap();
+        assert!(archive.index_for_name("maps/floor_1/map.yaml").is_some());
+        assert!(archive.index_for_name("maps/floor_1-2/map.yaml").is_some());
+        let manifest: ArchiveManifest = serde_json::from_str(&read_entry_string(&mut archive, MANIFEST_ENTRY).unwrap()).unwrap();
+        assert_eq!(manifest.plugins, vec!["line gen".to_string()]);
+
+        // Plugins stay in the archive unless the caller asks for them
+        let extract_dir = dir.path().join("pinned");
+        let loaded = load_project_archive(archive_path.to_str().unwrap(), None).unwrap();
+        assert_eq!(loaded.pinned_plugins, vec!["line gen".to_string()]);
+        assert!(loaded.plugins.is_empty());
+        assert!(!extract_dir.exists());
+
+        let loaded = load_project_archive(archive_path.to_str().unwrap(), Some(&extract_dir)).unwrap();
+        let ids: Vec<&str> = loaded.plugins.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["line gen"]);
+        assert!(extract_dir.join("line gen").join("main.py").exists());
+        let info = |i: usize| loaded.project.map_layers.as_ref().unwrap()[i].info.clone().unwrap();
+        assert_eq!(info(0)["resolution"], 0.1);
+        assert_eq!(info(1)["resolution"], 0.2);
+    }
+
+    #[test]
+    fn test_load_rejects_newer_archive_version() {
+        let dir = tempdir().unwrap();
+        let mut writer = ArchiveWriter::new();
+        writer.add(MANIFEST_ENTRY, br#"{"format_version": 99}"#).unwrap();
+        let path = dir.path().join("future.wptz");
+        fs::write(&path, writer.finish().unwrap()).unwrap();
+
+        let err = load_project_archive(path.to_str().unwrap(), None).unwrap_err();
+        assert!(err.contains("newer than the supported version"));
+    }
+}
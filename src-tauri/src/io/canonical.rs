@@ -0,0 +1,205 @@
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use base64::{engine::general_purpose, Engine as _};
+use crate::map;
+use crate::models::ProjectData;
+
+/// Decimal places kept for floats. Sub-nanometer noise from quaternion math is dropped.
+pub const FLOAT_PRECISION: i32 = 9;
+
+/// Recursively sort object keys and round floats so that logically equal projects
+/// always serialize to identical bytes. Array order is semantic and kept as is.
+pub fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), canonicalize(&obj[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        Value::Number(n) if n.is_f64() => Value::from(normalize_float(n.as_f64().unwrap_or_default())),
+        other => other.clone(),
+    }
+}
+
+fn normalize_float(f: f64) -> f64 {
+    // Rounding very large magnitudes would overflow the scaled value
+    if f.abs() >= 1e15 {
+        return f;
+    }
+    let factor = 10f64.powi(FLOAT_PRECISION);
+    let rounded = (f * factor).round() / factor;
+    // Avoid "-0.0" flip-flopping with "0.0"
+    if rounded == 0.0 { 0.0 } else { rounded }
+}
+
+/// Pretty-printed canonical JSON with a trailing newline.
+pub fn to_canonical_string(value: &Value) -> Result<String, String> {
+    let mut out = serde_json::to_string_pretty(&canonicalize(value))
+        .map_err(|e| format!("Serialization error: {}", e))?;
+    out.push('\n');
+    Ok(out)
+}
+
+/// Folder holding externally stored map images, e.g. `site.wptroj` -> `site_maps/`.
+pub fn map_image_dir(project_path: &Path) -> PathBuf {
+    let stem = project_path.file_stem().unwrap_or_default().to_string_lossy();
+    project_path.with_file_name(format!("{}_maps", stem))
+}
+
+/// Content-addressed name, so a changed image never overwrites a file an older save refers to.
+fn layer_file_name(layer_id: &str, data: &[u8]) -> String {
+    let safe: String = layer_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}-{:016x}.png", safe, super::recovery::fnv1a(data))
+}
+
+/// Move each layer's base64 image out of the project JSON, replacing it with a relative
+/// `image_file` reference. Returns the PNGs to write; nothing is written here.
+pub fn externalize_map_images(project_json: &mut Value, project_path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let dir = map_image_dir(project_path);
+    let dir_name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut images = Vec::new();
+
+    if let Some(layers) = project_json.get_mut("map_layers").and_then(Value::as_array_mut) {
+        for layer in layers {
+            let image = layer.get("image_base64").and_then(Value::as_str).unwrap_or("");
+            if image.is_empty() {
+                continue;
+            }
+            let data = map::decode_image_data_url(image)?;
+            let id = layer.get("id").and_then(Value::as_str).unwrap_or("layer");
+            let file_name = layer_file_name(id, &data);
+
+            layer["image_base64"] = Value::from("");
+            layer["image_file"] = Value::from(format!("{}/{}", dir_name, file_name));
+            images.push((dir.join(file_name), data));
+        }
+    }
+    Ok(images)
+}
+
+/// Write map images that are not on disk yet. Existing files have the same content by name.
+pub fn write_map_images(images: &[(PathBuf, Vec<u8>)]) -> Result<(), String> {
+    for (path, data) in images {
+        if path.exists() {
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create map image folder: {}", e))?;
+        }
+        super::write_atomic(path, data)?;
+    }
+    Ok(())
+}
+
+fn referenced_map_images(project_file: &Path) -> HashSet<String> {
+    let Ok(content) = fs::read_to_string(project_file) else { return HashSet::new() };
+    let Ok(value) = serde_json::from_str::<Value>(&content) else { return HashSet::new() };
+    value.get("map_layers").and_then(Value::as_array).into_iter().flatten()
+        .filter_map(|layer| layer.get("image_file").and_then(Value::as_str))
+        .filter_map(|file| Path::new(file).file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// Remove PNGs from the map folder that neither the project nor any of its `.bak` copies refer to.
+pub fn prune_map_images(project_path: &Path) {
+    let mut keep = referenced_map_images(project_path);
+    let backup_prefix = format!("{}.", project_path.file_name().unwrap_or_default().to_string_lossy());
+    let base_dir = project_path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    for entry in fs::read_dir(base_dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(&backup_prefix) && name.ends_with(".bak") {
+            keep.extend(referenced_map_images(&entry.path()));
+        }
+    }
+
+    for entry in fs::read_dir(map_image_dir(project_path)).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".png") && !keep.contains(&name) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Load `image_file` references back into `image_base64` data URLs. The reference is
+/// cleared so loaded layers look the same whether or not their image was external.
+pub fn resolve_external_map_images(project: &mut ProjectData, project_path: &Path) -> Result<(), String> {
+    let base_dir = project_path.parent().unwrap_or(Path::new(""));
+    for layer in project.map_layers.iter_mut().flatten() {
+        let Some(file) = layer.image_file.take() else { continue };
+        if !layer.image_base64.is_empty() {
+            continue;
+        }
+        let image_path = base_dir.join(file);
+        let data = fs::read(&image_path)
+            .map_err(|e| format!("Failed to read map image {}: {}", image_path.display(), e))?;
+        layer.image_base64 = format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(data));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_canonical_output_is_independent_of_key_order() {
+        let a = json!({ "nodes": { "b": { "y": 1.0, "x": 2 }, "a": {} }, "root_node_ids": ["b", "a"] });
+        let b = json!({ "root_node_ids": ["b", "a"], "nodes": { "a": {}, "b": { "x": 2, "y": 1.0 } } });
+        assert_eq!(to_canonical_string(&a).unwrap(), to_canonical_string(&b).unwrap());
+        // Arrays keep their semantic order
+        assert!(to_canonical_string(&a).unwrap().find("\"b\",").is_some());
+    }
+
+    #[test]
+    fn test_floats_are_rounded_and_negative_zero_normalized() {
+        let v = canonicalize(&json!({ "qz": 0.1234567891234, "qx": -0.0, "tiny": -1e-12, "count": 3 }));
+        assert_eq!(v["qz"], json!(0.123456789));
+        assert_eq!(v["qx"].to_string(), "0.0");
+        assert_eq!(v["tiny"].to_string(), "0.0");
+        assert_eq!(v["count"], json!(3));
+    }
+
+    #[test]
+    fn test_map_images_roundtrip_through_external_files() {
+        let dir = tempdir().unwrap();
+        let project_path = dir.path().join("site.wptroj");
+        let png = general_purpose::STANDARD.encode(b"not really a png");
+        let mut project_json = json!({
+            "root_node_ids": [],
+            "nodes": {},
+            "map_layers": [{
+                "id": "layer/1", "name": "Floor", "info": null,
+                "image_base64": format!("data:image/png;base64,{}", png),
+                "visible": true, "opacity": 1.0, "z_index": 0
+            }]
+        });
+
+        let images = externalize_map_images(&mut project_json, &project_path).unwrap();
+        let file_name = format!("layer_1-{:016x}.png", crate::io::recovery::fnv1a(b"not really a png"));
+        assert_eq!(project_json["map_layers"][0]["image_base64"], "");
+        assert_eq!(project_json["map_layers"][0]["image_file"], format!("site_maps/{}", file_name));
+        assert!(!dir.path().join("site_maps").exists());
+
+        write_map_images(&images).unwrap();
+        assert!(dir.path().join("site_maps").join(&file_name).exists());
+
+        let mut project: ProjectData = serde_json::from_value(project_json).unwrap();
+        resolve_external_map_images(&mut project, &project_path).unwrap();
+        let layer = &project.map_layers.as_ref().unwrap()[0];
+        assert_eq!(layer.image_base64, format!("data:image/png;base64,{}", png));
+        assert_eq!(layer.image_file, None);
+    }
+}
@@ -0,0 +1,147 @@
+use serde_json::{Map, Value};
+
+/// Format version written by this build. Bump it together with a new entry in `MIGRATIONS`.
+pub const CURRENT_FORMAT_VERSION: u32 = 1;
+
+type MigrationFn = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// Upgrade steps in order; entry `i` converts a version `i` project into version `i + 1`.
+const MIGRATIONS: &[(&str, MigrationFn)] = &[
+    ("legacy yaw to quaternion", migrate_v0_to_v1),
+];
+
+/// Read the `format_version` of a raw project. Files saved before versioning count as version 0.
+pub fn detect_format_version(project: &Value) -> Result<u32, String> {
+    match project.get("format_version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid format_version: {}", v)),
+    }
+}
+
+/// Upgrade a raw project JSON value step by step to `CURRENT_FORMAT_VERSION`.
+/// Returns the version the file was originally saved with.
+pub fn migrate_project(project: &mut Value) -> Result<u32, String> {
+    let original = detect_format_version(project)?;
+    if original > CURRENT_FORMAT_VERSION {
+        return Err(format!(
+            "Project format version {} is newer than the supported version {}. Please update the tool to open this file.",
+            original, CURRENT_FORMAT_VERSION
+        ));
+    }
+
+    let obj = project
+        .as_object_mut()
+        .ok_or_else(|| "Project file root must be a JSON object".to_string())?;
+
+    for version in original..CURRENT_FORMAT_VERSION {
+        let (name, migrate) = MIGRATIONS[version as usize];
+        migrate(obj).map_err(|e| {
+            format!("Migration v{} -> v{} ({}) failed: {}", version, version + 1, name, e)
+        })?;
+        obj.insert("format_version".to_string(), Value::from(version + 1));
+    }
+
+    Ok(original)
+}
+
+/// v0 files may store a bare `yaw` instead of a quaternion on each transform.
+fn migrate_v0_to_v1(project: &mut Map<String, Value>) -> Result<(), String> {
+    let Some(nodes) = project.get_mut("nodes") else {
+        return Ok(());
+    };
+    let nodes = nodes
+        .as_object_mut()
+        .ok_or_else(|| "`nodes` must be an object".to_string())?;
+
+    for (id, node) in nodes.iter_mut() {
+        let Some(transform) = node.get_mut("transform").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let Some(yaw) = transform.remove("yaw") else {
+            continue;
+        };
+        let has_quaternion = ["qx", "qy", "qz", "qw"].iter().all(|k| transform.get(*k).is_some_and(|v| !v.is_null()));
+        if has_quaternion {
+            continue;
+        }
+        // The legacy loader read a null yaw as no rotation
+        let yaw = match yaw {
+            Value::Null => 0.0,
+            yaw => yaw.as_f64().ok_or_else(|| format!("node {}: yaw is not a number", id))?,
+        };
+        let half_yaw = yaw / 2.0;
+        transform.insert("qx".to_string(), Value::from(0.0));
+        transform.insert("qy".to_string(), Value::from(0.0));
+        transform.insert("qz".to_string(), Value::from(half_yaw.sin()));
+        transform.insert("qw".to_string(), Value::from(half_yaw.cos()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unversioned_project_is_migrated_to_current() {
+        let mut project = json!({
+            "root_node_ids": ["a"],
+            "nodes": { "a": { "id": "a", "type": "manual", "transform": { "x": 1.0, "y": 2.0, "yaw": std::f64::consts::PI } } }
+        });
+        let original = migrate_project(&mut project).unwrap();
+        assert_eq!(original, 0);
+        assert_eq!(project["format_version"], json!(CURRENT_FORMAT_VERSION));
+
+        let transform = &project["nodes"]["a"]["transform"];
+        assert!(transform.get("yaw").is_none());
+        assert!((transform["qz"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+        assert!(transform["qw"].as_f64().unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_null_yaw_without_quaternion_is_identity() {
+        let mut project = json!({
+            "root_node_ids": ["a"],
+            "nodes": { "a": { "id": "a", "type": "manual", "transform": { "x": 1.0, "y": 2.0, "yaw": null, "qz": null } } }
+        });
+        migrate_project(&mut project).unwrap();
+
+        let transform = &project["nodes"]["a"]["transform"];
+        assert_eq!((transform["qz"].as_f64(), transform["qw"].as_f64()), (Some(0.0), Some(1.0)));
+        assert!(serde_json::from_value::<crate::models::ProjectData>(project).is_ok());
+    }
+
+    #[test]
+    fn test_current_project_is_left_untouched() {
+        let mut project = json!({
+            "format_version": CURRENT_FORMAT_VERSION,
+            "root_node_ids": [],
+            "nodes": {}
+        });
+        let before = project.clone();
+        migrate_project(&mut project).unwrap();
+        assert_eq!(project, before);
+    }
+
+    #[test]
+    fn test_newer_project_is_rejected() {
+        let mut project = json!({ "format_version": CURRENT_FORMAT_VERSION + 1, "root_node_ids": [], "nodes": {} });
+        let err = migrate_project(&mut project).unwrap_err();
+        assert!(err.contains("newer than the supported version"));
+    }
+
+    #[test]
+    fn test_invalid_version_is_rejected() {
+        let mut project = json!({ "format_version": "one", "root_node_ids": [], "nodes": {} });
+        assert!(migrate_project(&mut project).is_err());
+    }
+
+    #[test]
+    fn test_every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len(), CURRENT_FORMAT_VERSION as usize);
+    }
+}
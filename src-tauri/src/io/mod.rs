@@ -1,43 +1,130 @@
 use std::fs;
+use std::io::Write;
 use crate::models::ProjectData;
-use handlebars::Handlebars;
+use crate::export::context::ExportMeta;
+use crate::export::ExportRequest;
+use crate::export::templates::TemplateEnv;
+
+pub mod archive;
+pub mod canonical;
+pub mod migration;
+pub mod recovery;
+
+/// Write to a temporary sibling file, flush it to disk and rename it over `path`,
+/// so readers never observe a partially written file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("File write error: {}", e));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct SaveOptions {
+    /// Number of rotating `.bak` copies to keep
+    pub backup_count: usize,
+    /// Store map images as PNG files next to the project instead of inline base64
+    pub external_maps: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            backup_count: recovery::DEFAULT_BACKUP_COUNT,
+            external_maps: false,
+        }
+    }
+}
 
 pub fn save_project(path: &str, data: &ProjectData) -> Result<(), String> {
-    let json = serde_json::to_string_pretty(data)
+    save_project_with_options(path, data, &SaveOptions::default())
+}
+
+/// Save in canonical form (sorted keys, rounded floats) so that project files diff cleanly.
+pub fn save_project_with_options(path: &str, data: &ProjectData, options: &SaveOptions) -> Result<(), String> {
+    let mut value = serde_json::to_value(data)
         .map_err(|e| format!("Serialization error: {}", e))?;
-    
-    fs::write(path, json)
-        .map_err(|e| format!("File write error: {}", e))?;
-    
+
+    let path = Path::new(path);
+    let images = if options.external_maps {
+        canonical::externalize_map_images(&mut value, path)?
+    } else {
+        Vec::new()
+    };
+    let json = canonical::to_canonical_string(&value)?;
+
+    // Images are content-addressed and never overwritten, so writing them first cannot
+    // break the current file or its backups; stale ones go only once the project is saved.
+    canonical::write_map_images(&images)?;
+    recovery::rotate_backups(path, options.backup_count)?;
+    write_atomic(path, json.as_bytes())?;
+    if options.external_maps {
+        canonical::prune_map_images(path);
+    }
+
     Ok(())
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct LoadedProject {
+    pub data: ProjectData,
+    /// An autosave newer than the file, to be offered before editing the file's contents
+    pub recovery: Option<recovery::RecoveryInfo>,
+}
+
+/// Load a project along with any newer autosave of it in `recovery_dir`.
+pub fn load_project_with_recovery(path: &str, recovery_dir: &Path) -> Result<LoadedProject, String> {
+    Ok(LoadedProject {
+        data: load_project(path)?,
+        recovery: recovery::find_recovery(recovery_dir, Some(path)),
+    })
+}
+
 pub fn load_project(path: &str) -> Result<ProjectData, String> {
     let content = fs::read_to_string(path)
         .map_err(|e| format!("File read error: {}", e))?;
-    
-    let data: ProjectData = serde_json::from_str(&content)
+
+    let mut data = parse_project(&content)?;
+    canonical::resolve_external_map_images(&mut data, Path::new(path))?;
+
+    Ok(data)
+}
+
+/// Parse project JSON, upgrading it to the current format version first.
+/// Structural problems are left to `project::integrity` so callers decide how to report them.
+pub fn parse_project(content: &str) -> Result<ProjectData, String> {
+    let mut raw: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Deserialization error: {}", e))?;
+
+    migration::migrate_project(&mut raw)?;
+
+    let data: ProjectData = serde_json::from_value(raw)
         .map_err(|e| format!("Deserialization error: {}", e))?;
-    
+
     Ok(data)
 }
 
 use base64::{engine::general_purpose, Engine as _};
 use std::path::Path;
 
-pub fn export_waypoints(path: &str, waypoints: Vec<serde_json::Value>, template: Option<String>, image_data_b64: Option<String>) -> Result<(), String> {
-    let content = if let Some(tmpl) = template {
-        let reg = Handlebars::new();
-        // Register the template string and render it with wrapped data
-        let rendered = reg.render_template(&tmpl, &serde_json::json!({ "waypoints": waypoints }))
-            .map_err(|e| format!("Template render error: {}", e))?;
-        rendered
-    } else if path.to_lowercase().ends_with(".yaml") || path.to_lowercase().ends_with(".yml") {
-        serde_yaml::to_string(&waypoints)
-            .map_err(|e| format!("YAML serialization error: {}", e))?
-    } else {
-        serde_json::to_string_pretty(&waypoints)
-            .map_err(|e| format!("JSON serialization error: {}", e))?
+/// `env` supplies user helpers and partials to the template, `meta` the project and map fields.
+pub fn export_waypoints(path: &str, waypoints: Vec<serde_json::Value>, template: Option<String>, image_data_b64: Option<String>, env: &TemplateEnv, meta: &ExportMeta) -> Result<(), String> {
+    let content = match template {
+        Some(tmpl) => crate::export::render_template(&tmpl, &waypoints, env, meta)?,
+        None => crate::export::render_data(path, &waypoints)?,
     };
 
     fs::write(path, content)
@@ -56,6 +143,19 @@ pub fn export_waypoints(path: &str, waypoints: Vec<serde_json::Value>, template:
     Ok(())
 }
 
+/// Flatten the project's node tree on the backend, move it into the target frame
+/// and export it like `export_waypoints`.
+pub fn export_project(path: &str, project: &ProjectData, template: Option<String>, image_data_b64: Option<String>, request: &ExportRequest) -> Result<(), String> {
+    let mut route = crate::project::route::build_route(project, &request.route_options);
+    request.transform.apply_all(&mut route)?;
+    let waypoints = crate::project::route::route_to_values(&route)?;
+    let meta = ExportMeta {
+        frame_id: request.transform.frame_id.clone(),
+        ..ExportMeta::new(request.project_name.as_deref(), path, Some(project))
+    };
+    export_waypoints(path, waypoints, template, image_data_b64, &request.env, &meta)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,7 +176,7 @@ mod tests {
         let template = Some("{{#each waypoints}}Node {{id}} is at {{x}}, {{y}}\n{{/each}}".to_string());
         
         // Use temp file for export
-        let res = export_waypoints(path_str, waypoints, template, None);
+        let res = export_waypoints(path_str, waypoints, template, None, &TemplateEnv::default(), &ExportMeta::new(None, path_str, None));
         assert!(res.is_ok(), "Export failed: {:?}", res.err());
 
         // Read and verify
@@ -93,7 +193,7 @@ mod tests {
 
         let waypoints = vec![json!({ "id": "wp1" })];
         
-        let res = export_waypoints(path_str, waypoints, None, None);
+        let res = export_waypoints(path_str, waypoints, None, None, &TemplateEnv::default(), &ExportMeta::new(None, path_str, None));
         assert!(res.is_ok(), "Export failed");
 
         let content = fs::read_to_string(path_str).unwrap();
@@ -116,16 +216,14 @@ mod tests {
                 id: "node1".to_string(),
                 node_type: "manual".to_string(),
                 transform: Some(Transform { x: 1.0, y: 2.0, z: None, qx: 0.0, qy: 0.0, qz: 0.0, qw: 1.0 }),
-                options: None,
-                generator_params: None,
-                children_ids: None,
+                ..Default::default()
             },
         );
 
         let project_data = ProjectData {
             root_node_ids: vec!["node1".to_string()],
             nodes,
-            map_layers: None,
+            ..Default::default()
         };
 
         // Save
@@ -149,4 +247,99 @@ mod tests {
         assert_eq!(node.transform.as_ref().unwrap().qz, 0.0);
         assert_eq!(node.transform.as_ref().unwrap().qw, 1.0);
     }
+
+    #[test]
+    fn test_save_project_keeps_previous_version_as_backup() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("site.wptroj");
+        let path_str = file_path.to_str().unwrap();
+        fs::write(&file_path, "previous contents").unwrap();
+
+        let project_data = ProjectData::default();
+        let options = SaveOptions { backup_count: 2, ..SaveOptions::default() };
+        save_project_with_options(path_str, &project_data, &options).unwrap();
+
+        let backup = recovery::backup_path(&file_path, 1);
+        assert_eq!(fs::read_to_string(backup).unwrap(), "previous contents");
+        assert!(load_project(path_str).is_ok());
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_external_map_images_stay_valid_for_backups() {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("site.wptroj");
+        let path_str = file_path.to_str().unwrap();
+        let options = SaveOptions { backup_count: 1, external_maps: true };
+        let save = |pixels: &[u8]| -> ProjectData {
+            let project: ProjectData = serde_json::from_value(serde_json::json!({
+                "root_node_ids": [], "nodes": {},
+                "map_layers": [{
+                    "id": "floor", "name": "Floor", "info": null,
+                    "image_base64": format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(pixels)),
+                    "visible": true, "opacity": 1.0, "z_index": 0
+                }]
+            })).unwrap();
+            save_project_with_options(path_str, &project, &options).unwrap();
+            project
+        };
+
+        save(b"first");
+        save(b"second");
+        let third = save(b"third");
+
+        // The project and its single backup both load with their own image
+        let current = load_project(path_str).unwrap();
+        // Where the image was stored is not a change
+        assert!(crate::project::diff::diff_projects(&third, &current).layer_changes.is_empty());
+        assert!(current.map_layers.unwrap()[0].image_base64.ends_with(&general_purpose::STANDARD.encode(b"third")));
+        let backup = recovery::backup_path(&file_path, 1);
+        let restored = load_project(backup.to_str().unwrap()).unwrap();
+        assert!(restored.map_layers.unwrap()[0].image_base64.ends_with(&general_purpose::STANDARD.encode(b"second")));
+        // The image only the dropped backup used is removed
+        assert_eq!(fs::read_dir(dir.path().join("site_maps")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_load_project_reports_a_newer_autosave() {
+        let dir = tempdir().unwrap();
+        let recovery_dir = dir.path().join("recovery");
+        let file_path = dir.path().join("site.wptroj");
+        let path_str = file_path.to_str().unwrap();
+        save_project(path_str, &ProjectData::default()).unwrap();
+        assert!(load_project_with_recovery(path_str, &recovery_dir).unwrap().recovery.is_none());
+
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&file_path).unwrap().set_modified(old).unwrap();
+        let autosave = recovery::autosave_project(&recovery_dir, Some(path_str), &ProjectData::default()).unwrap();
+        let loaded = load_project_with_recovery(path_str, &recovery_dir).unwrap();
+        assert_eq!(loaded.recovery.unwrap().autosave_path, autosave);
+    }
+
+    #[test]
+    fn test_load_legacy_project_upgrades_format_version() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("legacy.wptroj");
+        fs::write(&file_path, r#"{
+            "root_node_ids": ["a"],
+            "nodes": { "a": { "id": "a", "type": "manual", "transform": { "x": 1.0, "y": 0.0, "yaw": 0.0 } } }
+        }"#).unwrap();
+
+        let loaded = load_project(file_path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.format_version, migration::CURRENT_FORMAT_VERSION);
+        assert_eq!(loaded.nodes["a"].transform.as_ref().unwrap().qw, 1.0);
+    }
+
+    #[test]
+    fn test_load_project_from_newer_version_fails() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("future.wptroj");
+        fs::write(&file_path, r#"{"format_version": 999, "root_node_ids": [], "nodes": {}}"#).unwrap();
+
+        let res = load_project(file_path.to_str().unwrap());
+        assert!(res.unwrap_err().contains("newer than the supported version"));
+    }
 }
@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::models::ProjectData;
+
+/// Number of `.bak` copies kept next to a project when the caller does not specify one.
+pub const DEFAULT_BACKUP_COUNT: usize = 3;
+
+const UNTITLED_KEY: &str = "untitled";
+
+/// Tauri's bundle identifier (`tauri.conf.json`), which names the app data directory.
+const APP_IDENTIFIER: &str = "com.chuson.waypoint-tool";
+
+/// The GUI's recovery directory, for callers without an `AppHandle` such as the CLI.
+pub fn default_recovery_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER).join("recovery"))
+}
+
+/// Path of the n-th (1 = newest) rotating backup, e.g. `site.wptroj.1.bak`.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bak", n));
+    path.with_file_name(name)
+}
+
+/// Shift existing backups up by one and copy the current file into slot 1.
+/// The oldest backup beyond `keep` is dropped. Does nothing if the file does not exist yet.
+pub fn rotate_backups(path: &Path, keep: usize) -> Result<(), String> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, keep);
+    if oldest.exists() {
+        fs::remove_file(&oldest).map_err(|e| format!("Backup rotation error: {}", e))?;
+    }
+    for n in (1..keep).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))
+                .map_err(|e| format!("Backup rotation error: {}", e))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1)).map_err(|e| format!("Backup write error: {}", e))?;
+    Ok(())
+}
+
+// FNV-1a keeps recovery file names stable across builds, unlike `DefaultHasher`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Recovery file for a project. Unsaved projects share a single `untitled` slot.
+pub fn autosave_path(recovery_dir: &Path, project_path: Option<&str>) -> PathBuf {
+    let file_name = match project_path {
+        Some(p) => {
+            let p = Path::new(p);
+            let absolute = p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+            let stem = p.file_stem().unwrap_or_default().to_string_lossy();
+            format!("{}-{:016x}.wptroj", stem, fnv1a(absolute.to_string_lossy().as_bytes()))
+        }
+        None => format!("{}.wptroj", UNTITLED_KEY),
+    };
+    recovery_dir.join(file_name)
+}
+
+pub fn autosave_project(recovery_dir: &Path, project_path: Option<&str>, data: &ProjectData) -> Result<String, String> {
+    fs::create_dir_all(recovery_dir)
+        .map_err(|e| format!("Failed to create recovery directory: {}", e))?;
+
+    let target = autosave_path(recovery_dir, project_path);
+    let json = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Serialization error: {}", e))?;
+    super::write_atomic(&target, json.as_bytes())?;
+
+    Ok(target.to_string_lossy().to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryInfo {
+    pub autosave_path: String,
+    /// Milliseconds since the Unix epoch
+    pub autosave_modified: u64,
+    pub project_modified: Option<u64>,
+}
+
+fn modified_millis(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// Report an autosave that is newer than the project file on disk (or any autosave for a missing file).
+pub fn find_recovery(recovery_dir: &Path, project_path: Option<&str>) -> Option<RecoveryInfo> {
+    let autosave = autosave_path(recovery_dir, project_path);
+    let autosave_modified = modified_millis(&autosave)?;
+    let project_modified = project_path.and_then(|p| modified_millis(Path::new(p)));
+
+    if matches!(project_modified, Some(m) if m >= autosave_modified) {
+        return None;
+    }
+
+    Some(RecoveryInfo {
+        autosave_path: autosave.to_string_lossy().to_string(),
+        autosave_modified,
+        project_modified,
+    })
+}
+
+pub fn discard_recovery(recovery_dir: &Path, project_path: Option<&str>) -> Result<(), String> {
+    let autosave = autosave_path(recovery_dir, project_path);
+    if autosave.exists() {
+        fs::remove_file(&autosave).map_err(|e| format!("Failed to remove autosave: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rotate_backups_keeps_only_n_copies() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("site.wptroj");
+
+        for i in 0..5 {
+            fs::write(&path, format!("v{}", i)).unwrap();
+            rotate_backups(&path, 2).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v4");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "v3");
+        assert!(!backup_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_rotate_backups_ignores_missing_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("new.wptroj");
+        rotate_backups(&path, 3).unwrap();
+        assert!(!backup_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn test_autosave_newer_than_project_is_offered_for_recovery() {
+        let dir = tempdir().unwrap();
+        let recovery_dir = dir.path().join("recovery");
+        let project_path = dir.path().join("site.wptroj");
+        fs::write(&project_path, "{}").unwrap();
+        let project_str = project_path.to_str().unwrap();
+
+        // Backdate the project so the autosave is strictly newer
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&project_path).unwrap().set_modified(old).unwrap();
+
+        autosave_project(&recovery_dir, Some(project_str), &ProjectData::default()).unwrap();
+        let info = find_recovery(&recovery_dir, Some(project_str)).expect("recovery should be offered");
+        assert!(info.autosave_path.ends_with(".wptroj"));
+
+        discard_recovery(&recovery_dir, Some(project_str)).unwrap();
+        assert!(find_recovery(&recovery_dir, Some(project_str)).is_none());
+    }
+
+    #[test]
+    fn test_autosave_older_than_project_is_ignored() {
+        let dir = tempdir().unwrap();
+        let recovery_dir = dir.path().join("recovery");
+        let project_path = dir.path().join("site.wptroj");
+        let project_str = project_path.to_str().unwrap();
+
+        let saved = autosave_project(&recovery_dir, Some(project_str), &ProjectData::default()).unwrap();
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&saved).unwrap().set_modified(old).unwrap();
+        fs::write(&project_path, "{}").unwrap();
+
+        assert!(find_recovery(&recovery_dir, Some(project_str)).is_none());
+    }
+
+    #[test]
+    fn test_untitled_autosave_is_offered_until_discarded() {
+        let dir = tempdir().unwrap();
+        let recovery_dir = dir.path().join("recovery");
+        autosave_project(&recovery_dir, None, &ProjectData::default()).unwrap();
+        let info = find_recovery(&recovery_dir, None).expect("untitled work should be offered");
+        assert!(info.project_modified.is_none());
+
+        // Saving some other project leaves it alone
+        discard_recovery(&recovery_dir, Some("other.wptroj")).unwrap();
+        assert!(find_recovery(&recovery_dir, None).is_some());
+        discard_recovery(&recovery_dir, None).unwrap();
+        assert!(find_recovery(&recovery_dir, None).is_none());
+    }
+}
@@ -1,8 +1,11 @@
+pub mod cli;
 pub mod models;
 pub mod map;
 pub mod io;
+pub mod export;
 pub mod commands;
 pub mod plugins;
+pub mod project;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -13,10 +16,39 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             commands::load_ros_map,
+            commands::generate_sim_world,
             commands::save_project,
             commands::load_project,
+            commands::merge_projects,
+            commands::diff_projects,
+            commands::save_project_archive,
+            commands::load_project_archive,
+            commands::autosave_project,
+            commands::find_project_recovery,
+            commands::discard_project_recovery,
+            commands::validate_project,
+            commands::repair_project,
             commands::load_options_schema,
+            commands::save_options_schema,
+            commands::validate_options,
+            commands::migrate_options_schema,
             commands::export_waypoints,
+            commands::build_route,
+            commands::resolve_node_options,
+            commands::export_project,
+            commands::export_builtin,
+            commands::render_map_image,
+            commands::export_report,
+            commands::run_export_profile,
+            commands::preview_template,
+            commands::list_export_templates,
+            commands::save_export_template,
+            commands::delete_export_template,
+            commands::import_export_template,
+            commands::export_export_template,
+            commands::list_template_partials,
+            commands::save_template_partial,
+            commands::delete_template_partial,
             commands::fetch_installed_plugins,
             commands::run_plugin,
             commands::scan_custom_plugin,
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = waypoint_tool_lib::cli::run_headless(&args) {
+        std::process::exit(code);
+    }
     waypoint_tool_lib::run()
 }
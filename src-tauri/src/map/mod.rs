@@ -5,6 +5,8 @@ use image::GenericImageView;
 use base64::{Engine as _, engine::general_purpose};
 use crate::models::MapInfo;
 
+pub mod world;
+
 #[derive(Debug, serde::Serialize)]
 pub struct MapLoadResult {
     pub info: MapInfo,
@@ -30,22 +32,99 @@ pub fn load_map(yaml_path: &str) -> std::result::Result<MapLoadResult, String> {
     let img = image::open(&image_path)
         .map_err(|e| format!("Failed to open image {}: {}", image_path.display(), e))?;
 
+    // 3. Convert image to PNG and encode to Base64 so frontend can render it easily
+    let (image_data_b64, width, height) = encode_image_data_url(&img)?;
+
+    Ok(MapLoadResult {
+        info: map_info,
+        image_data_b64,
+        width,
+        height
+    })
+}
+
+/// Encode an image as a `data:image/png;base64,` URL, returning it with the image dimensions.
+pub fn encode_image_data_url(img: &image::DynamicImage) -> std::result::Result<(String, u32, u32), String> {
     let (width, height) = img.dimensions();
 
-    // 3. Convert image to PNG and encode to Base64 so frontend can render it easily
     let mut png_data: Vec<u8> = Vec::new();
     let mut cursor = std::io::Cursor::new(&mut png_data);
     img.write_to(&mut cursor, image::ImageFormat::Png)
         .map_err(|e| format!("Failed to encode image to PNG: {}", e))?;
 
     let b64 = general_purpose::STANDARD.encode(&png_data);
-    let image_data_b64 = format!("data:image/png;base64,{}", b64);
+    Ok((format!("data:image/png;base64,{}", b64), width, height))
+}
 
-    Ok(MapLoadResult {
+/// Decode a base64 image, with or without a `data:` URL prefix, back into raw file bytes.
+pub fn decode_image_data_url(data: &str) -> std::result::Result<Vec<u8>, String> {
+    let b64 = match data.split_once(";base64,") {
+        Some((_, payload)) => payload,
+        None => data,
+    };
+    general_purpose::STANDARD.decode(b64.trim())
+        .map_err(|e| format!("Base64 decode error: {}", e))
+}
+
+/// Occupancy grid decoded from a ROS map, stored row-major from the top image row.
+#[derive(Debug)]
+pub struct OccupancyGrid {
+    pub info: MapInfo,
+    pub width: u32,
+    pub height: u32,
+    pub occupied: Vec<bool>,
+}
+
+impl OccupancyGrid {
+    pub fn is_occupied(&self, col: u32, row: u32) -> bool {
+        self.occupied[(row * self.width + col) as usize]
+    }
+
+    /// World coordinates of a cell center, following map_server conventions
+    /// (image row 0 is the top edge, `origin` is the bottom-left corner).
+    pub fn cell_center(&self, col: f64, row: f64) -> (f64, f64) {
+        let res = self.info.resolution;
+        let local_x = (col + 0.5) * res;
+        let local_y = (self.height as f64 - row - 0.5) * res;
+        let yaw = self.info.origin[2];
+        (
+            self.info.origin[0] + local_x * yaw.cos() - local_y * yaw.sin(),
+            self.info.origin[1] + local_x * yaw.sin() + local_y * yaw.cos(),
+        )
+    }
+}
+
+pub fn load_occupancy_grid(yaml_path: &str) -> std::result::Result<OccupancyGrid, String> {
+    let path = Path::new(yaml_path);
+    let parent_dir = path.parent().unwrap_or(Path::new(""));
+
+    let yaml_content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read YAML: {}", e))?;
+
+    let map_info: MapInfo = serde_yaml::from_str(&yaml_content)
+        .map_err(|e| format!("Failed to parse YAML: {}", e))?;
+
+    let image_path = parent_dir.join(&map_info.image);
+    let img = image::open(&image_path)
+        .map_err(|e| format!("Failed to open image {}: {}", image_path.display(), e))?
+        .to_luma8();
+
+    let (width, height) = img.dimensions();
+    // Same thresholding as map_server in trinary mode: dark pixels are occupied unless negated
+    let occupied = img
+        .pixels()
+        .map(|p| {
+            let value = p.0[0] as f64 / 255.0;
+            let occupancy = if map_info.negate != 0 { value } else { 1.0 - value };
+            occupancy > map_info.occupied_thresh
+        })
+        .collect();
+
+    Ok(OccupancyGrid {
         info: map_info,
-        image_data_b64,
         width,
-        height
+        height,
+        occupied,
     })
 }
 
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use crate::map::{load_occupancy_grid, OccupancyGrid};
+use crate::models::Transform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorldFormat {
+    /// SDF 1.6 world for Gazebo Classic
+    Gazebo,
+    /// SDF 1.8 world with the Ignition/Gazebo Sim system plugins
+    Ignition,
+    /// Player/Stage `.world` file using the map image as floorplan
+    Stage,
+}
+
+fn default_wall_height() -> f64 {
+    1.0
+}
+
+fn default_world_name() -> String {
+    "waypoint_map".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldOptions {
+    pub format: WorldFormat,
+    #[serde(default = "default_wall_height")]
+    pub wall_height: f64,
+    #[serde(default = "default_world_name")]
+    pub world_name: String,
+}
+
+/// A waypoint pose to visualize as a non-colliding marker model in the world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldMarker {
+    pub id: String,
+    pub transform: Transform,
+}
+
+/// Axis-aligned block of occupied cells, in grid coordinates (inclusive start, exclusive end).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallBlock {
+    pub col: u32,
+    pub row: u32,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+/// Merge occupied cells into as few rectangles as a row-run sweep allows.
+/// Runs in each row are extended downwards while the next row has the identical run.
+pub fn extract_wall_blocks(grid: &OccupancyGrid) -> Vec<WallBlock> {
+    let mut finished = Vec::new();
+    let mut open: Vec<WallBlock> = Vec::new();
+
+    for row in 0..grid.height {
+        let mut runs = Vec::new();
+        let mut col = 0;
+        while col < grid.width {
+            if grid.is_occupied(col, row) {
+                let start = col;
+                while col < grid.width && grid.is_occupied(col, row) {
+                    col += 1;
+                }
+                runs.push((start, col - start));
+            } else {
+                col += 1;
+            }
+        }
+
+        let mut next_open = Vec::with_capacity(runs.len());
+        for (start, len) in runs {
+            if let Some(pos) = open.iter().position(|b| b.col == start && b.cols == len) {
+                let mut block = open.swap_remove(pos);
+                block.rows += 1;
+                next_open.push(block);
+            } else {
+                next_open.push(WallBlock { col: start, row, cols: len, rows: 1 });
+            }
+        }
+        finished.append(&mut open);
+        open = next_open;
+    }
+    finished.append(&mut open);
+    finished.sort_by_key(|b| (b.row, b.col));
+    finished
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn sanitize_name(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Stage worldfile strings have no escapes, so quotes, backslashes and line breaks become `_`.
+fn stage_string(s: &str) -> String {
+    s.chars().map(|c| if c == '"' || c == '\\' || c.is_control() { '_' } else { c }).collect()
+}
+
+fn box_geometry(sx: f64, sy: f64, sz: f64) -> String {
+    format!("<geometry><box><size>{:.4} {:.4} {:.4}</size></box></geometry>", sx, sy, sz)
+}
+
+pub fn build_sdf_world(
+    grid: &OccupancyGrid,
+    blocks: &[WallBlock],
+    markers: &[WorldMarker],
+    options: &WorldOptions,
+) -> String {
+    let ignition = options.format == WorldFormat::Ignition;
+    let res = grid.info.resolution;
+    let map_yaw = grid.info.origin[2];
+    let h = options.wall_height;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "<?xml version=\"1.0\"?>");
+    let _ = writeln!(out, "<sdf version=\"{}\">", if ignition { "1.8" } else { "1.6" });
+    let _ = writeln!(out, "  <world name=\"{}\">", escape_xml(&options.world_name));
+
+    if ignition {
+        for (file, name) in [
+            ("ignition-gazebo-physics-system", "ignition::gazebo::systems::Physics"),
+            ("ignition-gazebo-user-commands-system", "ignition::gazebo::systems::UserCommands"),
+            ("ignition-gazebo-scene-broadcaster-system", "ignition::gazebo::systems::SceneBroadcaster"),
+        ] {
+            let _ = writeln!(out, "    <plugin filename=\"{}\" name=\"{}\"/>", file, name);
+        }
+    }
+
+    // Light and ground plane are inlined so the world does not depend on a model database
+    let _ = writeln!(out, "    <light type=\"directional\" name=\"sun\">");
+    let _ = writeln!(out, "      <cast_shadows>true</cast_shadows>");
+    let _ = writeln!(out, "      <pose>0 0 10 0 0 0</pose>");
+    let _ = writeln!(out, "      <diffuse>0.8 0.8 0.8 1</diffuse>");
+    let _ = writeln!(out, "      <direction>-0.5 0.1 -0.9</direction>");
+    let _ = writeln!(out, "    </light>");
+    let _ = writeln!(out, "    <model name=\"ground_plane\">");
+    let _ = writeln!(out, "      <static>true</static>");
+    let _ = writeln!(out, "      <link name=\"link\">");
+    let plane = "<geometry><plane><normal>0 0 1</normal><size>100 100</size></plane></geometry>";
+    let _ = writeln!(out, "        <collision name=\"collision\">{}</collision>", plane);
+    let _ = writeln!(out, "        <visual name=\"visual\">{}</visual>", plane);
+    let _ = writeln!(out, "      </link>");
+    let _ = writeln!(out, "    </model>");
+
+    let _ = writeln!(out, "    <model name=\"map_walls\">");
+    let _ = writeln!(out, "      <static>true</static>");
+    for (i, b) in blocks.iter().enumerate() {
+        let (cx, cy) = grid.cell_center(
+            b.col as f64 + (b.cols as f64 - 1.0) / 2.0,
+            b.row as f64 + (b.rows as f64 - 1.0) / 2.0,
+        );
+        let geometry = box_geometry(b.cols as f64 * res, b.rows as f64 * res, h);
+        let _ = writeln!(out, "      <link name=\"wall_{}\">", i);
+        let _ = writeln!(out, "        <pose>{:.4} {:.4} {:.4} 0 0 {:.6}</pose>", cx, cy, h / 2.0, map_yaw);
+        let _ = writeln!(out, "        <collision name=\"collision\">{}</collision>", geometry);
+        let _ = writeln!(out, "        <visual name=\"visual\">{}</visual>", geometry);
+        let _ = writeln!(out, "      </link>");
+    }
+    let _ = writeln!(out, "    </model>");
+
+    for (i, m) in markers.iter().enumerate() {
+        let t = &m.transform;
+        let _ = writeln!(out, "    <model name=\"waypoint_{}_{}\">", i, sanitize_name(&m.id));
+        let _ = writeln!(out, "      <static>true</static>");
+        let _ = writeln!(out, "      <pose>{:.4} {:.4} {:.4} 0 0 {:.6}</pose>", t.x, t.y, t.z.unwrap_or(0.0), t.yaw());
+        let _ = writeln!(out, "      <link name=\"marker\">");
+        let _ = writeln!(out, "        <visual name=\"base\"><pose>0 0 0.01 0 0 0</pose><geometry><cylinder><radius>0.15</radius><length>0.02</length></cylinder></geometry></visual>");
+        let _ = writeln!(out, "        <visual name=\"heading\"><pose>0.15 0 0.02 0 0 0</pose>{}</visual>", box_geometry(0.3, 0.04, 0.02));
+        let _ = writeln!(out, "      </link>");
+        let _ = writeln!(out, "    </model>");
+    }
+
+    let _ = writeln!(out, "  </world>");
+    let _ = writeln!(out, "</sdf>");
+    out
+}
+
+pub fn build_stage_world(
+    grid: &OccupancyGrid,
+    image_path: &Path,
+    markers: &[WorldMarker],
+    options: &WorldOptions,
+) -> String {
+    let res = grid.info.resolution;
+    let size_x = grid.width as f64 * res;
+    let size_y = grid.height as f64 * res;
+    let (cx, cy) = grid.cell_center((grid.width as f64 - 1.0) / 2.0, (grid.height as f64 - 1.0) / 2.0);
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# Generated by ROS Waypoint Tool: {}", options.world_name.replace(|c: char| c.is_control(), " "));
+    let _ = writeln!(out, "resolution {}", res);
+    let _ = writeln!(out, "interval_sim 100");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "define floorplan model");
+    let _ = writeln!(out, "(");
+    let _ = writeln!(out, "  color \"gray30\"");
+    let _ = writeln!(out, "  boundary 0");
+    let _ = writeln!(out, "  gui_nose 0");
+    let _ = writeln!(out, "  gui_grid 0");
+    let _ = writeln!(out, "  gui_move 0");
+    let _ = writeln!(out, "  obstacle_return 1");
+    let _ = writeln!(out, "  laser_return 1");
+    let _ = writeln!(out, ")");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "floorplan");
+    let _ = writeln!(out, "(");
+    let _ = writeln!(out, "  name \"{}\"", stage_string(&options.world_name));
+    let _ = writeln!(out, "  bitmap \"{}\"", image_path.to_string_lossy().replace('\\', "/"));
+    let _ = writeln!(out, "  size [ {:.4} {:.4} {:.4} ]", size_x, size_y, options.wall_height);
+    let _ = writeln!(out, "  pose [ {:.4} {:.4} 0 {:.4} ]", cx, cy, grid.info.origin[2].to_degrees());
+    let _ = writeln!(out, ")");
+
+    for (i, m) in markers.iter().enumerate() {
+        let t = &m.transform;
+        let _ = writeln!(out);
+        let _ = writeln!(out, "model");
+        let _ = writeln!(out, "(");
+        let _ = writeln!(out, "  name \"waypoint_{}_{}\"", i, sanitize_name(&m.id));
+        let _ = writeln!(out, "  pose [ {:.4} {:.4} 0 {:.4} ]", t.x, t.y, t.yaw().to_degrees());
+        let _ = writeln!(out, "  size [ 0.3 0.3 0.02 ]");
+        let _ = writeln!(out, "  color \"green\"");
+        let _ = writeln!(out, "  obstacle_return 0");
+        let _ = writeln!(out, "  laser_return 0");
+        let _ = writeln!(out, "  ranger_return -1");
+        let _ = writeln!(out, ")");
+    }
+    out
+}
+
+pub fn generate_world(
+    yaml_path: &str,
+    output_path: &str,
+    options: &WorldOptions,
+    markers: &[WorldMarker],
+) -> Result<(), String> {
+    if options.wall_height <= 0.0 {
+        return Err("Wall height must be greater than zero".to_string());
+    }
+
+    let grid = load_occupancy_grid(yaml_path)?;
+
+    let content = match options.format {
+        WorldFormat::Gazebo | WorldFormat::Ignition => {
+            let blocks = extract_wall_blocks(&grid);
+            build_sdf_world(&grid, &blocks, markers, options)
+        }
+        WorldFormat::Stage => {
+            let image_path = Path::new(yaml_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&grid.info.image);
+            let image_path = image_path.canonicalize().unwrap_or(image_path);
+            build_stage_world(&grid, &image_path, markers, options)
+        }
+    };
+
+    fs::write(output_path, content)
+        .map_err(|e| format!("File write error: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MapInfo;
+    use image::{ImageBuffer, Luma};
+    use tempfile::tempdir;
+
+    fn grid_from_rows(rows: &[&str]) -> OccupancyGrid {
+        let width = rows[0].len() as u32;
+        let height = rows.len() as u32;
+        let occupied = rows.iter().flat_map(|r| r.chars().map(|c| c == '#')).collect();
+        OccupancyGrid {
+            info: MapInfo {
+                image: "map.png".to_string(),
+                resolution: 0.5,
+                origin: [0.0, 0.0, 0.0],
+                negate: 0,
+                occupied_thresh: 0.65,
+                free_thresh: 0.196,
+            },
+            width,
+            height,
+            occupied,
+        }
+    }
+
+    fn sdf_options() -> WorldOptions {
+        WorldOptions { format: WorldFormat::Gazebo, wall_height: 2.0, world_name: "test".to_string() }
+    }
+
+    #[test]
+    fn test_extract_wall_blocks_merges_identical_runs() {
+        let grid = grid_from_rows(&[
+            "##..",
+            "##..",
+            "...#",
+        ]);
+        let blocks = extract_wall_blocks(&grid);
+        assert_eq!(blocks, vec![
+            WallBlock { col: 0, row: 0, cols: 2, rows: 2 },
+            WallBlock { col: 3, row: 2, cols: 1, rows: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_sdf_wall_pose_uses_map_origin_and_bottom_left_convention() {
+        let grid = grid_from_rows(&[
+            "#...",
+            "....",
+        ]);
+        let blocks = extract_wall_blocks(&grid);
+        let sdf = build_sdf_world(&grid, &blocks, &[], &sdf_options());
+        // Top-left cell of a 2-row map at 0.5 m/px is centered at (0.25, 0.75), half wall height up
+        assert!(sdf.contains("<pose>0.2500 0.7500 1.0000 0 0 0.000000</pose>"));
+        assert!(sdf.contains("<size>0.5000 0.5000 2.0000</size>"));
+        assert!(sdf.contains("<sdf version=\"1.6\">"));
+    }
+
+    #[test]
+    fn test_sdf_places_markers_at_waypoint_poses() {
+        let grid = grid_from_rows(&["...."]);
+        let marker = WorldMarker {
+            id: "wp<1>".to_string(),
+            transform: Transform { x: 1.0, y: 2.0, z: None, qx: 0.0, qy: 0.0, qz: 0.0, qw: 1.0 },
+        };
+        let options = WorldOptions { format: WorldFormat::Ignition, ..sdf_options() };
+        let sdf = build_sdf_world(&grid, &[], &[marker], &options);
+        assert!(sdf.contains("<model name=\"waypoint_0_wp_1_\">"));
+        assert!(sdf.contains("<pose>1.0000 2.0000 0.0000 0 0 0.000000</pose>"));
+        assert!(sdf.contains("ignition::gazebo::systems::Physics"));
+    }
+
+    #[test]
+    fn test_generate_stage_world_from_map_file() {
+        let dir = tempdir().unwrap();
+        let img = ImageBuffer::from_fn(4, 2, |x, _| if x == 0 { Luma([0u8]) } else { Luma([254u8]) });
+        img.save(dir.path().join("map.png")).unwrap();
+        let yaml_path = dir.path().join("map.yaml");
+        fs::write(&yaml_path, "image: map.png\nresolution: 0.5\norigin: [-1.0, -1.0, 0.0]\nnegate: 0\noccupied_thresh: 0.65\nfree_thresh: 0.196").unwrap();
+        let out_path = dir.path().join("map.world");
+
+        let options = WorldOptions { format: WorldFormat::Stage, wall_height: 0.5, world_name: "site".to_string() };
+        generate_world(yaml_path.to_str().unwrap(), out_path.to_str().unwrap(), &options, &[]).unwrap();
+
+        let content = fs::read_to_string(&out_path).unwrap();
+        assert!(content.contains("size [ 2.0000 1.0000 0.5000 ]"));
+        assert!(content.contains("pose [ 0.0000 -0.5000 0 0.0000 ]"));
+        assert!(content.contains("map.png"));
+    }
+
+    #[test]
+    fn test_stage_world_name_cannot_break_out_of_its_string() {
+        let grid = grid_from_rows(&["..", ".."]);
+        let options = WorldOptions { format: WorldFormat::Stage, wall_height: 0.5, world_name: "a\"b\\c\nd".to_string() };
+        let out = build_stage_world(&grid, Path::new("map.png"), &[], &options);
+        assert!(out.contains("# Generated by ROS Waypoint Tool: a\"b\\c d\n"));
+        assert!(out.contains("  name \"a_b_c_d\"\n"));
+    }
+
+    #[test]
+    fn test_generate_world_rejects_non_positive_wall_height() {
+        let options = WorldOptions { format: WorldFormat::Gazebo, wall_height: 0.0, world_name: "w".to_string() };
+        let res = generate_world("unused.yaml", "unused.sdf", &options, &[]);
+        assert!(res.is_err());
+    }
+}
@@ -1,9 +1,11 @@
 pub mod options;
+pub mod schema_evolution;
+pub mod validation;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Transform {
     pub x: f64,
     pub y: f64,
@@ -56,7 +58,15 @@ impl<'de> Deserialize<'de> for Transform {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Transform {
+    /// Rotation around the z axis in radians, extracted from the quaternion.
+    pub fn yaw(&self) -> f64 {
+        (2.0 * (self.qw * self.qz + self.qx * self.qy))
+            .atan2(1.0 - 2.0 * (self.qy * self.qy + self.qz * self.qz))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WaypointNode {
     pub id: String,
     #[serde(rename = "type")]
@@ -65,9 +75,11 @@ pub struct WaypointNode {
     pub options: Option<HashMap<String, serde_json::Value>>,
     pub generator_params: Option<HashMap<String, serde_json::Value>>,
     pub children_ids: Option<Vec<String>>,
+    /// Plugin that produced a generator node's children
+    pub plugin_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MapInfo {
     pub image: String,
     pub resolution: f64,
@@ -77,22 +89,70 @@ pub struct MapInfo {
     pub free_thresh: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// User-defined Handlebars export format, mirroring the frontend `ExportTemplate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    pub id: String,
+    pub name: String,
+    pub extension: String,
+    #[serde(default)]
+    pub suffix: String,
+    pub content: String,
+    /// HTML-escape `{{value}}` output as Handlebars does by default; YAML or CSV templates turn it off
+    #[serde(default = "default_escape_html")]
+    pub escape_html: bool,
+}
+
+pub fn default_escape_html() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectMapLayer {
     pub id: String,
     pub name: String,
     pub info: Option<serde_json::Value>,
     pub image_base64: String,
+    /// PNG stored next to the project (relative path) when saved with external map images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_file: Option<String>,
     pub visible: bool,
     pub opacity: f64,
     pub z_index: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn default_format_version() -> u32 {
+    crate::io::migration::CURRENT_FORMAT_VERSION
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectData {
+    /// Project file schema version. Older files are upgraded by `io::migration` before parsing.
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
     pub root_node_ids: Vec<String>,
     pub nodes: HashMap<String, WaypointNode>,
     pub map_layers: Option<Vec<ProjectMapLayer>>,
+    /// Option fields used by the project's nodes, so the file is self-describing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options_schema: Option<options::OptionsSchema>,
+    /// Named multi-target exports (`export::profile`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_profiles: Option<Vec<crate::export::profile::ExportProfile>>,
+}
+
+impl Default for ProjectData {
+    /// An empty project at the current format version.
+    fn default() -> Self {
+        ProjectData {
+            format_version: default_format_version(),
+            root_node_ids: Vec::new(),
+            nodes: HashMap::new(),
+            map_layers: None,
+            options_schema: None,
+            export_profiles: None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -135,9 +195,8 @@ mod tests {
             id: "wp-001".to_string(),
             node_type: "manual".to_string(),
             transform: Some(Transform { x: 10.0, y: 20.0, z: Some(0.5), qx: 0.0, qy: 0.0, qz: 0.0, qw: 1.0 }),
-            options: None,
-            generator_params: None,
             children_ids: Some(vec!["child-1".to_string()]),
+            ..Default::default()
         };
         let json = serde_json::to_string(&node).unwrap();
         let restored: WaypointNode = serde_json::from_str(&json).unwrap();
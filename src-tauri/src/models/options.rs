@@ -1,19 +1,63 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionDef {
     pub name: String,
+    #[serde(default)]
     pub label: String,
     #[serde(rename = "type")]
     pub option_type: String, // "float", "integer", "string", "boolean", "list"
     pub item_type: Option<String>,
     pub default: Option<serde_yaml::Value>,
+    /// Plugin manifests historically called this `options`
+    #[serde(default, alias = "options")]
     pub enum_values: Option<Vec<String>>,
+    // Constraints below are optional so older schema files keep parsing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Values must be `min` (or 0) plus a multiple of `step`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>,
+    /// Display-only unit such as "m/s"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Must be set on every waypoint where the option is visible (a default counts)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Regex that string values (or string list items) must fully match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible_when: Option<VisibleWhen>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Show an option only when another option has one of the given values,
+/// e.g. `visible_when: { option: action, equals: dock }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisibleWhen {
+    pub option: String,
+    /// A single value or a list of accepted values
+    pub equals: serde_json::Value,
+}
+
+impl VisibleWhen {
+    pub fn is_met(&self, options: &HashMap<String, serde_json::Value>) -> bool {
+        let Some(current) = options.get(&self.option) else { return false };
+        match &self.equals {
+            serde_json::Value::Array(accepted) => accepted.contains(current),
+            expected => expected == current,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionsSchema {
     pub options: Vec<OptionDef>,
 }
@@ -29,6 +73,13 @@ pub fn load_options_schema(yaml_path: &str) -> Result<OptionsSchema, String> {
     Ok(schema)
 }
 
+/// Write a schema to a standalone YAML file so it can be shared between projects.
+pub fn save_options_schema(yaml_path: &str, schema: &OptionsSchema) -> Result<(), String> {
+    let yaml = serde_yaml::to_string(schema)
+        .map_err(|e| format!("YAML serialization error: {}", e))?;
+    fs::write(yaml_path, yaml).map_err(|e| format!("Failed to write schema YAML: {}", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +122,53 @@ options:
         let result: Result<OptionsSchema, _> = serde_yaml::from_str(yaml_str);
         assert!(result.is_err(), "Should error on missing required fields");
     }
+
+    #[test]
+    fn test_schema_yaml_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.yaml");
+        let schema: OptionsSchema = serde_yaml::from_str("options:\n  - name: speed\n    label: Speed\n    type: float\n    default: 0.5\n").unwrap();
+
+        save_options_schema(path.to_str().unwrap(), &schema).unwrap();
+        let loaded = load_options_schema(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.options[0].name, "speed");
+        assert_eq!(loaded.options[0].default, Some(serde_yaml::Value::from(0.5)));
+    }
+
+    #[test]
+    fn test_parse_constraints_and_condition() {
+        let yaml_str = r#"
+options:
+  - name: action
+    label: Action
+    type: string
+    enum_values: ["dock", "wait"]
+  - name: dock_id
+    label: Dock
+    type: string
+    required: true
+    pattern: "^D[0-9]+$"
+    description: "Charging dock identifier"
+    visible_when: { option: action, equals: dock }
+  - name: speed
+    label: Speed
+    type: float
+    min: 0.0
+    max: 1.5
+    step: 0.1
+    unit: m/s
+"#;
+        let schema: OptionsSchema = serde_yaml::from_str(yaml_str).unwrap();
+        let dock = &schema.options[1];
+        assert!(dock.required);
+        assert_eq!(dock.pattern.as_deref(), Some("^D[0-9]+$"));
+        let cond = dock.visible_when.as_ref().unwrap();
+        assert!(cond.is_met(&HashMap::from([("action".to_string(), serde_json::json!("dock"))])));
+        assert!(!cond.is_met(&HashMap::from([("action".to_string(), serde_json::json!("wait"))])));
+
+        let speed = &schema.options[2];
+        assert_eq!((speed.min, speed.max, speed.step), (Some(0.0), Some(1.5), Some(0.1)));
+        assert_eq!(speed.unit.as_deref(), Some("m/s"));
+        assert!(!schema.options[0].required);
+    }
 }
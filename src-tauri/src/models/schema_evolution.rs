@@ -0,0 +1,420 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::models::options::OptionsSchema;
+use crate::models::ProjectData;
+use crate::project::inheritance;
+use std::collections::{BTreeMap, HashMap};
+
+/// One step of a schema migration, applied to the schema and to every node's options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SchemaOperation {
+    /// Rename an option key, including `visible_when` references to it
+    Rename { from: String, to: String },
+    /// Change an option's type and convert existing values
+    Retype {
+        name: String,
+        #[serde(rename = "type")]
+        option_type: String,
+        #[serde(default)]
+        item_type: Option<String>,
+    },
+    /// Write the schema default into waypoints that neither set nor inherit a value.
+    /// Without `names`, every `required` option is filled.
+    FillDefaults {
+        #[serde(default)]
+        names: Option<Vec<String>>,
+    },
+    /// Remove keys from the schema and all nodes.
+    /// Without `names`, every node key not defined in the schema is removed.
+    Strip {
+        #[serde(default)]
+        names: Option<Vec<String>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptionChange {
+    pub node_id: String,
+    pub option: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptionChangeFailure {
+    pub node_id: String,
+    pub option: String,
+    pub value: Value,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaChangeReport {
+    /// Human-readable description of the operation
+    pub operation: String,
+    /// Whether the schema itself was modified
+    pub schema_changed: bool,
+    pub changes: Vec<OptionChange>,
+    /// Values left untouched because they could not be converted or filled
+    pub failures: Vec<OptionChangeFailure>,
+}
+
+fn sorted_ids(project: &ProjectData) -> Vec<String> {
+    let mut ids: Vec<String> = project.nodes.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Convert a single (non-list) value. Lossy conversions such as 1.5 -> integer fail.
+fn convert_scalar(value: &Value, to: &str) -> Result<Value, String> {
+    let fail = || Err(format!("cannot convert {} to {}", value, to));
+    match to {
+        "float" => match value {
+            Value::Number(n) => Ok(Value::from(n.as_f64().unwrap_or_default())),
+            Value::String(s) => s.trim().parse::<f64>().map(Value::from).or_else(|_| fail()),
+            _ => fail(),
+        },
+        "integer" => {
+            let f = match value {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+            match f {
+                Some(f) if f.fract() == 0.0 => Ok(Value::from(f as i64)),
+                _ => fail(),
+            }
+        }
+        "boolean" => match value {
+            Value::Bool(_) => Ok(value.clone()),
+            Value::String(s) if s.eq_ignore_ascii_case("true") || s == "1" => Ok(Value::from(true)),
+            Value::String(s) if s.eq_ignore_ascii_case("false") || s == "0" => Ok(Value::from(false)),
+            Value::Number(n) if n.as_f64() == Some(1.0) => Ok(Value::from(true)),
+            Value::Number(n) if n.as_f64() == Some(0.0) => Ok(Value::from(false)),
+            _ => fail(),
+        },
+        "string" => scalar_to_string(value).map(Value::from).ok_or_else(|| format!("cannot convert {} to string", value)),
+        // Types this build does not know are left for the validator to flag
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Convert a value to `to`, mirroring how the UI edits lists as comma-separated text.
+pub fn convert_value(value: &Value, to: &str, item_type: Option<&str>) -> Result<Value, String> {
+    let item_type = item_type.unwrap_or("string");
+    match (value, to) {
+        (Value::Array(items), "list") => items.iter().map(|i| convert_scalar(i, item_type)).collect::<Result<Vec<_>, _>>().map(Value::from),
+        (Value::String(s), "list") => s.split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| convert_scalar(&Value::from(p), item_type))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::from),
+        (_, "list") => convert_scalar(value, item_type).map(|v| Value::from(vec![v])),
+        (Value::Array(items), "string") => items.iter()
+            .map(|i| scalar_to_string(i).ok_or_else(|| format!("cannot convert {} to string", i)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|parts| Value::from(parts.join(", "))),
+        // A single-element list collapses to its item
+        (Value::Array(items), _) if items.len() == 1 => convert_scalar(&items[0], to),
+        (Value::Array(_), _) => Err(format!("cannot convert a list to {}", to)),
+        _ => convert_scalar(value, to),
+    }
+}
+
+fn rename(project: &mut ProjectData, from: &str, to: &str, report: &mut SchemaChangeReport) -> Result<(), String> {
+    if let Some(schema) = &mut project.options_schema {
+        if schema.options.iter().any(|o| o.name == to) {
+            return Err(format!("Cannot rename '{}' to '{}': an option with that name already exists", from, to));
+        }
+        for def in &mut schema.options {
+            if def.name == from {
+                def.name = to.to_string();
+                report.schema_changed = true;
+            }
+            if let Some(cond) = def.visible_when.as_mut().filter(|c| c.option == from) {
+                cond.option = to.to_string();
+                report.schema_changed = true;
+            }
+        }
+    }
+
+    for id in sorted_ids(project) {
+        let Some(options) = project.nodes.get_mut(&id).and_then(|n| n.options.as_mut()) else { continue };
+        let Some(value) = options.get(from).cloned() else { continue };
+        if let Some(existing) = options.get(to) {
+            report.failures.push(OptionChangeFailure {
+                node_id: id,
+                option: from.to_string(),
+                value: existing.clone(),
+                reason: format!("node already has a value for '{}'", to),
+            });
+            continue;
+        }
+        options.remove(from);
+        options.insert(to.to_string(), value.clone());
+        report.changes.push(OptionChange { node_id: id.clone(), option: from.to_string(), before: Some(value.clone()), after: None });
+        report.changes.push(OptionChange { node_id: id, option: to.to_string(), before: None, after: Some(value) });
+    }
+    Ok(())
+}
+
+fn retype(project: &mut ProjectData, name: &str, to: &str, item_type: Option<&str>, report: &mut SchemaChangeReport) {
+    if let Some(def) = project.options_schema.as_mut().and_then(|s| s.options.iter_mut().find(|o| o.name == name)) {
+        def.option_type = to.to_string();
+        def.item_type = item_type.map(str::to_string);
+        if let Some(default) = def.default.as_ref().and_then(|d| serde_json::to_value(d).ok()) {
+            match convert_value(&default, to, item_type).and_then(|v| serde_yaml::to_value(v).map_err(|e| e.to_string())) {
+                Ok(converted) => def.default = Some(converted),
+                // A default that no longer fits is worse than none
+                Err(_) => def.default = None,
+            }
+        }
+        report.schema_changed = true;
+    }
+
+    for id in sorted_ids(project) {
+        let Some(options) = project.nodes.get_mut(&id).and_then(|n| n.options.as_mut()) else { continue };
+        let Some(value) = options.get(name).filter(|v| !v.is_null()).cloned() else { continue };
+        match convert_value(&value, to, item_type) {
+            Ok(converted) if converted == value => {}
+            Ok(converted) => {
+                options.insert(name.to_string(), converted.clone());
+                report.changes.push(OptionChange { node_id: id, option: name.to_string(), before: Some(value), after: Some(converted) });
+            }
+            Err(reason) => report.failures.push(OptionChangeFailure { node_id: id, option: name.to_string(), value, reason }),
+        }
+    }
+}
+
+fn fill_defaults(project: &mut ProjectData, names: Option<&[String]>, report: &mut SchemaChangeReport) -> Result<(), String> {
+    let schema: OptionsSchema = project.options_schema.clone()
+        .ok_or_else(|| "Cannot fill defaults: the project has no options schema".to_string())?;
+    let targets: Vec<_> = schema.options.iter()
+        .filter(|d| match names {
+            Some(names) => names.contains(&d.name),
+            None => d.required,
+        })
+        .collect();
+    // A value set on a generator or group already applies to its children; a default would override it
+    let parents = inheritance::parent_map(project);
+    let inherited: HashMap<String, BTreeMap<String, Value>> = project.nodes.keys()
+        .map(|id| (id.clone(), inheritance::ancestor_values(project, &parents, id)))
+        .collect();
+
+    for id in sorted_ids(project) {
+        let node = project.nodes.get_mut(&id).expect("id taken from the map");
+        if node.node_type != "manual" {
+            continue;
+        }
+        let options = node.options.get_or_insert_with(Default::default);
+        for def in &targets {
+            let is_set = |values: Option<&Value>| values.is_some_and(|v| !v.is_null());
+            if is_set(options.get(&def.name)) || is_set(inherited[&id].get(&def.name)) {
+                continue;
+            }
+            match def.default.as_ref().and_then(|d| serde_json::to_value(d).ok()).filter(|d| !d.is_null()) {
+                Some(default) => {
+                    options.insert(def.name.clone(), default.clone());
+                    report.changes.push(OptionChange { node_id: id.clone(), option: def.name.clone(), before: None, after: Some(default) });
+                }
+                None => report.failures.push(OptionChangeFailure {
+                    node_id: id.clone(),
+                    option: def.name.clone(),
+                    value: Value::Null,
+                    reason: "option has no default value".to_string(),
+                }),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn strip(project: &mut ProjectData, names: Option<&[String]>, report: &mut SchemaChangeReport) -> Result<(), String> {
+    // Without a schema every option would count as undefined
+    if names.is_none() && project.options_schema.is_none() {
+        return Err("Cannot strip: the project has no options schema".to_string());
+    }
+    let defined: Vec<String> = project.options_schema.iter().flat_map(|s| &s.options).map(|o| o.name.clone()).collect();
+    if let (Some(names), Some(schema)) = (names, &mut project.options_schema) {
+        let before = schema.options.len();
+        schema.options.retain(|o| !names.contains(&o.name));
+        report.schema_changed = schema.options.len() != before;
+    }
+
+    for id in sorted_ids(project) {
+        let Some(options) = project.nodes.get_mut(&id).and_then(|n| n.options.as_mut()) else { continue };
+        let mut removed: Vec<String> = options.keys()
+            .filter(|k| match names {
+                Some(names) => names.contains(k),
+                None => !defined.contains(k),
+            })
+            .cloned()
+            .collect();
+        removed.sort();
+        for key in removed {
+            let before = options.remove(&key);
+            report.changes.push(OptionChange { node_id: id.clone(), option: key, before, after: None });
+        }
+    }
+    Ok(())
+}
+
+fn describe(op: &SchemaOperation) -> String {
+    let list = |names: &Option<Vec<String>>, all: &str| names.as_ref().map_or_else(|| all.to_string(), |n| n.join(", "));
+    match op {
+        SchemaOperation::Rename { from, to } => format!("Rename '{}' to '{}'", from, to),
+        SchemaOperation::Retype { name, option_type, item_type: Some(item) } => format!("Change '{}' to {} of {}", name, option_type, item),
+        SchemaOperation::Retype { name, option_type, .. } => format!("Change '{}' to {}", name, option_type),
+        SchemaOperation::FillDefaults { names } => format!("Fill defaults for {}", list(names, "required options")),
+        SchemaOperation::Strip { names } => format!("Remove {}", list(names, "options not in the schema")),
+    }
+}
+
+/// Apply operations in order and report what each one changed.
+/// Nothing is modified if any operation fails outright.
+pub fn apply_schema_operations(project: &mut ProjectData, operations: &[SchemaOperation]) -> Result<Vec<SchemaChangeReport>, String> {
+    let mut working = project.clone();
+    let mut reports = Vec::with_capacity(operations.len());
+
+    for op in operations {
+        let mut report = SchemaChangeReport { operation: describe(op), ..Default::default() };
+        match op {
+            SchemaOperation::Rename { from, to } => rename(&mut working, from, to, &mut report)?,
+            SchemaOperation::Retype { name, option_type, item_type } => {
+                retype(&mut working, name, option_type, item_type.as_deref(), &mut report)
+            }
+            SchemaOperation::FillDefaults { names } => fill_defaults(&mut working, names.as_deref(), &mut report)?,
+            SchemaOperation::Strip { names } => strip(&mut working, names.as_deref(), &mut report)?,
+        }
+        reports.push(report);
+    }
+
+    *project = working;
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WaypointNode;
+    use crate::project::test_support::{node, node_with, project as project_with};
+    use serde_json::json;
+
+    fn project(schema_yaml: &str, nodes: Vec<(&str, Value)>) -> ProjectData {
+        ProjectData {
+            root_node_ids: nodes.iter().map(|(id, _)| id.to_string()).collect(),
+            nodes: nodes.into_iter().map(|(id, options)| {
+                (id.to_string(), WaypointNode {
+                    id: id.to_string(),
+                    node_type: "manual".to_string(),
+                    transform: None,
+                    options: Some(serde_json::from_value::<HashMap<String, Value>>(options).unwrap()),
+                    generator_params: None,
+                    children_ids: None,
+                    plugin_id: None,
+                })
+            }).collect(),
+            options_schema: Some(serde_yaml::from_str(schema_yaml).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn ops(v: Value) -> Vec<SchemaOperation> {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn test_rename_updates_nodes_and_conditions() {
+        let mut p = project(
+            "options:\n  - { name: act, label: Action, type: string }\n  - { name: dock_id, label: Dock, type: string, visible_when: { option: act, equals: dock } }\n",
+            vec![("a", json!({ "act": "dock" })), ("b", json!({}))],
+        );
+        let reports = apply_schema_operations(&mut p, &ops(json!([{ "op": "rename", "from": "act", "to": "action" }]))).unwrap();
+
+        assert!(reports[0].schema_changed);
+        assert_eq!(reports[0].changes.len(), 2);
+        assert_eq!(p.nodes["a"].options.as_ref().unwrap()["action"], json!("dock"));
+        let schema = p.options_schema.unwrap();
+        assert_eq!(schema.options[0].name, "action");
+        assert_eq!(schema.options[1].visible_when.as_ref().unwrap().option, "action");
+    }
+
+    #[test]
+    fn test_retype_converts_values_and_reports_failures() {
+        let mut p = project(
+            "options:\n  - { name: retries, label: Retries, type: integer, default: 2 }\n  - { name: tags, label: Tags, type: string }\n",
+            vec![("a", json!({ "retries": 3, "tags": "a, b" })), ("b", json!({ "retries": 1.5, "tags": "" }))],
+        );
+        let reports = apply_schema_operations(&mut p, &ops(json!([
+            { "op": "retype", "name": "tags", "type": "list", "item_type": "string" },
+            { "op": "retype", "name": "retries", "type": "string" },
+            { "op": "retype", "name": "retries", "type": "integer" }
+        ]))).unwrap();
+
+        assert_eq!(p.nodes["a"].options.as_ref().unwrap()["tags"], json!(["a", "b"]));
+        assert_eq!(p.nodes["b"].options.as_ref().unwrap()["tags"], json!([]));
+        assert_eq!(p.nodes["a"].options.as_ref().unwrap()["retries"], json!(3));
+        // "1.5" cannot become an integer and is left as the string
+        assert_eq!(reports[2].failures.len(), 1);
+        assert_eq!(p.nodes["b"].options.as_ref().unwrap()["retries"], json!("1.5"));
+        assert_eq!(p.options_schema.unwrap().options[0].default, Some(serde_yaml::Value::from(2)));
+    }
+
+    #[test]
+    fn test_fill_defaults_and_strip() {
+        let mut p = project(
+            "options:\n  - { name: speed, label: Speed, type: float, default: 0.5, required: true }\n  - { name: dock, label: Dock, type: string, required: true }\n",
+            vec![("a", json!({ "old": 1 })), ("b", json!({ "speed": 1.0 }))],
+        );
+        let reports = apply_schema_operations(&mut p, &ops(json!([{ "op": "fill_defaults" }, { "op": "strip" }]))).unwrap();
+
+        assert_eq!(reports[0].changes, vec![OptionChange { node_id: "a".into(), option: "speed".into(), before: None, after: Some(json!(0.5)) }]);
+        assert_eq!(reports[0].failures.len(), 2, "dock has no default on either node");
+        assert_eq!(reports[1].changes.len(), 1);
+        assert!(!p.nodes["a"].options.as_ref().unwrap().contains_key("old"));
+
+        // Without a schema nothing counts as defined, so a blanket strip is refused
+        p.options_schema = None;
+        let err = apply_schema_operations(&mut p, &ops(json!([{ "op": "strip" }]))).unwrap_err();
+        assert_eq!(err, "Cannot strip: the project has no options schema");
+        assert!(p.nodes["b"].options.as_ref().unwrap().contains_key("speed"));
+    }
+
+    #[test]
+    fn test_fill_defaults_keeps_values_inherited_from_a_generator() {
+        let mut p = ProjectData {
+            options_schema: Some(serde_yaml::from_str("options:\n  - { name: speed, label: Speed, type: float, default: 0.5, required: true }\n").unwrap()),
+            ..project_with(&["g", "m"], vec![node_with("g", json!({ "speed": 0.2 }), &["a"]), node("a", &[]), node("m", &[])])
+        };
+        let reports = apply_schema_operations(&mut p, &ops(json!([{ "op": "fill_defaults" }]))).unwrap();
+
+        assert_eq!(reports[0].changes, vec![OptionChange { node_id: "m".into(), option: "speed".into(), before: None, after: Some(json!(0.5)) }]);
+        assert!(!p.nodes["a"].options.as_ref().unwrap().contains_key("speed"), "a keeps the generator's speed");
+    }
+
+    #[test]
+    fn test_failed_operation_leaves_project_untouched() {
+        let mut p = project(
+            "options:\n  - { name: a, label: A, type: string }\n  - { name: b, label: B, type: string }\n",
+            vec![("n", json!({ "a": "x" }))],
+        );
+        let result = apply_schema_operations(&mut p, &ops(json!([
+            { "op": "strip", "names": ["a"] },
+            { "op": "rename", "from": "a", "to": "b" }
+        ])));
+        assert!(result.is_err());
+        assert_eq!(p.nodes["n"].options.as_ref().unwrap()["a"], json!("x"));
+        assert_eq!(p.options_schema.unwrap().options.len(), 2);
+    }
+}
@@ -0,0 +1,412 @@
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use crate::models::options::{OptionDef, OptionsSchema};
+use crate::models::{ProjectData, WaypointNode};
+use crate::project::inheritance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OptionIssue {
+    /// Value (or a list item) has the wrong JSON type
+    TypeMismatch { expected: String, found: String },
+    /// Value (or a list item) is not one of `enum_values`
+    NotInEnum { value: String, allowed: Vec<String> },
+    /// Number outside `min`/`max`
+    OutOfRange { value: f64, min: Option<f64>, max: Option<f64> },
+    /// Number not on the `step` grid
+    StepMismatch { value: f64, step: f64 },
+    /// String does not fully match `pattern`
+    PatternMismatch { value: String, pattern: String },
+    /// The schema's `pattern` is not a valid regex
+    InvalidPattern { pattern: String, message: String },
+    /// Key set on the node but not defined in the schema
+    UnknownKey,
+    /// Visible key without a default and not set on the waypoint.
+    /// An error for `required` options, otherwise a warning.
+    MissingKey,
+}
+
+impl fmt::Display for OptionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |b: &Option<f64>| b.map_or_else(|| "-".to_string(), |v| v.to_string());
+        match self {
+            OptionIssue::TypeMismatch { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            OptionIssue::NotInEnum { value, allowed } => write!(f, "'{}' is not one of [{}]", value, allowed.join(", ")),
+            OptionIssue::OutOfRange { value, min, max } => write!(f, "{} is outside [{}, {}]", value, bound(min), bound(max)),
+            OptionIssue::StepMismatch { value, step } => write!(f, "{} is not a multiple of step {}", value, step),
+            OptionIssue::PatternMismatch { value, pattern } => write!(f, "'{}' does not match /{}/", value, pattern),
+            OptionIssue::InvalidPattern { pattern, message } => write!(f, "invalid pattern /{}/: {}", pattern, message),
+            OptionIssue::UnknownKey => write!(f, "not defined in the schema"),
+            OptionIssue::MissingKey => write!(f, "value is required"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptionDiagnostic {
+    pub node_id: String,
+    pub option: String,
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub issue: OptionIssue,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OptionReport {
+    pub diagnostics: Vec<OptionDiagnostic>,
+}
+
+impl OptionReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "list",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, option_type: &str) -> bool {
+    match option_type {
+        "float" => value.is_number(),
+        // The UI stores numbers as JS numbers, so 3.0 still counts as an integer
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "list" => value.is_array(),
+        // Unknown types come from newer schemas; don't reject what we can't check
+        _ => true,
+    }
+}
+
+fn enum_label(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Tolerance for float comparisons in range and step checks
+const EPSILON: f64 = 1e-9;
+
+/// Per-schema state shared across nodes, so each `pattern` is compiled once.
+pub struct OptionValidator<'a> {
+    schema: &'a OptionsSchema,
+    patterns: HashMap<&'a str, Result<Regex, String>>,
+}
+
+impl<'a> OptionValidator<'a> {
+    pub fn new(schema: &'a OptionsSchema) -> Self {
+        let patterns = schema.options.iter()
+            .filter_map(|def| {
+                // Anchored so the whole value has to match, not just a substring
+                let pattern = def.pattern.as_ref()?;
+                let compiled = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| e.to_string());
+                Some((def.name.as_str(), compiled))
+            })
+            .collect();
+        Self { schema, patterns }
+    }
+
+    fn check_scalar(&self, def: &OptionDef, value: &Value, option_type: &str, issues: &mut Vec<OptionIssue>) {
+        if !matches_type(value, option_type) {
+            issues.push(OptionIssue::TypeMismatch {
+                expected: option_type.to_string(),
+                found: json_type_name(value).to_string(),
+            });
+            return;
+        }
+        if let Some(allowed) = def.enum_values.as_ref().filter(|e| !e.is_empty()) {
+            let label = enum_label(value);
+            if !allowed.contains(&label) {
+                issues.push(OptionIssue::NotInEnum { value: label, allowed: allowed.clone() });
+            }
+        }
+        if let Some(n) = value.as_f64() {
+            if def.min.is_some_and(|min| n < min - EPSILON) || def.max.is_some_and(|max| n > max + EPSILON) {
+                issues.push(OptionIssue::OutOfRange { value: n, min: def.min, max: def.max });
+            }
+            if let Some(step) = def.step.filter(|s| *s > 0.0) {
+                let steps = (n - def.min.unwrap_or(0.0)) / step;
+                if (steps - steps.round()).abs() > 1e-6 {
+                    issues.push(OptionIssue::StepMismatch { value: n, step });
+                }
+            }
+        }
+        if let (Some(text), Some(pattern)) = (value.as_str(), &def.pattern) {
+            match self.patterns.get(def.name.as_str()) {
+                Some(Ok(re)) if !re.is_match(text) => {
+                    issues.push(OptionIssue::PatternMismatch { value: text.to_string(), pattern: pattern.clone() });
+                }
+                Some(Err(message)) => {
+                    issues.push(OptionIssue::InvalidPattern { pattern: pattern.clone(), message: message.clone() });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check a single value against its definition. List items are checked against `item_type`.
+    pub fn validate_value(&self, def: &OptionDef, value: &Value) -> Vec<OptionIssue> {
+        let mut issues = Vec::new();
+        if def.option_type == "list" {
+            let Some(items) = value.as_array() else {
+                issues.push(OptionIssue::TypeMismatch { expected: "list".to_string(), found: json_type_name(value).to_string() });
+                return issues;
+            };
+            let item_type = def.item_type.as_deref().unwrap_or("string");
+            for item in items {
+                self.check_scalar(def, item, item_type, &mut issues);
+            }
+        } else {
+            self.check_scalar(def, value, &def.option_type, &mut issues);
+        }
+        issues
+    }
+
+    /// Diagnostics for one node. Missing keys are only reported for manual waypoints,
+    /// since generators do not carry exported options themselves, and hidden options
+    /// (`visible_when` not met) are never missing.
+    pub fn validate_node(&self, node: &WaypointNode) -> Vec<OptionDiagnostic> {
+        let empty = HashMap::new();
+        let options = node.options.as_ref().unwrap_or(&empty);
+        self.validate_values(&node.id, options, node.node_type == "manual")
+    }
+
+    /// Diagnostics for a set of values owned by `owner_id` (a node or a plugin).
+    /// `check_missing` enables missing/required checks for visible options.
+    pub fn validate_values(&self, owner_id: &str, options: &HashMap<String, Value>, check_missing: bool) -> Vec<OptionDiagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut push = |option: &str, issue: OptionIssue, severity: Severity| {
+            diagnostics.push(OptionDiagnostic { node_id: owner_id.to_string(), option: option.to_string(), severity, issue });
+        };
+
+        for def in &self.schema.options {
+            let value = options.get(&def.name).filter(|v| !v.is_null());
+            let blank = value.is_none_or(|v| v.as_str() == Some("") || v.as_array().is_some_and(|a| a.is_empty()));
+            if let Some(value) = value {
+                for issue in self.validate_value(def, value) {
+                    push(&def.name, issue, Severity::Error);
+                }
+            }
+
+            let visible = def.visible_when.as_ref().is_none_or(|c| c.is_met(&effective_values(self.schema, options)));
+            if check_missing && visible {
+                if def.required && blank && (value.is_some() || !has_default(def)) {
+                    push(&def.name, OptionIssue::MissingKey, Severity::Error);
+                } else if value.is_none() && !has_default(def) {
+                    push(&def.name, OptionIssue::MissingKey, Severity::Warning);
+                }
+            }
+        }
+
+        let mut unknown: Vec<&String> = options.keys()
+            .filter(|k| !self.schema.options.iter().any(|d| &d.name == *k))
+            .collect();
+        unknown.sort();
+        for key in unknown {
+            push(key, OptionIssue::UnknownKey, Severity::Warning);
+        }
+        diagnostics
+    }
+}
+
+fn has_default(def: &OptionDef) -> bool {
+    !matches!(def.default, None | Some(serde_yaml::Value::Null))
+}
+
+/// Node values overlaid on schema defaults, as the exporter sees them.
+fn effective_values(schema: &OptionsSchema, options: &HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut values: HashMap<String, Value> = schema.options.iter()
+        .filter_map(|d| Some((d.name.clone(), serde_json::to_value(d.default.as_ref()?).ok()?)))
+        .collect();
+    values.extend(options.iter().map(|(k, v)| (k.clone(), v.clone())));
+    values
+}
+
+pub fn validate_option_value(def: &OptionDef, value: &Value) -> Vec<OptionIssue> {
+    let schema = OptionsSchema { options: vec![def.clone()] };
+    OptionValidator::new(&schema).validate_value(def, value)
+}
+
+pub fn validate_node_options(schema: &OptionsSchema, node: &WaypointNode) -> Vec<OptionDiagnostic> {
+    OptionValidator::new(schema).validate_node(node)
+}
+
+/// Validate every node's options against the project's schema, sorted by node id.
+/// Values inherited from ancestors count towards missing and required checks.
+/// A project without a schema has nothing to check against.
+pub fn validate_project_options(project: &ProjectData) -> OptionReport {
+    let Some(schema) = &project.options_schema else {
+        return OptionReport::default();
+    };
+    let validator = OptionValidator::new(schema);
+    let parents = inheritance::parent_map(project);
+    let mut ids: Vec<&String> = project.nodes.keys().collect();
+    ids.sort();
+
+    let mut diagnostics = Vec::new();
+    for id in ids {
+        let node = &project.nodes[id];
+        let empty = HashMap::new();
+        let own = node.options.as_ref().unwrap_or(&empty);
+        // Inherited values satisfy missing/required checks; their type issues
+        // are reported on the ancestor that sets them, not on every descendant
+        let mut effective: HashMap<String, Value> = inheritance::inherited_values(project, &parents, id).into_iter().collect();
+        effective.extend(own.iter().map(|(k, v)| (k.clone(), v.clone())));
+        diagnostics.extend(
+            validator
+                .validate_values(id, &effective, node.node_type == "manual")
+                .into_iter()
+                .filter(|d| own.contains_key(&d.option) || d.issue == OptionIssue::MissingKey),
+        );
+    }
+    OptionReport { diagnostics }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> OptionsSchema {
+        serde_yaml::from_str(r#"
+options:
+  - name: speed
+    label: Speed
+    type: float
+    default: 0.5
+  - name: retries
+    label: Retries
+    type: integer
+  - name: actions
+    label: Actions
+    type: list
+    item_type: string
+    enum_values: ["dock", "undock"]
+"#).unwrap()
+    }
+
+    fn waypoint(options: serde_json::Value) -> WaypointNode {
+        WaypointNode {
+            id: "wp".to_string(),
+            node_type: "manual".to_string(),
+            options: Some(serde_json::from_value::<HashMap<String, Value>>(options).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_options_have_no_diagnostics() {
+        let node = waypoint(json!({ "speed": 1, "retries": 3.0, "actions": ["dock"] }));
+        assert!(validate_node_options(&schema(), &node).is_empty());
+    }
+
+    #[test]
+    fn test_type_and_enum_errors() {
+        let node = waypoint(json!({ "speed": "fast", "retries": 1.5, "actions": ["dock", "fly", 3] }));
+        let issues: Vec<(String, OptionIssue)> = validate_node_options(&schema(), &node)
+            .into_iter().map(|d| (d.option, d.issue)).collect();
+
+        assert!(issues.contains(&("speed".into(), OptionIssue::TypeMismatch { expected: "float".into(), found: "string".into() })));
+        assert!(issues.contains(&("retries".into(), OptionIssue::TypeMismatch { expected: "integer".into(), found: "float".into() })));
+        assert!(issues.iter().any(|(k, i)| k == "actions" && matches!(i, OptionIssue::NotInEnum { value, .. } if value == "fly")));
+        assert!(issues.iter().any(|(k, i)| k == "actions" && matches!(i, OptionIssue::TypeMismatch { found, .. } if found == "integer")));
+    }
+
+    #[test]
+    fn test_missing_and_unknown_keys() {
+        let node = waypoint(json!({ "legacy_flag": true }));
+        let diagnostics = validate_node_options(&schema(), &node);
+        let kinds: Vec<(&str, &OptionIssue, Severity)> = diagnostics.iter()
+            .map(|d| (d.option.as_str(), &d.issue, d.severity)).collect();
+
+        // "speed" has a default, so only the other two are missing; neither is required
+        assert_eq!(kinds, vec![
+            ("retries", &OptionIssue::MissingKey, Severity::Warning),
+            ("actions", &OptionIssue::MissingKey, Severity::Warning),
+            ("legacy_flag", &OptionIssue::UnknownKey, Severity::Warning),
+        ]);
+    }
+
+    fn constrained_schema() -> OptionsSchema {
+        serde_yaml::from_str(r#"
+options:
+  - name: action
+    label: Action
+    type: string
+    default: wait
+  - name: dock_id
+    label: Dock
+    type: string
+    required: true
+    pattern: "D[0-9]+"
+    visible_when: { option: action, equals: dock }
+  - name: speed
+    label: Speed
+    type: float
+    default: 0.5
+    min: 0.0
+    max: 1.5
+    step: 0.1
+"#).unwrap()
+    }
+
+    #[test]
+    fn test_range_step_and_pattern_constraints() {
+        let node = waypoint(json!({ "action": "dock", "dock_id": "X12", "speed": 2.05 }));
+        let issues: Vec<OptionIssue> = validate_node_options(&constrained_schema(), &node)
+            .into_iter().map(|d| d.issue).collect();
+
+        assert!(issues.contains(&OptionIssue::PatternMismatch { value: "X12".into(), pattern: "D[0-9]+".into() }));
+        assert!(issues.contains(&OptionIssue::OutOfRange { value: 2.05, min: Some(0.0), max: Some(1.5) }));
+        assert!(issues.contains(&OptionIssue::StepMismatch { value: 2.05, step: 0.1 }));
+
+        let ok = waypoint(json!({ "action": "dock", "dock_id": "D12", "speed": 0.3 }));
+        assert!(validate_node_options(&constrained_schema(), &ok).is_empty());
+    }
+
+    #[test]
+    fn test_required_only_applies_when_visible() {
+        // action defaults to "wait", so dock_id is hidden and may be absent
+        let hidden = waypoint(json!({}));
+        assert!(validate_node_options(&constrained_schema(), &hidden).is_empty());
+
+        let shown = waypoint(json!({ "action": "dock", "dock_id": "" }));
+        let diagnostics = validate_node_options(&constrained_schema(), &shown);
+        assert!(diagnostics.iter().any(|d| d.option == "dock_id" && d.issue == OptionIssue::MissingKey && d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_inherited_values_satisfy_required_options() {
+        let mut generator = waypoint(json!({ "retries": 2 }));
+        generator.id = "g".to_string();
+        generator.node_type = "generator".to_string();
+        generator.children_ids = Some(vec!["wp".to_string()]);
+        let project = ProjectData {
+            root_node_ids: vec!["g".to_string()],
+            nodes: [generator, waypoint(json!({ "actions": ["dock"] }))].into_iter().map(|n| (n.id.clone(), n)).collect(),
+            options_schema: Some(schema()),
+            ..Default::default()
+        };
+        assert!(validate_project_options(&project).diagnostics.is_empty());
+    }
+}
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::models::options::{OptionDef, OptionsSchema};
+use crate::models::validation::{OptionValidator, Severity};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -31,8 +34,41 @@ pub struct PluginManifest {
     pub inputs: Vec<PluginInputDef>,
     #[serde(default)]
     pub needs: Vec<String>,
+    /// Same schema language as project options
     #[serde(default)]
-    pub properties: Vec<serde_json::Value>,
+    pub properties: Vec<OptionDef>,
+}
+
+impl PluginManifest {
+    /// Check `context.properties` against the declared properties before the plugin is spawned.
+    pub fn validate_params(&self, context_json: &str) -> Result<(), String> {
+        let context: serde_json::Value = serde_json::from_str(context_json)
+            .map_err(|e| format!("Invalid plugin context JSON: {}", e))?;
+        let params: HashMap<String, serde_json::Value> = match context.get("properties") {
+            Some(p) if !p.is_null() => serde_json::from_value(p.clone())
+                .map_err(|e| format!("Plugin properties must be an object: {}", e))?,
+            _ => HashMap::new(),
+        };
+
+        let schema = OptionsSchema { options: self.properties.clone() };
+        let errors: Vec<String> = OptionValidator::new(&schema)
+            .validate_values(&self.name, &params, true)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| {
+                let label = self.properties.iter()
+                    .find(|p| p.name == d.option && !p.label.is_empty())
+                    .map_or(d.option.clone(), |p| format!("{} ({})", p.label, d.option));
+                format!("- {}: {}", label, d.issue)
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid parameters for plugin '{}':\n{}", self.name, errors.join("\n")))
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -97,5 +133,49 @@ mod tests {
         assert!(manifest.properties.is_empty());
         assert!(manifest.needs.is_empty());
     }
-}
 
+    #[test]
+    fn test_legacy_options_key_maps_to_enum_values() {
+        let json = r#"{
+            "name": "Rect",
+            "type": "python",
+            "executable": "main.py",
+            "properties": [{"name": "corner", "label": "Corner", "type": "string", "options": ["A", "B"]}]
+        }"#;
+        let manifest: PluginManifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.properties[0].enum_values, Some(vec!["A".to_string(), "B".to_string()]));
+    }
+
+    #[test]
+    fn test_validate_params_reports_bad_values() {
+        let json = r#"{
+            "name": "Line",
+            "type": "python",
+            "executable": "main.py",
+            "properties": [
+                {"name": "num_points", "label": "Number of Points", "type": "integer", "default": 5, "min": 1},
+                {"name": "mode", "label": "Mode", "type": "string", "enum_values": ["a", "b"]}
+            ]
+        }"#;
+        let manifest: PluginManifest = serde_json::from_str(json).unwrap();
+
+        assert!(manifest.validate_params(r#"{"properties": {"num_points": 3, "mode": "a"}}"#).is_ok());
+        let err = manifest.validate_params(r#"{"properties": {"num_points": 0, "mode": "c"}}"#).unwrap_err();
+        assert!(err.contains("Number of Points (num_points): 0 is outside [1, -]"), "{}", err);
+        assert!(err.contains("'c' is not one of [a, b]"), "{}", err);
+    }
+
+    #[test]
+    fn test_builtin_manifests_parse_with_typed_properties() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        for dir in ["python_sdk/line_generator", "python_sdk/rect_sweep_generator", "python_sdk/sweep_generator", "rust_plugins/sweep_generator_rs"] {
+            let Ok(content) = std::fs::read_to_string(root.join(dir).join("manifest.json")) else { continue };
+            let manifest: PluginManifest = serde_json::from_str(&content).unwrap_or_else(|e| panic!("{}: {}", dir, e));
+            let defaults: serde_json::Map<String, serde_json::Value> = manifest.properties.iter()
+                .filter_map(|p| Some((p.name.clone(), serde_json::to_value(p.default.as_ref()?).ok()?)))
+                .collect();
+            let context = serde_json::json!({ "properties": defaults }).to_string();
+            manifest.validate_params(&context).unwrap_or_else(|e| panic!("{}: {}", dir, e));
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use crate::io;
+use crate::models::{ProjectData, ProjectMapLayer, WaypointNode};
+
+/// Position/yaw changes smaller than this are treated as float noise.
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSummary {
+    pub id: String,
+    pub node_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeMove {
+    pub id: String,
+    pub dx: f64,
+    pub dy: f64,
+    pub dz: f64,
+    pub distance: f64,
+    /// Yaw change in radians, normalized to (-pi, pi]
+    pub dyaw: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    pub node_id: String,
+    pub key: String,
+    /// `None` when the key was added
+    pub before: Option<Value>,
+    /// `None` when the key was removed
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reparent {
+    pub id: String,
+    /// `None` means the node was a root
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderChange {
+    /// `None` for `root_node_ids`
+    pub parent_id: Option<String>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerChange {
+    pub id: String,
+    pub name: String,
+    pub kind: LayerChangeKind,
+    /// Changed fields for `Modified`; `image` stands for the map bitmap
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProjectDiff {
+    pub added_nodes: Vec<NodeSummary>,
+    pub removed_nodes: Vec<NodeSummary>,
+    pub moved_nodes: Vec<NodeMove>,
+    pub type_changes: Vec<ValueChange>,
+    pub option_changes: Vec<ValueChange>,
+    pub generator_param_changes: Vec<ValueChange>,
+    pub reparented: Vec<Reparent>,
+    pub order_changes: Vec<OrderChange>,
+    pub layer_changes: Vec<LayerChange>,
+    /// Names of option definitions added, removed or edited in the project's schema
+    pub schema_changes: Vec<String>,
+    /// Human-readable rendering of the fields above
+    pub summary: String,
+}
+
+impl ProjectDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.moved_nodes.is_empty()
+            && self.type_changes.is_empty()
+            && self.option_changes.is_empty()
+            && self.generator_param_changes.is_empty()
+            && self.reparented.is_empty()
+            && self.order_changes.is_empty()
+            && self.layer_changes.is_empty()
+            && self.schema_changes.is_empty()
+    }
+}
+
+fn summary_of(node: &WaypointNode) -> NodeSummary {
+    NodeSummary { id: node.id.clone(), node_type: node.node_type.clone() }
+}
+
+fn normalize_angle(a: f64) -> f64 {
+    let mut a = a % (2.0 * PI);
+    if a <= -PI {
+        a += 2.0 * PI;
+    } else if a > PI {
+        a -= 2.0 * PI;
+    }
+    a
+}
+
+fn node_move(before: &WaypointNode, after: &WaypointNode) -> Option<NodeMove> {
+    let (b, a) = (before.transform.as_ref()?, after.transform.as_ref()?);
+    let (dx, dy, dz) = (a.x - b.x, a.y - b.y, a.z.unwrap_or(0.0) - b.z.unwrap_or(0.0));
+    let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+    let dyaw = normalize_angle(a.yaw() - b.yaw());
+    if distance < EPSILON && dyaw.abs() < EPSILON {
+        return None;
+    }
+    Some(NodeMove { id: after.id.clone(), dx, dy, dz, distance, dyaw })
+}
+
+fn map_changes(id: &str, before: Option<&HashMap<String, Value>>, after: Option<&HashMap<String, Value>>) -> Vec<ValueChange> {
+    let empty = HashMap::new();
+    let (b, a) = (before.unwrap_or(&empty), after.unwrap_or(&empty));
+    let keys: BTreeSet<&String> = b.keys().chain(a.keys()).collect();
+    keys.into_iter()
+        .filter(|k| b.get(*k) != a.get(*k))
+        .map(|k| ValueChange {
+            node_id: id.to_string(),
+            key: k.clone(),
+            before: b.get(k).cloned(),
+            after: a.get(k).cloned(),
+        })
+        .collect()
+}
+
+/// Parent of every node; roots map to `None`.
+fn parents(project: &ProjectData) -> HashMap<&str, Option<&str>> {
+    let mut map = HashMap::new();
+    for root in &project.root_node_ids {
+        map.entry(root.as_str()).or_insert(None);
+    }
+    let mut keys: Vec<&String> = project.nodes.keys().collect();
+    keys.sort();
+    for key in keys {
+        for child in project.nodes[key].children_ids.iter().flatten() {
+            map.entry(child.as_str()).or_insert(Some(key.as_str()));
+        }
+    }
+    map
+}
+
+/// Report an order change only when the ids present in both lists moved relative to each other.
+fn order_change(parent_id: Option<&str>, before: &[String], after: &[String]) -> Option<OrderChange> {
+    let common_b: Vec<&String> = before.iter().filter(|id| after.contains(id)).collect();
+    let common_a: Vec<&String> = after.iter().filter(|id| before.contains(id)).collect();
+    if common_b == common_a {
+        return None;
+    }
+    Some(OrderChange {
+        parent_id: parent_id.map(str::to_string),
+        before: before.to_vec(),
+        after: after.to_vec(),
+    })
+}
+
+fn layer_fields(before: &ProjectMapLayer, after: &ProjectMapLayer) -> Vec<String> {
+    let mut fields = Vec::new();
+    if before.name != after.name {
+        fields.push("name".to_string());
+    }
+    if before.image_base64 != after.image_base64 || before.image_file != after.image_file {
+        fields.push("image".to_string());
+    }
+    if before.info != after.info {
+        fields.push("info".to_string());
+    }
+    if before.visible != after.visible {
+        fields.push("visible".to_string());
+    }
+    if (before.opacity - after.opacity).abs() > EPSILON {
+        fields.push("opacity".to_string());
+    }
+    if before.z_index != after.z_index {
+        fields.push("z_index".to_string());
+    }
+    fields
+}
+
+fn layer_changes(before: &ProjectData, after: &ProjectData) -> Vec<LayerChange> {
+    let index = |p: &ProjectData| -> BTreeMap<String, ProjectMapLayer> {
+        p.map_layers.iter().flatten().map(|l| (l.id.clone(), l.clone())).collect()
+    };
+    let (b, a) = (index(before), index(after));
+    let ids: BTreeSet<&String> = b.keys().chain(a.keys()).collect();
+
+    let mut changes = Vec::new();
+    for id in ids {
+        let change = match (b.get(id), a.get(id)) {
+            (None, Some(l)) => LayerChange { id: id.clone(), name: l.name.clone(), kind: LayerChangeKind::Added, fields: vec![] },
+            (Some(l), None) => LayerChange { id: id.clone(), name: l.name.clone(), kind: LayerChangeKind::Removed, fields: vec![] },
+            (Some(old), Some(new)) => {
+                let fields = layer_fields(old, new);
+                if fields.is_empty() {
+                    continue;
+                }
+                LayerChange { id: id.clone(), name: new.name.clone(), kind: LayerChangeKind::Modified, fields }
+            }
+            (None, None) => continue,
+        };
+        changes.push(change);
+    }
+    changes
+}
+
+fn schema_changes(before: &ProjectData, after: &ProjectData) -> Vec<String> {
+    let index = |p: &ProjectData| -> BTreeMap<String, Value> {
+        p.options_schema.iter()
+            .flat_map(|s| &s.options)
+            .map(|o| (o.name.clone(), serde_json::to_value(o).unwrap_or_default()))
+            .collect()
+    };
+    let (b, a) = (index(before), index(after));
+    let names: BTreeSet<&String> = b.keys().chain(a.keys()).collect();
+    names.into_iter().filter(|n| b.get(*n) != a.get(*n)).cloned().collect()
+}
+
+/// Compare two projects node by node. Nodes are matched by id.
+pub fn diff_projects(before: &ProjectData, after: &ProjectData) -> ProjectDiff {
+    let mut diff = ProjectDiff::default();
+    let ids: BTreeSet<&String> = before.nodes.keys().chain(after.nodes.keys()).collect();
+    let (parents_b, parents_a) = (parents(before), parents(after));
+
+    for id in ids {
+        match (before.nodes.get(id), after.nodes.get(id)) {
+            (None, Some(n)) => diff.added_nodes.push(summary_of(n)),
+            (Some(n), None) => diff.removed_nodes.push(summary_of(n)),
+            (Some(b), Some(a)) => {
+                diff.moved_nodes.extend(node_move(b, a));
+                if b.node_type != a.node_type {
+                    diff.type_changes.push(ValueChange {
+                        node_id: id.clone(),
+                        key: "type".to_string(),
+                        before: Some(Value::from(b.node_type.clone())),
+                        after: Some(Value::from(a.node_type.clone())),
+                    });
+                }
+                diff.option_changes.extend(map_changes(id, b.options.as_ref(), a.options.as_ref()));
+                diff.generator_param_changes.extend(map_changes(id, b.generator_params.as_ref(), a.generator_params.as_ref()));
+
+                let (from, to) = (parents_b.get(id.as_str()).copied().flatten(), parents_a.get(id.as_str()).copied().flatten());
+                if from != to {
+                    diff.reparented.push(Reparent { id: id.clone(), from: from.map(str::to_string), to: to.map(str::to_string) });
+                }
+                let children = |n: &WaypointNode| n.children_ids.clone().unwrap_or_default();
+                diff.order_changes.extend(order_change(Some(id), &children(b), &children(a)));
+            }
+            (None, None) => {}
+        }
+    }
+    if let Some(change) = order_change(None, &before.root_node_ids, &after.root_node_ids) {
+        diff.order_changes.insert(0, change);
+    }
+    diff.layer_changes = layer_changes(before, after);
+    diff.schema_changes = schema_changes(before, after);
+    diff.summary = render_summary(&diff);
+    diff
+}
+
+fn fmt_value(v: &Option<Value>) -> String {
+    v.as_ref().map_or_else(|| "(none)".to_string(), |v| v.to_string())
+}
+
+fn fmt_parent(p: &Option<String>) -> &str {
+    p.as_deref().unwrap_or("(root)")
+}
+
+pub fn render_summary(diff: &ProjectDiff) -> String {
+    if diff.is_empty() {
+        return "No changes.\n".to_string();
+    }
+    let mut out = String::new();
+    let mut section = |title: &str, lines: Vec<String>| {
+        if lines.is_empty() {
+            return;
+        }
+        let _ = writeln!(out, "{} ({}):", title, lines.len());
+        for line in lines {
+            let _ = writeln!(out, "  {}", line);
+        }
+    };
+
+    section("Added nodes", diff.added_nodes.iter().map(|n| format!("+ {} [{}]", n.id, n.node_type)).collect());
+    section("Removed nodes", diff.removed_nodes.iter().map(|n| format!("- {} [{}]", n.id, n.node_type)).collect());
+    section("Moved nodes", diff.moved_nodes.iter().map(|m| {
+        format!(
+            "{}: dx={:+.3} dy={:+.3} dz={:+.3} (distance {:.3} m), yaw {:+.1} deg",
+            m.id, m.dx, m.dy, m.dz, m.distance, m.dyaw.to_degrees()
+        )
+    }).collect());
+    section("Type changes", diff.type_changes.iter().map(|c| {
+        format!("{}: {} -> {}", c.node_id, fmt_value(&c.before), fmt_value(&c.after))
+    }).collect());
+    section("Option changes", diff.option_changes.iter().map(|c| {
+        format!("{}.{}: {} -> {}", c.node_id, c.key, fmt_value(&c.before), fmt_value(&c.after))
+    }).collect());
+    // Generator params can hold large interaction data, so values are not printed
+    section("Generator parameter changes", diff.generator_param_changes.iter().map(|c| {
+        format!("{}.{}", c.node_id, c.key)
+    }).collect());
+    section("Reparented nodes", diff.reparented.iter().map(|r| {
+        format!("{}: {} -> {}", r.id, fmt_parent(&r.from), fmt_parent(&r.to))
+    }).collect());
+    section("Order changes", diff.order_changes.iter().map(|o| {
+        format!("{}: [{}] -> [{}]", fmt_parent(&o.parent_id), o.before.join(", "), o.after.join(", "))
+    }).collect());
+    section("Map layer changes", diff.layer_changes.iter().map(|l| match l.kind {
+        LayerChangeKind::Added => format!("+ {} ({})", l.id, l.name),
+        LayerChangeKind::Removed => format!("- {} ({})", l.id, l.name),
+        LayerChangeKind::Modified => format!("~ {} ({}): {}", l.id, l.name, l.fields.join(", ")),
+    }).collect());
+    section("Options schema changes", diff.schema_changes.clone());
+    out
+}
+
+pub fn diff_project_files(before_path: &str, after_path: &str) -> Result<ProjectDiff, String> {
+    let before = io::load_project(before_path)?;
+    let after = io::load_project(after_path)?;
+    Ok(diff_projects(&before, &after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::test_support::{node_at, project};
+    use serde_json::json;
+
+    #[test]
+    fn test_identical_projects_have_no_changes() {
+        let p = project(&["a"], vec![node_at("a", 1.0, &[])]);
+        let diff = diff_projects(&p, &p.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.summary, "No changes.\n");
+    }
+
+    #[test]
+    fn test_added_removed_and_moved_nodes() {
+        let before = project(&["a", "b"], vec![node_at("a", 0.0, &[]), node_at("b", 0.0, &[])]);
+        let mut after = project(&["a", "c"], vec![node_at("a", 3.0, &[]), node_at("c", 0.0, &[])]);
+        // Rotate "a" by +90 degrees
+        let t = after.nodes.get_mut("a").unwrap().transform.as_mut().unwrap();
+        t.qz = (PI / 4.0).sin();
+        t.qw = (PI / 4.0).cos();
+
+        let diff = diff_projects(&before, &after);
+        assert_eq!(diff.added_nodes, vec![NodeSummary { id: "c".into(), node_type: "manual".into() }]);
+        assert_eq!(diff.removed_nodes[0].id, "b");
+        let m = &diff.moved_nodes[0];
+        assert_eq!((m.id.as_str(), m.dx, m.distance), ("a", 3.0, 3.0));
+        assert!((m.dyaw - PI / 2.0).abs() < 1e-9);
+        assert!(diff.summary.contains("yaw +90.0 deg"));
+    }
+
+    #[test]
+    fn test_option_changes_reparenting_and_order() {
+        let mut before = project(&["g", "h"], vec![node_at("g", 0.0, &["a", "b", "c"]), node_at("h", 0.0, &["d"]), node_at("a", 0.0, &[]), node_at("b", 0.0, &[]), node_at("c", 0.0, &[]), node_at("d", 0.0, &[])]);
+        before.nodes.get_mut("a").unwrap().options = Some(HashMap::from([("speed".to_string(), json!(0.5))]));
+        let mut after = before.clone();
+        after.nodes.get_mut("a").unwrap().options = Some(HashMap::from([("speed".to_string(), json!(1.0)), ("wait".to_string(), json!(2))]));
+        after.nodes.get_mut("g").unwrap().children_ids = Some(vec!["c".into(), "b".into()]);
+        after.nodes.get_mut("h").unwrap().children_ids = Some(vec!["d".into(), "a".into()]);
+
+        let diff = diff_projects(&before, &after);
+        assert_eq!(diff.option_changes.len(), 2);
+        assert_eq!(diff.option_changes[0].key, "speed");
+        assert_eq!(diff.option_changes[1].before, None);
+        assert_eq!(diff.reparented, vec![Reparent { id: "a".into(), from: Some("g".into()), to: Some("h".into()) }]);
+        // Moving "a" out of "g" alone is not an order change, swapping "b" and "c" is
+        assert_eq!(diff.order_changes.len(), 1);
+        assert_eq!(diff.order_changes[0].parent_id.as_deref(), Some("g"));
+    }
+
+    #[test]
+    fn test_map_layer_changes() {
+        let layer = |id: &str, opacity: f64| ProjectMapLayer {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            info: None,
+            image_base64: String::new(),
+            image_file: None,
+            visible: true,
+            opacity,
+            z_index: 0,
+        };
+        let mut before = project(&[], vec![]);
+        before.map_layers = Some(vec![layer("floor", 1.0), layer("old", 1.0)]);
+        let mut after = project(&[], vec![]);
+        after.map_layers = Some(vec![layer("floor", 0.5), layer("new", 1.0)]);
+
+        let kinds: Vec<(String, LayerChangeKind)> = diff_projects(&before, &after).layer_changes
+            .into_iter().map(|l| (l.id, l.kind)).collect();
+        assert_eq!(kinds, vec![
+            ("floor".to_string(), LayerChangeKind::Modified),
+            ("new".to_string(), LayerChangeKind::Added),
+            ("old".to_string(), LayerChangeKind::Removed),
+        ]);
+    }
+}
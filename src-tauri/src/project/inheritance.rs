@@ -0,0 +1,160 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::models::ProjectData;
+use crate::project::route::schema_defaults;
+
+/// Where an effective option value comes from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum OptionSource {
+    /// The schema's `default`
+    Default,
+    /// Set on an ancestor (generator or group) and not overridden below it
+    Inherited { node_id: String },
+    /// Set on the node itself
+    Own,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolvedOption {
+    pub value: Value,
+    #[serde(flatten)]
+    pub source: OptionSource,
+    /// Sources this value overrides, nearest first (e.g. an ancestor value and the default)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<OptionSource>,
+}
+
+/// Parent of every referenced node. A node listed by several parents keeps the
+/// first one in key order, matching `integrity::repair_project`'s tie-breaking.
+pub fn parent_map(project: &ProjectData) -> HashMap<&str, &str> {
+    let mut keys: Vec<&String> = project.nodes.keys().collect();
+    keys.sort();
+    let mut parents = HashMap::new();
+    for key in keys {
+        for child in project.nodes[key].children_ids.iter().flatten() {
+            parents.entry(child.as_str()).or_insert(key.as_str());
+        }
+    }
+    parents
+}
+
+/// Ancestors of `id`, nearest first. Stops at a cycle instead of looping.
+pub fn ancestors<'a>(parents: &HashMap<&'a str, &'a str>, id: &str) -> Vec<&'a str> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::from([id.to_string()]);
+    let mut current = id;
+    while let Some(parent) = parents.get(current) {
+        if !seen.insert(parent.to_string()) {
+            break;
+        }
+        chain.push(*parent);
+        current = parent;
+    }
+    chain
+}
+
+/// Values set on the ancestors of a node, the nearest ancestor winning. Schema defaults are not included.
+pub fn ancestor_values(project: &ProjectData, parents: &HashMap<&str, &str>, id: &str) -> BTreeMap<String, Value> {
+    let mut values = BTreeMap::new();
+    for ancestor in ancestors(parents, id).into_iter().rev() {
+        if let Some(options) = project.nodes.get(ancestor).and_then(|n| n.options.as_ref()) {
+            values.extend(options.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+    values
+}
+
+/// Values a node inherits: schema defaults overlaid by ancestor options, root-most first.
+/// The node's own options are not included.
+pub fn inherited_values(project: &ProjectData, parents: &HashMap<&str, &str>, id: &str) -> BTreeMap<String, Value> {
+    let mut values = schema_defaults(project.options_schema.as_ref());
+    values.extend(ancestor_values(project, parents, id));
+    values
+}
+
+/// Effective options of one node with the source of every key:
+/// schema default, then ancestors from the root down, then the node's own values.
+pub fn resolve_node_options(project: &ProjectData, id: &str) -> Result<BTreeMap<String, ResolvedOption>, String> {
+    let node = project.nodes.get(id).ok_or_else(|| format!("Node not found: {}", id))?;
+    let parents = parent_map(project);
+
+    let mut layers: Vec<(OptionSource, BTreeMap<String, Value>)> = vec![
+        (OptionSource::Default, schema_defaults(project.options_schema.as_ref())),
+    ];
+    for ancestor in ancestors(&parents, id).into_iter().rev() {
+        if let Some(options) = project.nodes.get(ancestor).and_then(|n| n.options.as_ref()) {
+            let values = options.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            layers.push((OptionSource::Inherited { node_id: ancestor.to_string() }, values));
+        }
+    }
+    if let Some(options) = &node.options {
+        layers.push((OptionSource::Own, options.iter().map(|(k, v)| (k.clone(), v.clone())).collect()));
+    }
+
+    let mut resolved: BTreeMap<String, ResolvedOption> = BTreeMap::new();
+    for (source, values) in layers {
+        for (key, value) in values {
+            let overrides = match resolved.remove(&key) {
+                Some(previous) => {
+                    let mut chain = vec![previous.source];
+                    chain.extend(previous.overrides);
+                    chain
+                }
+                None => Vec::new(),
+            };
+            resolved.insert(key, ResolvedOption { value, source: source.clone(), overrides });
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::test_support::{node_with, project};
+    use serde_json::json;
+
+    fn sweep() -> ProjectData {
+        let nodes = vec![
+            node_with("group", json!({ "speed": 0.8, "wait": 2.0 }), &["sweep"]),
+            node_with("sweep", json!({ "speed": 0.3 }), &["a", "b"]),
+            node_with("a", json!({}), &[]),
+            node_with("b", json!({ "speed": 1.0 }), &[]),
+        ];
+        ProjectData {
+            options_schema: Some(serde_yaml::from_str("options:\n  - { name: speed, label: Speed, type: float, default: 0.5 }\n  - { name: lights, label: Lights, type: boolean, default: false }\n").unwrap()),
+            ..project(&["group"], nodes)
+        }
+    }
+
+    #[test]
+    fn test_nearest_ancestor_wins_over_default() {
+        let resolved = resolve_node_options(&sweep(), "a").unwrap();
+        assert_eq!(resolved["speed"].value, json!(0.3));
+        assert_eq!(resolved["speed"].source, OptionSource::Inherited { node_id: "sweep".into() });
+        assert_eq!(resolved["speed"].overrides, vec![
+            OptionSource::Inherited { node_id: "group".into() },
+            OptionSource::Default,
+        ]);
+        assert_eq!(resolved["wait"].source, OptionSource::Inherited { node_id: "group".into() });
+        assert_eq!(resolved["lights"].source, OptionSource::Default);
+    }
+
+    #[test]
+    fn test_own_value_overrides_everything() {
+        let resolved = resolve_node_options(&sweep(), "b").unwrap();
+        assert_eq!(resolved["speed"].value, json!(1.0));
+        assert_eq!(resolved["speed"].source, OptionSource::Own);
+        assert_eq!(resolved["speed"].overrides.len(), 3);
+    }
+
+    #[test]
+    fn test_ancestors_stop_at_cycles() {
+        let mut p = sweep();
+        p.nodes.get_mut("a").unwrap().children_ids = Some(vec!["group".to_string()]);
+        let parents = parent_map(&p);
+        assert_eq!(ancestors(&parents, "a"), vec!["sweep", "group"]);
+    }
+}
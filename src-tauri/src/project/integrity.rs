@@ -0,0 +1,316 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use crate::models::ProjectData;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// A `nodes` entry whose `id` field disagrees with its map key
+    IdMismatch { key: String, id: String },
+    /// `root_node_ids` lists an id that has no node
+    DanglingRoot { id: String },
+    /// `root_node_ids` lists the same id more than once
+    DuplicateRoot { id: String },
+    /// `children_ids` references an id that has no node
+    DanglingChild { parent_id: String, child_id: String },
+    /// A node referenced from more than one place (other parents and/or the root list)
+    MultipleParents { id: String, parent_ids: Vec<String>, is_root: bool },
+    /// Nodes whose `children_ids` lead back to themselves
+    Cycle { ids: Vec<String> },
+    /// A node that is neither a root nor anybody's child
+    Orphan { id: String },
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::IdMismatch { key, id } => write!(f, "node '{}' has id '{}'", key, id),
+            IntegrityIssue::DanglingRoot { id } => write!(f, "root '{}' has no node", id),
+            IntegrityIssue::DuplicateRoot { id } => write!(f, "root '{}' is listed more than once", id),
+            IntegrityIssue::DanglingChild { parent_id, child_id } => write!(f, "'{}' lists missing child '{}'", parent_id, child_id),
+            IntegrityIssue::MultipleParents { id, parent_ids, is_root } => {
+                write!(f, "'{}' has several parents: {}", id, parent_ids.join(", "))?;
+                if *is_root {
+                    write!(f, " and the root list")?;
+                }
+                Ok(())
+            }
+            IntegrityIssue::Cycle { ids } => write!(f, "cycle through {}", ids.join(" -> ")),
+            IntegrityIssue::Orphan { id } => write!(f, "'{}' has no parent and is not listed as a root", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairResult {
+    /// Issues found before the repair
+    pub report: IntegrityReport,
+    /// Human-readable description of every change applied
+    pub actions: Vec<String>,
+}
+
+fn sorted_keys(project: &ProjectData) -> Vec<&String> {
+    let mut keys: Vec<&String> = project.nodes.keys().collect();
+    keys.sort();
+    keys
+}
+
+pub fn validate_project(project: &ProjectData) -> IntegrityReport {
+    let mut issues = Vec::new();
+    let keys = sorted_keys(project);
+
+    for key in &keys {
+        let node = &project.nodes[*key];
+        if node.id != **key {
+            issues.push(IntegrityIssue::IdMismatch { key: (*key).clone(), id: node.id.clone() });
+        }
+    }
+
+    let mut seen_roots = HashSet::new();
+    for id in &project.root_node_ids {
+        if !project.nodes.contains_key(id) {
+            issues.push(IntegrityIssue::DanglingRoot { id: id.clone() });
+        } else if !seen_roots.insert(id) {
+            issues.push(IntegrityIssue::DuplicateRoot { id: id.clone() });
+        }
+    }
+
+    // Collect every incoming reference per node to detect sharing and orphans
+    let mut parents: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+    for key in &keys {
+        for child in project.nodes[*key].children_ids.iter().flatten() {
+            if project.nodes.contains_key(child) {
+                parents.entry(child).or_default().push((*key).clone());
+            } else {
+                issues.push(IntegrityIssue::DanglingChild { parent_id: (*key).clone(), child_id: child.clone() });
+            }
+        }
+    }
+
+    for key in &keys {
+        let is_root = seen_roots.contains(*key);
+        let parent_ids = parents.get(*key).cloned().unwrap_or_default();
+        if parent_ids.len() + is_root as usize > 1 {
+            issues.push(IntegrityIssue::MultipleParents { id: (*key).clone(), parent_ids, is_root });
+        } else if parent_ids.is_empty() && !is_root {
+            issues.push(IntegrityIssue::Orphan { id: (*key).clone() });
+        }
+    }
+
+    for ids in find_cycles(project, &keys) {
+        issues.push(IntegrityIssue::Cycle { ids });
+    }
+
+    IntegrityReport { issues }
+}
+
+/// Depth-first search over `children_ids`, reporting each cycle once by its node set.
+fn find_cycles(project: &ProjectData, keys: &[&String]) -> Vec<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State { Visiting, Done }
+
+    fn visit<'a>(
+        id: &'a String,
+        project: &'a ProjectData,
+        state: &mut HashMap<&'a String, State>,
+        stack: &mut Vec<&'a String>,
+        cycles: &mut Vec<Vec<String>>,
+        seen: &mut HashSet<Vec<String>>,
+    ) {
+        state.insert(id, State::Visiting);
+        stack.push(id);
+        for child in project.nodes[id].children_ids.iter().flatten() {
+            let Some((child_key, _)) = project.nodes.get_key_value(child) else { continue };
+            match state.get(child_key) {
+                Some(State::Visiting) => {
+                    let start = stack.iter().position(|s| *s == child_key).unwrap_or(0);
+                    let cycle: Vec<String> = stack[start..].iter().map(|s| (*s).clone()).collect();
+                    let mut key = cycle.clone();
+                    key.sort();
+                    if seen.insert(key) {
+                        cycles.push(cycle);
+                    }
+                }
+                Some(State::Done) => {}
+                None => visit(child_key, project, state, stack, cycles, seen),
+            }
+        }
+        stack.pop();
+        state.insert(id, State::Done);
+    }
+
+    let mut state = HashMap::new();
+    let mut cycles = Vec::new();
+    let mut seen = HashSet::new();
+    for key in keys {
+        if !state.contains_key(*key) {
+            visit(key, project, &mut state, &mut Vec::new(), &mut cycles, &mut seen);
+        }
+    }
+    cycles
+}
+
+/// Fix every issue reported by `validate_project` in a deterministic way:
+/// map keys win over `id` fields, dangling and duplicate references are dropped,
+/// a shared node stays with the first parent in tree order (roots first),
+/// edges closing a cycle are cut, and orphans are promoted to roots.
+pub fn repair_project(project: &mut ProjectData) -> RepairResult {
+    let report = validate_project(project);
+    let mut actions = Vec::new();
+
+    let keys: Vec<String> = sorted_keys(project).into_iter().cloned().collect();
+    for key in keys {
+        let node = project.nodes.get_mut(&key).expect("key taken from the map");
+        if node.id != key {
+            actions.push(format!("Renamed node id '{}' to match its key '{}'", node.id, key));
+            node.id = key;
+        }
+    }
+
+    let mut roots = Vec::new();
+    for id in std::mem::take(&mut project.root_node_ids) {
+        if !project.nodes.contains_key(&id) {
+            actions.push(format!("Removed dangling root '{}'", id));
+        } else if roots.contains(&id) {
+            actions.push(format!("Removed duplicate root '{}'", id));
+        } else {
+            roots.push(id);
+        }
+    }
+
+    let mut claimed: HashSet<String> = roots.iter().cloned().collect();
+    for id in roots.clone() {
+        claim_subtree(project, &id, &mut claimed, &mut Vec::new(), &mut actions);
+    }
+
+    // Remaining nodes are orphans or members of unreachable cycles.
+    // Prefer nodes without an unclaimed parent so whole orphan subtrees stay intact.
+    loop {
+        let unclaimed: Vec<String> = sorted_keys(project)
+            .into_iter()
+            .filter(|k| !claimed.contains(*k))
+            .cloned()
+            .collect();
+        if unclaimed.is_empty() {
+            break;
+        }
+        let referenced: HashSet<&String> = unclaimed
+            .iter()
+            .flat_map(|p| project.nodes[p].children_ids.iter().flatten())
+            .collect();
+        let next = unclaimed
+            .iter()
+            .find(|id| !referenced.contains(id))
+            .unwrap_or(&unclaimed[0])
+            .clone();
+
+        actions.push(format!("Promoted orphan node '{}' to root", next));
+        claimed.insert(next.clone());
+        roots.push(next.clone());
+        claim_subtree(project, &next, &mut claimed, &mut Vec::new(), &mut actions);
+    }
+
+    project.root_node_ids = roots;
+    RepairResult { report, actions }
+}
+
+fn claim_subtree(
+    project: &mut ProjectData,
+    id: &str,
+    claimed: &mut HashSet<String>,
+    stack: &mut Vec<String>,
+    actions: &mut Vec<String>,
+) {
+    stack.push(id.to_string());
+    let children = project.nodes.get_mut(id).and_then(|n| n.children_ids.take());
+
+    if let Some(children) = children {
+        let mut kept = Vec::with_capacity(children.len());
+        for child in children {
+            if !project.nodes.contains_key(&child) {
+                actions.push(format!("Removed dangling child '{}' from '{}'", child, id));
+            } else if stack.contains(&child) {
+                actions.push(format!("Removed child '{}' from '{}' to break a cycle", child, id));
+            } else if claimed.contains(&child) {
+                actions.push(format!("Removed duplicate reference to '{}' from '{}'", child, id));
+            } else {
+                claimed.insert(child.clone());
+                kept.push(child);
+            }
+        }
+        for child in &kept {
+            claim_subtree(project, child, claimed, stack, actions);
+        }
+        if let Some(node) = project.nodes.get_mut(id) {
+            node.children_ids = Some(kept);
+        }
+    }
+    stack.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::test_support::{node, project};
+
+    #[test]
+    fn test_valid_project_has_no_issues() {
+        let p = project(&["g", "m"], vec![node("g", &["a", "b"]), node("a", &[]), node("b", &[]), node("m", &[])]);
+        assert!(validate_project(&p).is_valid());
+    }
+
+    #[test]
+    fn test_detects_dangling_duplicate_and_orphan_references() {
+        let p = project(&["g", "g", "missing"], vec![node("g", &["a", "ghost"]), node("a", &[]), node("lost", &[])]);
+        let issues = validate_project(&p).issues;
+        assert!(issues.contains(&IntegrityIssue::DuplicateRoot { id: "g".into() }));
+        assert!(issues.contains(&IntegrityIssue::DanglingRoot { id: "missing".into() }));
+        assert!(issues.contains(&IntegrityIssue::DanglingChild { parent_id: "g".into(), child_id: "ghost".into() }));
+        assert!(issues.contains(&IntegrityIssue::Orphan { id: "lost".into() }));
+    }
+
+    #[test]
+    fn test_detects_shared_child_cycle_and_id_mismatch() {
+        let mut p = project(&["g1", "g2"], vec![node("g1", &["a"]), node("g2", &["a"]), node("a", &[])]);
+        p.nodes.insert("x".into(), node("y", &["z"]));
+        p.nodes.insert("z".into(), node("z", &["x"]));
+        p.root_node_ids.push("x".into());
+
+        let issues = validate_project(&p).issues;
+        assert!(issues.contains(&IntegrityIssue::MultipleParents { id: "a".into(), parent_ids: vec!["g1".into(), "g2".into()], is_root: false }));
+        assert!(issues.contains(&IntegrityIssue::IdMismatch { key: "x".into(), id: "y".into() }));
+        assert!(issues.iter().any(|i| matches!(i, IntegrityIssue::Cycle { ids } if ids.len() == 2)));
+    }
+
+    #[test]
+    fn test_repair_produces_valid_project() {
+        let mut p = project(
+            &["g1", "g2", "g1", "missing"],
+            vec![node("g1", &["a", "ghost"]), node("g2", &["a", "g1"]), node("a", &[]), node("o", &["oc"]), node("oc", &[])],
+        );
+        p.nodes.insert("c1".into(), node("c1", &["c2"]));
+        p.nodes.insert("c2".into(), node("c2", &["c1"]));
+
+        let result = repair_project(&mut p);
+        assert!(!result.report.is_valid());
+        assert!(!result.actions.is_empty());
+        assert!(validate_project(&p).is_valid(), "{:?}", validate_project(&p));
+
+        // First parent in tree order keeps the shared child, orphan subtree stays intact
+        assert_eq!(p.nodes["g1"].children_ids, Some(vec!["a".to_string()]));
+        assert_eq!(p.nodes["o"].children_ids, Some(vec!["oc".to_string()]));
+        assert_eq!(p.root_node_ids, vec!["g1", "g2", "o", "c1"]);
+    }
+}
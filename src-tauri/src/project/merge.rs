@@ -0,0 +1,445 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashSet};
+use crate::io;
+use crate::models::ProjectData;
+use crate::project::integrity::{validate_project, IntegrityReport};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MergeConflict {
+    /// `None` for project-level fields such as `root_node_ids`
+    pub node_id: Option<String>,
+    pub field: String,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeResult {
+    /// Merged project. Conflicting values resolve to "ours".
+    pub project: ProjectData,
+    pub conflicts: Vec<MergeConflict>,
+    pub integrity: IntegrityReport,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+struct Merger {
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merger {
+    fn conflict(&mut self, node_id: Option<&str>, field: &str, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) {
+        self.conflicts.push(MergeConflict {
+            node_id: node_id.map(str::to_string),
+            field: field.to_string(),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        });
+    }
+
+    /// Classic three-way rule for a single value; `None` means absent.
+    fn merge_value(&mut self, node_id: Option<&str>, field: &str, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Option<Value> {
+        if ours == theirs || theirs == base {
+            ours.cloned()
+        } else if ours == base {
+            theirs.cloned()
+        } else {
+            self.conflict(node_id, field, base, ours, theirs);
+            ours.cloned()
+        }
+    }
+
+    /// Key-by-key merge of an object such as `options` or `generator_params`.
+    fn merge_map(&mut self, node_id: &str, field: &str, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Option<Value> {
+        let as_obj = |v: Option<&Value>| v.and_then(Value::as_object).cloned();
+        let (b, o, t) = (as_obj(base), as_obj(ours), as_obj(theirs));
+        if o.is_none() && t.is_none() {
+            return None;
+        }
+        let (b, o, t) = (b.unwrap_or_default(), o.unwrap_or_default(), t.unwrap_or_default());
+
+        let keys: BTreeSet<&String> = b.keys().chain(o.keys()).chain(t.keys()).collect();
+        let mut merged = Map::new();
+        for key in keys {
+            let label = format!("{}.{}", field, key);
+            if let Some(v) = self.merge_value(Some(node_id), &label, b.get(key), o.get(key), t.get(key)) {
+                merged.insert(key.clone(), v);
+            }
+        }
+        Some(Value::Object(merged))
+    }
+
+    /// Position and orientation merge independently, so a move on one branch
+    /// combines with a rotation on the other.
+    fn merge_transform(&mut self, node_id: &str, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Option<Value> {
+        if ours.is_none() || theirs.is_none() || base.is_none() {
+            return self.merge_value(Some(node_id), "transform", base, ours, theirs);
+        }
+        let pick = |v: Option<&Value>, keys: &[&str]| -> Value {
+            let mut part = Map::new();
+            for k in keys {
+                if let Some(x) = v.and_then(|v| v.get(*k)) {
+                    part.insert(k.to_string(), x.clone());
+                }
+            }
+            Value::Object(part)
+        };
+
+        let mut merged = Map::new();
+        for (label, keys) in [
+            ("transform.position", &["x", "y", "z"][..]),
+            ("transform.orientation", &["qx", "qy", "qz", "qw"][..]),
+        ] {
+            let part = self.merge_value(
+                Some(node_id),
+                label,
+                Some(&pick(base, keys)),
+                Some(&pick(ours, keys)),
+                Some(&pick(theirs, keys)),
+            );
+            if let Some(Value::Object(part)) = part {
+                merged.extend(part);
+            }
+        }
+        Some(Value::Object(merged))
+    }
+
+    /// Merge an ordered id list. Removals from either side win, additions from both
+    /// sides are kept next to their original neighbour, and a reorder on one side is
+    /// applied unless both sides reordered differently.
+    fn merge_list(&mut self, node_id: Option<&str>, field: &str, base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+        let in_ours: HashSet<&String> = ours.iter().collect();
+        let in_theirs: HashSet<&String> = theirs.iter().collect();
+        let removed: HashSet<&String> = base.iter().filter(|b| !in_ours.contains(b) || !in_theirs.contains(b)).collect();
+        let in_base: HashSet<&String> = base.iter().collect();
+
+        let keep = |list: &[String]| -> Vec<String> {
+            let mut seen = HashSet::new();
+            list.iter().filter(|i| !removed.contains(i) && seen.insert(*i)).cloned().collect()
+        };
+        let ours_kept = keep(ours);
+        let theirs_kept = keep(theirs);
+        let base_order: Vec<&String> = base.iter().filter(|b| !removed.contains(b)).collect();
+        fn common<'a>(list: &'a [String], in_base: &HashSet<&String>) -> Vec<&'a String> {
+            list.iter().filter(|i| in_base.contains(i)).collect()
+        }
+
+        let (ours_common, theirs_common) = (common(&ours_kept, &in_base), common(&theirs_kept, &in_base));
+        let (primary, secondary) = if ours_common == base_order {
+            (theirs_kept, ours_kept)
+        } else if theirs_common == base_order || ours_common == theirs_common {
+            (ours_kept, theirs_kept)
+        } else {
+            let to_value = |l: &[String]| Value::from(l.to_vec());
+            self.conflict(node_id, field, Some(&to_value(base)), Some(&to_value(ours)), Some(&to_value(theirs)));
+            (ours_kept, theirs_kept)
+        };
+
+        let mut result = primary;
+        for (i, item) in secondary.iter().enumerate() {
+            if result.contains(item) {
+                continue;
+            }
+            let insert_at = secondary[..i]
+                .iter()
+                .rev()
+                .find_map(|prev| result.iter().position(|r| r == prev).map(|p| p + 1))
+                .unwrap_or(0);
+            result.insert(insert_at, item.clone());
+        }
+        result
+    }
+
+    fn merge_node(&mut self, id: &str, base: Option<&Value>, ours: &Value, theirs: &Value) -> Value {
+        let empty = Map::new();
+        let b = base.and_then(Value::as_object).unwrap_or(&empty);
+        let o = ours.as_object().unwrap_or(&empty);
+        let t = theirs.as_object().unwrap_or(&empty);
+
+        let keys: BTreeSet<&String> = b.keys().chain(o.keys()).chain(t.keys()).collect();
+        let mut merged = Map::new();
+        for key in keys {
+            let (bv, ov, tv) = (b.get(key).filter(|v| !v.is_null()), o.get(key).filter(|v| !v.is_null()), t.get(key).filter(|v| !v.is_null()));
+            let value = match key.as_str() {
+                "transform" => self.merge_transform(id, bv, ov, tv),
+                "options" | "generator_params" => self.merge_map(id, key, bv, ov, tv),
+                "children_ids" => {
+                    let list = |v: Option<&Value>| -> Vec<String> {
+                        v.and_then(|v| serde_json::from_value(v.clone()).ok()).unwrap_or_default()
+                    };
+                    if ov.is_none() && tv.is_none() {
+                        None
+                    } else {
+                        Some(Value::from(self.merge_list(Some(id), key, &list(bv), &list(ov), &list(tv))))
+                    }
+                }
+                _ => self.merge_value(Some(id), key, bv, ov, tv),
+            };
+            merged.insert(key.clone(), value.unwrap_or(Value::Null));
+        }
+        Value::Object(merged)
+    }
+}
+
+fn to_json(project: &ProjectData) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(project).map_err(|e| format!("Serialization error: {}", e))? {
+        Value::Object(obj) => Ok(obj),
+        _ => Err("Project did not serialize to an object".to_string()),
+    }
+}
+
+fn object_field(project: &Map<String, Value>, key: &str) -> Map<String, Value> {
+    project.get(key).and_then(Value::as_object).cloned().unwrap_or_default()
+}
+
+fn id_list(project: &Map<String, Value>, key: &str) -> Vec<String> {
+    project.get(key).and_then(|v| serde_json::from_value(v.clone()).ok()).unwrap_or_default()
+}
+
+pub fn merge_projects(base: &ProjectData, ours: &ProjectData, theirs: &ProjectData) -> Result<MergeResult, String> {
+    let (b, o, t) = (to_json(base)?, to_json(ours)?, to_json(theirs)?);
+    let mut merger = Merger { conflicts: Vec::new() };
+
+    let (bn, on, tn) = (object_field(&b, "nodes"), object_field(&o, "nodes"), object_field(&t, "nodes"));
+    let ids: BTreeSet<&String> = bn.keys().chain(on.keys()).chain(tn.keys()).collect();
+    let mut nodes = Map::new();
+    for id in ids {
+        let merged = match (bn.get(id), on.get(id), tn.get(id)) {
+            (base, Some(ours), Some(theirs)) => Some(merger.merge_node(id, base, ours, theirs)),
+            // Deleted on one side: honour the deletion only if the other side left the node alone
+            (Some(base), None, Some(survivor)) | (Some(base), Some(survivor), None) => {
+                if base == survivor {
+                    None
+                } else {
+                    merger.conflict(Some(id), "node", Some(base), on.get(id), tn.get(id));
+                    Some(survivor.clone())
+                }
+            }
+            (None, Some(added), None) | (None, None, Some(added)) => Some(added.clone()),
+            _ => None,
+        };
+        if let Some(node) = merged {
+            nodes.insert(id.clone(), node);
+        }
+    }
+
+    // A node kept because of a delete/modify conflict must stay referenced somewhere
+    let kept_by_conflict: HashSet<String> = merger.conflicts.iter()
+        .filter(|c| c.field == "node")
+        .filter_map(|c| c.node_id.clone())
+        .collect();
+
+    let mut roots = merger.merge_list(None, "root_node_ids", &id_list(&b, "root_node_ids"), &id_list(&o, "root_node_ids"), &id_list(&t, "root_node_ids"));
+    for id in &kept_by_conflict {
+        let referenced = nodes.values().any(|n| {
+            n.get("children_ids").and_then(Value::as_array).is_some_and(|c| c.iter().any(|c| c == id.as_str()))
+        });
+        if !referenced && !roots.contains(id) {
+            roots.push(id.clone());
+        }
+    }
+    roots.retain(|r| nodes.contains_key(r));
+
+    let layers = merge_keyed_list(&mut merger, "map_layers", "id", b.get("map_layers"), o.get("map_layers"), t.get("map_layers"));
+    let schema = merge_schema(&mut merger, &b, &o, &t);
+    let profiles = merge_keyed_list(&mut merger, "export_profiles", "id", b.get("export_profiles"), o.get("export_profiles"), t.get("export_profiles"));
+
+    let mut merged = Map::new();
+    merged.insert("format_version".to_string(), Value::from(ours.format_version.max(theirs.format_version)));
+    merged.insert("root_node_ids".to_string(), Value::from(roots));
+    merged.insert("nodes".to_string(), Value::Object(nodes));
+    merged.insert("map_layers".to_string(), layers);
+    merged.insert("options_schema".to_string(), schema);
+    merged.insert("export_profiles".to_string(), profiles);
+
+    let project: ProjectData = serde_json::from_value(Value::Object(merged))
+        .map_err(|e| format!("Merged project is invalid: {}", e))?;
+    let integrity = validate_project(&project);
+
+    Ok(MergeResult { project, conflicts: merger.conflicts, integrity })
+}
+
+/// Merge three project files and write the result to `output_path` in canonical form.
+pub fn merge_project_files(base_path: &str, ours_path: &str, theirs_path: &str, output_path: &str) -> Result<MergeResult, String> {
+    let base = io::load_project(base_path)?;
+    let ours = io::load_project(ours_path)?;
+    let theirs = io::load_project(theirs_path)?;
+
+    let result = merge_projects(&base, &ours, &theirs)?;
+    // Version control already keeps history, so no `.bak` files next to the merge target
+    let options = io::SaveOptions { backup_count: 0, ..io::SaveOptions::default() };
+    io::save_project_with_options(output_path, &result.project, &options)?;
+    Ok(result)
+}
+
+/// Merge an array of objects identified by `key`, such as map layers (`id`) or option defs (`name`).
+fn merge_keyed_list(merger: &mut Merger, field: &str, key: &str, b: Option<&Value>, o: Option<&Value>, t: Option<&Value>) -> Value {
+    if o.is_none_or(Value::is_null) && t.is_none_or(Value::is_null) {
+        return Value::Null;
+    }
+    let items = |v: Option<&Value>| -> Vec<(String, Value)> {
+        v.and_then(Value::as_array).into_iter().flatten()
+            .filter_map(|item| Some((item.get(key)?.as_str()?.to_string(), item.clone())))
+            .collect()
+    };
+    let (bl, ol, tl) = (items(b), items(o), items(t));
+
+    let ids = |l: &[(String, Value)]| l.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+    let order = merger.merge_list(None, field, &ids(&bl), &ids(&ol), &ids(&tl));
+    let find = |l: &[(String, Value)], id: &str| l.iter().find(|(i, _)| i == id).map(|(_, v)| v.clone());
+
+    let merged: Vec<Value> = order.iter()
+        .filter_map(|id| {
+            let label = format!("{}.{}", field, id);
+            merger.merge_value(None, &label, find(&bl, id).as_ref(), find(&ol, id).as_ref(), find(&tl, id).as_ref())
+        })
+        .collect();
+    Value::from(merged)
+}
+
+/// Option defs merge by name; the schema is absent only if neither side has one.
+fn merge_schema(merger: &mut Merger, b: &Map<String, Value>, o: &Map<String, Value>, t: &Map<String, Value>) -> Value {
+    let options = |p: &Map<String, Value>| p.get("options_schema").and_then(|s| s.get("options")).cloned();
+    let (bo, oo, to) = (options(b), options(o), options(t));
+    match merge_keyed_list(merger, "options_schema", "name", bo.as_ref(), oo.as_ref(), to.as_ref()) {
+        Value::Null => Value::Null,
+        merged => serde_json::json!({ "options": merged }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project(v: Value) -> ProjectData {
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn manual(x: f64, y: f64) -> Value {
+        json!({ "id": "", "type": "manual", "transform": { "x": x, "y": y, "qx": 0.0, "qy": 0.0, "qz": 0.0, "qw": 1.0 } })
+    }
+
+    fn with_id(mut v: Value, id: &str) -> Value {
+        v["id"] = json!(id);
+        v
+    }
+
+    #[test]
+    fn test_independent_edits_merge_cleanly() {
+        let base = project(json!({
+            "root_node_ids": ["a", "b"],
+            "nodes": { "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(1.0, 1.0), "b") }
+        }));
+        let mut a_moved = with_id(manual(5.0, 5.0), "a");
+        a_moved["options"] = json!({ "speed": 0.3 });
+        let ours = project(json!({
+            "root_node_ids": ["a", "b"],
+            "nodes": { "a": a_moved, "b": with_id(manual(1.0, 1.0), "b") }
+        }));
+        let mut b_rotated = with_id(manual(1.0, 1.0), "b");
+        b_rotated["transform"]["qz"] = json!(1.0);
+        b_rotated["transform"]["qw"] = json!(0.0);
+        let theirs = project(json!({
+            "root_node_ids": ["a", "b", "c"],
+            "nodes": { "a": with_id(manual(0.0, 0.0), "a"), "b": b_rotated, "c": with_id(manual(2.0, 2.0), "c") }
+        }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert!(result.is_clean(), "{:?}", result.conflicts);
+        assert!(result.integrity.is_valid());
+        let p = &result.project;
+        assert_eq!(p.root_node_ids, vec!["a", "b", "c"]);
+        assert_eq!(p.nodes["a"].transform.as_ref().unwrap().x, 5.0);
+        assert_eq!(p.nodes["a"].options.as_ref().unwrap()["speed"], json!(0.3));
+        assert_eq!(p.nodes["b"].transform.as_ref().unwrap().qz, 1.0);
+    }
+
+    #[test]
+    fn test_position_and_orientation_merge_independently() {
+        let base = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(0.0, 0.0), "a") } }));
+        let ours = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(3.0, 4.0), "a") } }));
+        let mut rotated = with_id(manual(0.0, 0.0), "a");
+        rotated["transform"]["qz"] = json!(1.0);
+        rotated["transform"]["qw"] = json!(0.0);
+        let theirs = project(json!({ "root_node_ids": ["a"], "nodes": { "a": rotated } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert!(result.is_clean());
+        let t = result.project.nodes["a"].transform.clone().unwrap();
+        assert_eq!((t.x, t.y, t.qz, t.qw), (3.0, 4.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_conflicting_edits_are_reported_per_node() {
+        let base = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(0.0, 0.0), "a") } }));
+        let ours = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(1.0, 0.0), "a") } }));
+        let theirs = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(2.0, 0.0), "a") } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].node_id.as_deref(), Some("a"));
+        assert_eq!(result.conflicts[0].field, "transform.position");
+        // Ours wins on conflict
+        assert_eq!(result.project.nodes["a"].transform.as_ref().unwrap().x, 1.0);
+    }
+
+    #[test]
+    fn test_children_additions_from_both_sides_keep_their_neighbours() {
+        let generator = |children: &[&str]| json!({ "id": "g", "type": "generator", "children_ids": children });
+        let base = project(json!({ "root_node_ids": ["g"], "nodes": { "g": generator(&["a", "b"]), "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(0.0, 0.0), "b") } }));
+        let ours = project(json!({ "root_node_ids": ["g"], "nodes": {
+            "g": generator(&["a", "x", "b"]), "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(0.0, 0.0), "b"), "x": with_id(manual(0.0, 0.0), "x") } }));
+        let theirs = project(json!({ "root_node_ids": ["g"], "nodes": {
+            "g": generator(&["b", "a", "y"]), "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(0.0, 0.0), "b"), "y": with_id(manual(0.0, 0.0), "y") } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert!(result.is_clean(), "{:?}", result.conflicts);
+        // Theirs reordered a/b, ours inserted x after a
+        assert_eq!(result.project.nodes["g"].children_ids.as_ref().unwrap(), &vec!["b", "a", "x", "y"]);
+    }
+
+    #[test]
+    fn test_delete_versus_modify_keeps_node_and_reports_conflict() {
+        let base = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(0.0, 0.0), "a") } }));
+        let ours = project(json!({ "root_node_ids": [], "nodes": {} }));
+        let theirs = project(json!({ "root_node_ids": ["a"], "nodes": { "a": with_id(manual(9.0, 0.0), "a") } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert_eq!(result.conflicts[0].field, "node");
+        assert!(result.project.nodes.contains_key("a"));
+        assert_eq!(result.project.root_node_ids, vec!["a"]);
+    }
+
+    #[test]
+    fn test_delete_of_unmodified_node_is_applied() {
+        let base = project(json!({ "root_node_ids": ["a", "b"], "nodes": { "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(0.0, 0.0), "b") } }));
+        let ours = project(json!({ "root_node_ids": ["b"], "nodes": { "b": with_id(manual(0.0, 0.0), "b") } }));
+        let theirs = project(json!({ "root_node_ids": ["a", "b"], "nodes": { "a": with_id(manual(0.0, 0.0), "a"), "b": with_id(manual(1.0, 0.0), "b") } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert!(result.is_clean());
+        assert!(!result.project.nodes.contains_key("a"));
+        assert_eq!(result.project.root_node_ids, vec!["b"]);
+    }
+
+    #[test]
+    fn test_options_schema_merges_by_option_name() {
+        let def = |name: &str| json!({ "name": name, "label": name, "type": "float" });
+        let base = project(json!({ "root_node_ids": [], "nodes": {}, "options_schema": { "options": [def("speed")] } }));
+        let ours = project(json!({ "root_node_ids": [], "nodes": {}, "options_schema": { "options": [def("speed"), def("wait")] } }));
+        let theirs = project(json!({ "root_node_ids": [], "nodes": {}, "options_schema": { "options": [def("speed"), def("tolerance")] } }));
+
+        let result = merge_projects(&base, &ours, &theirs).unwrap();
+        assert!(result.is_clean(), "{:?}", result.conflicts);
+        let names: Vec<String> = result.project.options_schema.unwrap().options.into_iter().map(|o| o.name).collect();
+        assert_eq!(names, vec!["speed", "wait", "tolerance"]);
+    }
+}
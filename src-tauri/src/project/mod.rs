@@ -0,0 +1,8 @@
+pub mod diff;
+pub mod inheritance;
+pub mod integrity;
+pub mod merge;
+pub mod route;
+
+#[cfg(test)]
+pub(crate) mod test_support;
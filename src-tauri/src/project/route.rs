@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use crate::models::options::OptionsSchema;
+use crate::models::{ProjectData, Transform};
+
+/// One exported waypoint, in the shape templates and YAML/JSON exports have always received.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteWaypoint {
+    pub index: usize,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Radians, derived from the quaternion
+    pub yaw: f64,
+    pub qx: f64,
+    pub qy: f64,
+    pub qz: f64,
+    pub qw: f64,
+    /// Effective options: schema defaults, then ancestor options, then the node's own
+    pub options: BTreeMap<String, Value>,
+    /// Generator that produced this waypoint, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteOptions {
+    /// Index of the first waypoint (the UI lets users pick 0 or 1)
+    pub index_start: usize,
+}
+
+/// Schema defaults as JSON, skipping options without one.
+pub fn schema_defaults(schema: Option<&OptionsSchema>) -> BTreeMap<String, Value> {
+    schema
+        .iter()
+        .flat_map(|s| &s.options)
+        .filter_map(|def| {
+            let value = serde_json::to_value(def.default.as_ref()?).ok()?;
+            Some((def.name.clone(), value))
+        })
+        .collect()
+}
+
+/// Walk `root_node_ids` depth-first. Manual nodes become waypoints; generators
+/// contribute their stored outputs (`children_ids`) in order and pass their options
+/// down. Dangling ids and cycles are skipped so a damaged project still exports what it can.
+pub fn build_route(project: &ProjectData, options: &RouteOptions) -> Vec<RouteWaypoint> {
+    let defaults = schema_defaults(project.options_schema.as_ref());
+    let mut route = Vec::new();
+    let mut visited = HashSet::new();
+    for root in &project.root_node_ids {
+        visit(project, root, None, &defaults, options, &mut visited, &mut route);
+    }
+    route
+}
+
+fn visit(
+    project: &ProjectData,
+    id: &str,
+    parent_id: Option<&str>,
+    inherited: &BTreeMap<String, Value>,
+    options: &RouteOptions,
+    visited: &mut HashSet<String>,
+    route: &mut Vec<RouteWaypoint>,
+) {
+    let Some(node) = project.nodes.get(id) else { return };
+    if !visited.insert(id.to_string()) {
+        return;
+    }
+
+    // Options cascade down the tree, so a value set on a generator applies to all its outputs
+    let mut resolved = inherited.clone();
+    resolved.extend(node.options.iter().flatten().map(|(k, v)| (k.clone(), v.clone())));
+
+    if node.node_type == "manual" {
+        let identity = Transform { x: 0.0, y: 0.0, z: None, qx: 0.0, qy: 0.0, qz: 0.0, qw: 1.0 };
+        let t = node.transform.as_ref().unwrap_or(&identity);
+
+        route.push(RouteWaypoint {
+            index: options.index_start + route.len(),
+            id: node.id.clone(),
+            node_type: node.node_type.clone(),
+            x: t.x,
+            y: t.y,
+            z: t.z.unwrap_or(0.0),
+            yaw: t.yaw(),
+            qx: t.qx,
+            qy: t.qy,
+            qz: t.qz,
+            qw: t.qw,
+            options: resolved.clone(),
+            parent_id: parent_id.map(str::to_string),
+        });
+    }
+
+    for child in node.children_ids.iter().flatten() {
+        visit(project, child, Some(id), &resolved, options, visited, route);
+    }
+}
+
+/// The route as plain JSON values, as `io::export_waypoints` expects.
+pub fn route_to_values(route: &[RouteWaypoint]) -> Result<Vec<Value>, String> {
+    route
+        .iter()
+        .map(|wp| serde_json::to_value(wp).map_err(|e| format!("Serialization error: {}", e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::test_support::{node_at, project};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_generators_expand_to_their_outputs_in_order() {
+        let p = project(
+            &["m1", "g", "m2", "missing"],
+            vec![
+                node_at("m1", 1.0, &[]),
+                node_at("g", 0.0, &["c1", "c2"]),
+                node_at("c1", 2.0, &[]),
+                node_at("c2", 3.0, &[]),
+                node_at("m2", 4.0, &[]),
+            ],
+        );
+        let route = build_route(&p, &RouteOptions { index_start: 1 });
+        let ids: Vec<(&str, usize, Option<&str>)> = route.iter()
+            .map(|w| (w.id.as_str(), w.index, w.parent_id.as_deref()))
+            .collect();
+        assert_eq!(ids, vec![("m1", 1, None), ("c1", 2, Some("g")), ("c2", 3, Some("g")), ("m2", 4, None)]);
+        assert_eq!(route[2].x, 3.0);
+    }
+
+    #[test]
+    fn test_options_overlay_schema_defaults() {
+        let mut p = project(&["a"], vec![node_at("a", 0.0, &[])]);
+        p.options_schema = Some(serde_yaml::from_str("options:\n  - { name: speed, label: Speed, type: float, default: 0.5 }\n  - { name: wait, label: Wait, type: float, default: 1.0 }\n").unwrap());
+        p.nodes.get_mut("a").unwrap().options = Some(HashMap::from([("speed".to_string(), json!(0.2)), ("tag".to_string(), json!("x"))]));
+
+        let wp = &build_route(&p, &RouteOptions::default())[0];
+        assert_eq!(wp.options["speed"], json!(0.2));
+        assert_eq!(wp.options["wait"], json!(1.0));
+        assert_eq!(wp.options["tag"], json!("x"));
+    }
+
+    #[test]
+    fn test_cycles_do_not_loop_forever() {
+        let p = project(&["g"], vec![node_at("g", 0.0, &["h"]), node_at("h", 0.0, &["g", "a"]), node_at("a", 0.0, &[])]);
+        let route = build_route(&p, &RouteOptions::default());
+        assert_eq!(route.len(), 1);
+        assert_eq!(route[0].parent_id.as_deref(), Some("h"));
+    }
+
+    #[test]
+    fn test_serialized_shape_matches_export_format() {
+        let p = project(&["a"], vec![node_at("a", 1.5, &[])]);
+        let values = route_to_values(&build_route(&p, &RouteOptions::default())).unwrap();
+        assert_eq!(values[0]["type"], json!("manual"));
+        assert_eq!(values[0]["z"], json!(0.0));
+        assert!(values[0].get("parent_id").is_none());
+    }
+
+    #[test]
+    fn test_generator_options_cascade_to_outputs() {
+        let mut p = project(&["g"], vec![node_at("g", 0.0, &["a", "b"]), node_at("a", 0.0, &[]), node_at("b", 0.0, &[])]);
+        p.nodes.get_mut("g").unwrap().options = Some(HashMap::from([("speed".to_string(), json!(0.3))]));
+        p.nodes.get_mut("b").unwrap().options = Some(HashMap::from([("speed".to_string(), json!(0.9))]));
+
+        let route = build_route(&p, &RouteOptions::default());
+        assert_eq!(route[0].options["speed"], json!(0.3));
+        assert_eq!(route[1].options["speed"], json!(0.9));
+    }
+}
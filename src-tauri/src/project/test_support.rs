@@ -0,0 +1,37 @@
+//! Node and project builders shared by the tests of the project modules.
+use serde_json::Value;
+use crate::models::{ProjectData, Transform, WaypointNode};
+
+/// A manual waypoint, or a generator when it has children.
+pub(crate) fn node(id: &str, children: &[&str]) -> WaypointNode {
+    WaypointNode {
+        id: id.to_string(),
+        node_type: if children.is_empty() { "manual" } else { "generator" }.to_string(),
+        children_ids: if children.is_empty() { None } else { Some(children.iter().map(|c| c.to_string()).collect()) },
+        ..Default::default()
+    }
+}
+
+/// `node` placed at (x, 0) facing +x.
+pub(crate) fn node_at(id: &str, x: f64, children: &[&str]) -> WaypointNode {
+    WaypointNode {
+        transform: Some(Transform { x, y: 0.0, z: None, qx: 0.0, qy: 0.0, qz: 0.0, qw: 1.0 }),
+        ..node(id, children)
+    }
+}
+
+/// `node` with its own option values.
+pub(crate) fn node_with(id: &str, options: Value, children: &[&str]) -> WaypointNode {
+    WaypointNode {
+        options: serde_json::from_value(options).ok(),
+        ..node(id, children)
+    }
+}
+
+pub(crate) fn project(roots: &[&str], nodes: Vec<WaypointNode>) -> ProjectData {
+    ProjectData {
+        root_node_ids: roots.iter().map(|r| r.to_string()).collect(),
+        nodes: nodes.into_iter().map(|n| (n.id.clone(), n)).collect(),
+        ..Default::default()
+    }
+}
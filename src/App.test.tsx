@@ -28,6 +28,7 @@ vi.mock('./api/backend', () => ({
     loadROSMap: vi.fn(),
     saveProject: vi.fn(),
     loadProject: vi.fn(),
+    findProjectRecovery: vi.fn().mockResolvedValue(null),
     loadOptionsSchema: vi.fn(),
     exportWaypoints: vi.fn(),
   },
@@ -96,6 +96,23 @@ function App() {
       } catch (e) {
         console.error("Failed to load plugins:", e);
       }
+
+      try {
+        // The backend template library is the source of truth; templates only
+        // known to this browser's storage (older versions) are moved into it
+        const { templates: library, skipped } = await BackendAPI.listExportTemplates();
+        if (skipped.length > 0) {
+          alert(
+            `読み込めないテンプレートファイルがあります。\n${skipped.map((s) => `${s.file}: ${s.error}`).join("\n")}`,
+          );
+        }
+        const local = useAppStore.getState().exportTemplates;
+        const missing = local.filter((t) => !library.some((l) => l.id === t.id));
+        for (const t of missing) await BackendAPI.saveExportTemplate(t);
+        useAppStore.getState().setExportTemplates([...library, ...missing]);
+      } catch (e) {
+        console.error("Failed to load export templates:", e);
+      }
     };
     initApp();
   }, []);
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { OptionsSchema, ProjectData, PluginInstance } from '../types/store';
+import { ExportTemplate, OptionsSchema, ProjectData, PluginInstance, Transform } from '../types/store';
 
 export type MapLoadResult = {
   info: {
@@ -15,25 +15,450 @@ export type MapLoadResult = {
   height: number;
 };
 
+export type WorldOptions = {
+  format: 'gazebo' | 'ignition' | 'stage';
+  wall_height?: number;
+  world_name?: string;
+};
+
+export type WorldMarker = {
+  id: string;
+  transform: Transform;
+};
+
+export type SaveOptions = {
+  backup_count?: number;
+  external_maps?: boolean; // store map images as PNG files next to the project
+};
+
+export type RecoveryInfo = {
+  autosave_path: string;
+  autosave_modified: number;
+  project_modified?: number;
+};
+
+export type LoadedProject = {
+  data: ProjectData;
+  recovery: RecoveryInfo | null; // an autosave newer than the file
+};
+
+export type IntegrityIssue =
+  | { kind: 'id_mismatch'; key: string; id: string }
+  | { kind: 'dangling_root'; id: string }
+  | { kind: 'duplicate_root'; id: string }
+  | { kind: 'dangling_child'; parent_id: string; child_id: string }
+  | { kind: 'multiple_parents'; id: string; parent_ids: string[]; is_root: boolean }
+  | { kind: 'cycle'; ids: string[] }
+  | { kind: 'orphan'; id: string };
+
+export type IntegrityReport = {
+  issues: IntegrityIssue[];
+};
+
+export type RepairProjectResponse = {
+  data: ProjectData;
+  result: { report: IntegrityReport; actions: string[] };
+};
+
+export type MergeConflict = {
+  node_id: string | null; // null for project-level fields such as map layers
+  field: string;
+  base: unknown;
+  ours: unknown;
+  theirs: unknown;
+};
+
+export type MergeResult = {
+  project: ProjectData;
+  conflicts: MergeConflict[];
+  integrity: IntegrityReport;
+};
+
+export type ValueChange = {
+  node_id: string;
+  key: string;
+  before: unknown | null; // null when the key was added
+  after: unknown | null; // null when the key was removed
+};
+
+export type ProjectDiff = {
+  added_nodes: { id: string; node_type: string }[];
+  removed_nodes: { id: string; node_type: string }[];
+  moved_nodes: { id: string; dx: number; dy: number; dz: number; distance: number; dyaw: number }[];
+  type_changes: ValueChange[];
+  option_changes: ValueChange[];
+  generator_param_changes: ValueChange[];
+  reparented: { id: string; from: string | null; to: string | null }[]; // null = root
+  order_changes: { parent_id: string | null; before: string[]; after: string[] }[];
+  layer_changes: { id: string; name: string; kind: 'added' | 'removed' | 'modified'; fields: string[] }[];
+  summary: string;
+};
+
+export type OptionDiagnostic = {
+  node_id: string;
+  option: string;
+  severity: 'error' | 'warning';
+  kind:
+    | 'type_mismatch'
+    | 'not_in_enum'
+    | 'out_of_range'
+    | 'step_mismatch'
+    | 'pattern_mismatch'
+    | 'invalid_pattern'
+    | 'unknown_key'
+    | 'missing_key';
+  expected?: string;
+  found?: string;
+  value?: string | number;
+  allowed?: string[];
+  min?: number | null;
+  max?: number | null;
+  step?: number;
+  pattern?: string;
+  message?: string;
+};
+
+export type OptionReport = {
+  diagnostics: OptionDiagnostic[];
+};
+
+export type SchemaOperation =
+  | { op: 'rename'; from: string; to: string }
+  | { op: 'retype'; name: string; type: string; item_type?: string }
+  | { op: 'fill_defaults'; names?: string[] } // default: every required option
+  | { op: 'strip'; names?: string[] }; // default: every key not in the schema
+
+export type SchemaChangeReport = {
+  operation: string;
+  schema_changed: boolean;
+  changes: { node_id: string; option: string; before: unknown | null; after: unknown | null }[];
+  failures: { node_id: string; option: string; value: unknown; reason: string }[];
+};
+
+export type SchemaMigrationResponse = {
+  data: ProjectData;
+  reports: SchemaChangeReport[];
+};
+
+export type RouteOptions = {
+  index_start?: number;
+};
+
+export type RouteWaypoint = {
+  index: number;
+  id: string;
+  type: string;
+  x: number;
+  y: number;
+  z: number;
+  yaw: number; // radians
+  qx: number;
+  qy: number;
+  qz: number;
+  qw: number;
+  options: Record<string, any>;
+  parent_id?: string; // generator that produced the waypoint
+};
+
+export type Nav2Settings = {
+  frame_id?: string; // default 'map'
+  node_name?: string; // params file only, default 'waypoint_follower'
+};
+
+export type CsvField =
+  | { field: 'index' | 'id' | 'type' | 'parent_id' | 'x' | 'y' | 'z' | 'qx' | 'qy' | 'qz' | 'qw' }
+  | { field: 'yaw'; unit?: 'radians' | 'degrees' }
+  | { field: 'option'; key: string };
+
+export type CsvColumn = CsvField & { header?: string };
+
+export type CsvSettings = {
+  columns?: CsvColumn[]; // default: index, id, x, y, yaw
+  header?: boolean;
+  delimiter?: string; // single ASCII character
+  list_delimiter?: string; // joins list option values
+  precision?: number; // digits after the decimal point
+};
+
+export type ExportFormat =
+  | ({ format: 'nav2_waypoints' } & Nav2Settings)
+  | ({ format: 'nav2_follow_waypoints' } & Nav2Settings)
+  | ({ format: 'nav2_navigate_through_poses' } & Nav2Settings)
+  | ({ format: 'nav2_params' } & Nav2Settings)
+  | ({ format: 'csv' } & CsvSettings);
+
+// Moves waypoints into a target frame before rendering: rigid transform, then axes, then scale
+export type FrameTransform = {
+  x?: number; // pose of the map frame in the target frame (meters)
+  y?: number;
+  z?: number;
+  roll?: number; // radians
+  pitch?: number;
+  yaw?: number;
+  axes?: [string, string, string]; // target axes as signed source axes, e.g. ['x', '-y', 'z']
+  scale?: number; // e.g. 1000 for millimeters
+  frame_id?: string; // written by Nav2 formats and {{frame_id}}
+};
+
+// Backend map rendering; everything is optional
+export type RenderSettings = {
+  scale?: number; // pixels per meter, defaults to the finest map layer's resolution
+  crop?: { min_x: number; min_y: number; max_x: number; max_y: number }; // meters, map frame
+  margin?: number; // meters around the waypoints when there is no map
+  show_map?: boolean;
+  show_path?: boolean;
+  show_zones?: boolean;
+  labels?: 'none' | 'id' | 'index';
+  text_size?: number; // pixels per font dot
+  marker_size?: number; // arrow length in pixels
+};
+
+// Mission report options; the map settings sit alongside them
+export type ReportSettings = RenderSettings & {
+  title?: string;
+  speed?: number; // m/s for legs whose destination has no speed option
+  speed_option?: string; // option read as the speed of the leg arriving at a waypoint, default 'speed'
+};
+
+export type ExportTarget = {
+  source:
+    | { template: string }
+    | { builtin: ExportFormat }
+    | { image: RenderSettings }
+    | { report: ReportSettings }
+    | 'data';
+  path: string; // relative to the chosen folder; {project}, {profile} and {date} are substituted
+  nodes?: { include?: string[]; exclude?: string[] }; // node ids, generators include their outputs
+  transform?: FrameTransform;
+};
+
+export type ExportProfile = {
+  id: string;
+  name: string;
+  targets: ExportTarget[];
+};
+
+export type TemplateDiagnostic = {
+  stage: 'compile' | 'render';
+  message: string;
+  line?: number; // 1-based
+  column?: number;
+};
+
+export type TemplatePreview = {
+  output?: string;
+  error?: TemplateDiagnostic;
+};
+
+export type OptionSource =
+  | { source: 'default' }
+  | { source: 'inherited'; node_id: string }
+  | { source: 'own' };
+
+export type ResolvedOption = OptionSource & {
+  value: any;
+  overrides?: OptionSource[]; // nearest first
+};
+
+export type ArchiveSaveOptions = {
+  options_schema?: OptionsSchema;
+  templates?: ExportTemplate[];
+  map_sources?: Record<string, string>; // layer id -> original map YAML path
+  plugins?: PluginInstance[];
+};
+
+export type LoadedArchive = {
+  project: ProjectData;
+  options_schema?: OptionsSchema;
+  templates: ExportTemplate[];
+  pinned_plugins: string[]; // ids of the plugins the archive carries
+  plugins: PluginInstance[]; // extracted pinned plugins; empty unless extractPlugins was set
+};
+
+// Everything about a template export besides the data and output path
+export type ExportRequest = {
+  route_options?: RouteOptions;
+  helpers?: Record<string, string>;
+  project_name?: string; // `project.name` in templates; defaults to the output file name
+  transform?: FrameTransform;
+  escape_html?: boolean; // the template's setting; escaping is on when missing
+};
+
+export type TemplateListing = {
+  templates: ExportTemplate[];
+  skipped: { file: string; error: string }[]; // library files that could not be parsed
+};
+
 export const BackendAPI = {
   loadROSMap: async (yamlPath: string): Promise<MapLoadResult> => {
     return invoke('load_ros_map', { yamlPath });
   },
 
-  saveProject: async (path: string, data: ProjectData): Promise<void> => {
-    return invoke('save_project', { path, data });
+  generateSimWorld: async (yamlPath: string, outputPath: string, options: WorldOptions, markers?: WorldMarker[]): Promise<void> => {
+    return invoke('generate_sim_world', { yamlPath, outputPath, options, markers: markers || null });
+  },
+
+  // firstSave: the project had no file yet, so its untitled autosave can be dropped
+  saveProject: async (path: string, data: ProjectData, options?: SaveOptions, firstSave = false): Promise<void> => {
+    return invoke('save_project', { path, data, options: options || null, firstSave });
+  },
+
+  saveProjectArchive: async (path: string, data: ProjectData, options?: ArchiveSaveOptions): Promise<void> => {
+    return invoke('save_project_archive', { path, data, options: options || null });
+  },
+
+  // Pinned plugins are executable code; extract them only after the user agreed
+  loadProjectArchive: async (path: string, extractPlugins = false): Promise<LoadedArchive> => {
+    return invoke('load_project_archive', { path, extractPlugins });
+  },
+
+  autosaveProject: async (projectPath: string | null, data: ProjectData): Promise<string> => {
+    return invoke('autosave_project', { projectPath, data });
+  },
+
+  findProjectRecovery: async (projectPath: string | null): Promise<RecoveryInfo | null> => {
+    return invoke('find_project_recovery', { projectPath });
+  },
+
+  discardProjectRecovery: async (projectPath: string | null): Promise<void> => {
+    return invoke('discard_project_recovery', { projectPath });
   },
 
-  loadProject: async (path: string): Promise<ProjectData> => {
+  loadProject: async (path: string): Promise<LoadedProject> => {
     return invoke('load_project', { path });
   },
 
+  validateProject: async (data: ProjectData): Promise<IntegrityReport> => {
+    return invoke('validate_project', { data });
+  },
+
+  repairProject: async (data: ProjectData): Promise<RepairProjectResponse> => {
+    return invoke('repair_project', { data });
+  },
+
+  mergeProjects: async (basePath: string, oursPath: string, theirsPath: string, outputPath: string): Promise<MergeResult> => {
+    return invoke('merge_projects', { basePath, oursPath, theirsPath, outputPath });
+  },
+
+  diffProjects: async (beforePath: string, afterPath: string): Promise<ProjectDiff> => {
+    return invoke('diff_projects', { beforePath, afterPath });
+  },
+
+  buildRoute: async (data: ProjectData, options?: RouteOptions): Promise<RouteWaypoint[]> => {
+    return invoke('build_route', { data, options });
+  },
+
+  resolveNodeOptions: async (data: ProjectData, nodeId: string): Promise<Record<string, ResolvedOption>> => {
+    return invoke('resolve_node_options', { data, nodeId });
+  },
+
+  exportProject: async (
+    path: string,
+    data: ProjectData,
+    template?: string,
+    imageDataB64?: string,
+    request: ExportRequest = {},
+  ): Promise<void> => {
+    return invoke('export_project', { path, data, template: template || null, imageDataB64: imageDataB64 || null, request });
+  },
+
+  exportBuiltin: async (path: string, data: ProjectData, format: ExportFormat, options?: RouteOptions, transform?: FrameTransform): Promise<void> => {
+    return invoke('export_builtin', { path, data, format, options, transform });
+  },
+
+  // Draws the route over the map layers (which need their image_base64) and writes a PNG
+  renderMapImage: async (path: string, data: ProjectData, settings?: RenderSettings, options?: RouteOptions): Promise<void> => {
+    return invoke('render_map_image', { path, data, settings, options });
+  },
+
+  // SVG or PDF by the path's extension; the map layers need their image_base64
+  exportReport: async (path: string, data: ProjectData, settings?: ReportSettings, options?: RouteOptions, projectName?: string): Promise<void> => {
+    return invoke('export_report', { path, data, settings, options, projectName });
+  },
+
+  // Writes every target of the profile or, if one fails, none of them. Returns the written paths.
+  runExportProfile: async (
+    data: ProjectData,
+    profileId: string,
+    baseDir: string,
+    options?: RouteOptions,
+    helpers?: Record<string, string>,
+    projectName?: string,
+  ): Promise<string[]> => {
+    return invoke('run_export_profile', { data, profileId, baseDir, options, helpers, projectName });
+  },
+
+  // Renders against the project's route, or sample waypoints when it has none. Nothing is written.
+  previewTemplate: async (
+    template: ExportTemplate,
+    data?: ProjectData,
+    options?: RouteOptions,
+    helpers?: Record<string, string>,
+    strict = true,
+    projectName?: string,
+  ): Promise<TemplatePreview> => {
+    return invoke('preview_template', { template, data: data ?? null, options, helpers, strict, projectName });
+  },
+
+  // Template library: <app config>/templates/<id>.hbs with front matter, partials in templates/partials
+  // Files that fail to parse come back in `skipped` instead of hiding the rest
+  listExportTemplates: async (): Promise<TemplateListing> => {
+    return invoke('list_export_templates');
+  },
+
+  saveExportTemplate: async (template: ExportTemplate): Promise<void> => {
+    return invoke('save_export_template', { template });
+  },
+
+  deleteExportTemplate: async (id: string): Promise<void> => {
+    return invoke('delete_export_template', { id });
+  },
+
+  importExportTemplate: async (path: string): Promise<ExportTemplate> => {
+    return invoke('import_export_template', { path });
+  },
+
+  exportExportTemplate: async (id: string, path: string): Promise<void> => {
+    return invoke('export_export_template', { id, path });
+  },
+
+  listTemplatePartials: async (): Promise<Record<string, string>> => {
+    return invoke('list_template_partials');
+  },
+
+  saveTemplatePartial: async (name: string, content: string): Promise<void> => {
+    return invoke('save_template_partial', { name, content });
+  },
+
+  deleteTemplatePartial: async (name: string): Promise<void> => {
+    return invoke('delete_template_partial', { name });
+  },
+
   loadOptionsSchema: async (yamlPath: string): Promise<OptionsSchema> => {
     return invoke('load_options_schema', { yamlPath });
   },
 
-  exportWaypoints: async (path: string, waypoints: Record<string, any>[], template?: string, imageB64?: string): Promise<void> => {
-    return invoke('export_waypoints', { path, waypoints, template: template || null, imageDataB64: imageB64 || null });
+  validateOptions: async (data: ProjectData): Promise<OptionReport> => {
+    return invoke('validate_options', { data });
+  },
+
+  migrateOptionsSchema: async (data: ProjectData, operations: SchemaOperation[]): Promise<SchemaMigrationResponse> => {
+    return invoke('migrate_options_schema', { data, operations });
+  },
+
+  saveOptionsSchema: async (yamlPath: string, schema: OptionsSchema): Promise<void> => {
+    return invoke('save_options_schema', { yamlPath, schema });
+  },
+
+  exportWaypoints: async (
+    path: string,
+    waypoints: Record<string, any>[],
+    template?: string,
+    imageB64?: string,
+    helpers?: Record<string, string>, // user template helpers: name -> Handlebars snippet
+    projectName?: string,
+  ): Promise<void> => {
+    return invoke('export_waypoints', { path, waypoints, template: template || null, imageDataB64: imageB64 || null, helpers, projectName });
   },
 
   fetchInstalledPlugins: async (): Promise<PluginInstance[]> => {
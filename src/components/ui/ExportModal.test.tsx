@@ -6,11 +6,16 @@ import { useAppStore } from '../../stores/appStore';
 // Mock Tauri modules
 vi.mock('@tauri-apps/plugin-dialog', () => ({
   save: vi.fn(),
+  open: vi.fn(),
 }));
 
 vi.mock('../../api/backend', () => ({
   BackendAPI: {
-    exportWaypoints: vi.fn().mockResolvedValue(undefined),
+    exportProject: vi.fn().mockResolvedValue(undefined),
+    renderMapImage: vi.fn().mockResolvedValue(undefined),
+    exportReport: vi.fn().mockResolvedValue(undefined),
+    validateOptions: vi.fn().mockResolvedValue({ diagnostics: [] }),
+    previewTemplate: vi.fn().mockResolvedValue({ output: '' }),
   },
 }));
 
@@ -1,8 +1,16 @@
-import { X, Save, Image as ImageIcon } from "lucide-react";
+import { X, Save, Image as ImageIcon, Play, FileText } from "lucide-react";
 import { useState } from "react";
+import { v4 as uuidv4 } from "uuid";
 import { useAppStore } from "../../stores/appStore";
-import { save } from "@tauri-apps/plugin-dialog";
-import { BackendAPI } from "../../api/backend";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { BackendAPI, ExportFormat, ExportProfile, FrameTransform } from "../../api/backend";
+
+// Axis conventions offered for single exports; profiles can use any signed permutation
+const AXIS_PRESETS: { label: string; axes: [string, string, string] }[] = [
+  { label: "x, y, z (ROS)", axes: ["x", "y", "z"] },
+  { label: "x, -y, z (flipped y)", axes: ["x", "-y", "z"] },
+  { label: "y, x, -z (NED)", axes: ["y", "x", "-z"] },
+];
 
 interface ExportModalProps {
   isOpen: boolean;
@@ -15,13 +23,32 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
   const nodes = useAppStore((state) => state.nodes);
   const lastDirectory = useAppStore((state) => state.lastDirectory);
   const setLastDirectory = useAppStore((state) => state.setLastDirectory);
+  // Unsaved projects fall back to the output file name on the backend
+  const projectName = useAppStore((state) => state.projectName) ?? undefined;
   const indexStartIndex = useAppStore((state) => state.indexStartIndex);
   const optionsSchema = useAppStore((state) => state.optionsSchema);
+  const templateHelpers = useAppStore((state) => state.templateHelpers);
+  const mapLayers = useAppStore((state) => state.mapLayers);
+  const exportProfiles = useAppStore((state) => state.exportProfiles);
+  const setExportProfiles = useAppStore((state) => state.setExportProfiles);
 
   const [includeImage, setIncludeImage] = useState(false);
+  const [imageScale, setImageScale] = useState(0); // px/m, 0 = map resolution
+  const [reportSpeed, setReportSpeed] = useState(0.5); // m/s where waypoints have no speed option
   const [selectedFormats, setSelectedFormats] = useState<string[]>([
     "__default_yaml__",
   ]);
+  const [transform, setTransform] = useState({
+    x: 0,
+    y: 0,
+    z: 0,
+    yawDeg: 0,
+    scale: 1,
+    axes: 0, // index into AXIS_PRESETS
+    frameId: "",
+  });
+  // JSON of the profiles while the editor is open
+  const [profilesText, setProfilesText] = useState<string | null>(null);
 
   if (!isOpen) return null;
 
@@ -31,6 +58,126 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
     );
   };
 
+  // Refuse to hand robots malformed parameters
+  const checkOptions = async () => {
+    const report = await BackendAPI.validateOptions({
+      root_node_ids: rootNodeIds,
+      nodes,
+      options_schema: optionsSchema ?? undefined,
+    });
+    const errors = report.diagnostics.filter((d) => d.severity === "error");
+    if (errors.length > 0) {
+      const lines = errors
+        .slice(0, 10)
+        .map((d) => `- ${d.node_id} / ${d.option}: ${d.kind}`);
+      if (errors.length > 10) lines.push(`...他 ${errors.length - 10} 件`);
+      alert(
+        `オプション値にエラーがあるためエクスポートできません。\n${lines.join("\n")}`,
+      );
+      return false;
+    }
+    return true;
+  };
+
+  // Templates read map.resolution/origin from the layer info; the pixels aren't needed
+  const buildExportProjectData = () => ({
+    root_node_ids: rootNodeIds,
+    nodes,
+    options_schema: optionsSchema ?? undefined,
+    map_layers: mapLayers.map((layer) => ({ ...layer, image_base64: "" })),
+    export_profiles: exportProfiles,
+  });
+
+  const handleRunProfile = async (profile: ExportProfile) => {
+    try {
+      if (!(await checkOptions())) return;
+      const dir = await open({
+        directory: true,
+        defaultPath: lastDirectory || undefined,
+        title: `Select Output Folder for "${profile.name}"`,
+      });
+      if (!dir || Array.isArray(dir)) return;
+      setLastDirectory(dir);
+      // Image and report targets draw the map, so they need the layer pixels
+      const written = await BackendAPI.runExportProfile(
+        { ...buildExportProjectData(), map_layers: mapLayers },
+        profile.id,
+        dir,
+        { index_start: indexStartIndex },
+        templateHelpers,
+        projectName,
+      );
+      alert(
+        `プロファイル「${profile.name}」で ${written.length} 件のファイルを書き出しました。\n${written.join("\n")}`,
+      );
+      onClose();
+    } catch (err) {
+      console.error("Failed to run export profile:", err);
+      alert(`エクスポートに失敗しました。\nエラー詳細: ${String(err)}`);
+    }
+  };
+
+  const handleExportReport = async () => {
+    try {
+      if (!(await checkOptions())) return;
+      const path = await save({
+        defaultPath: lastDirectory || undefined,
+        title: "Save Mission Report",
+        filters: [
+          { name: "PDF", extensions: ["pdf"] },
+          { name: "SVG", extensions: ["svg"] },
+        ],
+      });
+      if (!path) return;
+      const lastSlash = Math.max(path.lastIndexOf("/"), path.lastIndexOf("\\"));
+      if (lastSlash > -1) setLastDirectory(path.substring(0, lastSlash));
+      await BackendAPI.exportReport(
+        path,
+        { ...buildExportProjectData(), map_layers: mapLayers },
+        { speed: reportSpeed, scale: imageScale > 0 ? imageScale : undefined },
+        { index_start: indexStartIndex },
+        projectName,
+      );
+      alert(`レポートを書き出しました。\n${path}`);
+    } catch (err) {
+      console.error("Failed to export report:", err);
+      alert(`レポートの出力に失敗しました。\nエラー詳細: ${String(err)}`);
+    }
+  };
+
+  const handleAddProfile = () => {
+    setExportProfiles([
+      ...exportProfiles,
+      {
+        id: uuidv4(),
+        name: `Profile ${exportProfiles.length + 1}`,
+        targets: [{ source: "data", path: "{project}.json" }],
+      },
+    ]);
+  };
+
+  const handleApplyProfiles = () => {
+    if (profilesText === null) return;
+    try {
+      const parsed = JSON.parse(profilesText);
+      if (!Array.isArray(parsed)) throw new Error("profiles must be a JSON array");
+      setExportProfiles(parsed as ExportProfile[]);
+      setProfilesText(null);
+    } catch (err) {
+      alert(`プロファイルの形式が正しくありません。\n${String(err)}`);
+    }
+  };
+
+  const buildFrameTransform = (): FrameTransform => ({
+    x: transform.x,
+    y: transform.y,
+    z: transform.z,
+    yaw: (transform.yawDeg * Math.PI) / 180,
+    scale: transform.scale,
+    axes: AXIS_PRESETS[transform.axes].axes,
+    frame_id: transform.frameId.trim() || undefined,
+  });
+
   const handleExport = async () => {
     if (selectedFormats.length === 0) {
       alert("At least one export format must be selected.");
@@ -38,6 +185,26 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
     }
 
     try {
+      if (!(await checkOptions())) return;
+
+      // Catch template mistakes before the user picks a destination
+      for (const t of exportTemplates.filter((x) =>
+        selectedFormats.includes(x.id),
+      )) {
+        const { error } = await BackendAPI.previewTemplate(
+          t,
+          undefined,
+          undefined,
+          templateHelpers,
+          false,
+        );
+        if (error) {
+          const pos = error.line ? ` (${error.line}:${error.column ?? 0})` : "";
+          alert(`テンプレート「${t.name}」にエラーがあります${pos}。\n${error.message}`);
+          return;
+        }
+      }
+
       const savePath = await save({
         defaultPath: lastDirectory || undefined,
         title: "Select Destination Base Path",
@@ -57,74 +224,19 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
 
         if (lastSlash > -1) setLastDirectory(basePath.substring(0, lastSlash));
 
-        // 1. Flatten all waypoints including generator children
-        const flatIds: string[] = [];
-        rootNodeIds.forEach((id) => {
-          const node = nodes[id];
-          if (!node) return;
-          if (node.type === "manual") flatIds.push(id);
-          else if (node.type === "generator" && node.children_ids) {
-            flatIds.push(...node.children_ids);
-          }
-        });
-
-        // 2. Hydrate and map
-        const waypointsToExport = flatIds
-          .map((id, index) => {
-            const node = nodes[id];
-            if (!node) return null;
-
-            const fullOptions: Record<string, any> = {};
-            if (optionsSchema && optionsSchema.options) {
-              optionsSchema.options.forEach((opt: any) => {
-                fullOptions[opt.name] =
-                  node.options && node.options[opt.name] !== undefined
-                    ? node.options[opt.name]
-                    : opt.default;
-              });
-            }
-            if (node.options) {
-              Object.keys(node.options).forEach((k) => {
-                if (fullOptions[k] === undefined)
-                  fullOptions[k] = node.options![k];
-              });
-            }
-
-            const qx = node.transform?.qx || 0;
-            const qy = node.transform?.qy || 0;
-            const qz = node.transform?.qz || 0;
-            const qw = node.transform?.qw ?? 1;
-            const yawVal = Math.atan2(
-              2.0 * (qw * qz + qx * qy),
-              1.0 - 2.0 * (qy * qy + qz * qz),
-            );
+        // Flatten the node tree on the backend so every export path shares the same order
+        const projectData = buildExportProjectData();
+        const routeOptions = { index_start: indexStartIndex };
+        const frameTransform = buildFrameTransform();
 
-            return {
-              index: index + indexStartIndex,
-              id: node.id,
-              type: node.type,
-              x: node.transform?.x ?? 0,
-              y: node.transform?.y ?? 0,
-              z: node.transform?.z ?? 0,
-              yaw: yawVal,
-              qx,
-              qy,
-              qz,
-              qw,
-              options: fullOptions,
-            };
-          })
-          .filter((n) => n !== null);
-
-        // Extract image if requested
-        let imageDataB64 = undefined;
+        // Drawn on the backend so the picture doesn't depend on the canvas view
         if (includeImage) {
-          useAppStore.setState({ shouldFitToMaps: Date.now() });
-          await new Promise((r) => setTimeout(r, 800)); // wait for Pixi
-          const canvas = document.querySelector("canvas");
-          if (canvas) {
-            imageDataB64 = canvas.toDataURL("image/png").split(",")[1];
-          }
+          await BackendAPI.renderMapImage(
+            `${basePath}.png`,
+            { ...projectData, map_layers: mapLayers },
+            { scale: imageScale > 0 ? imageScale : undefined },
+            routeOptions,
+          );
         }
 
         // Export each selected format
@@ -134,6 +246,9 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
           let extension = "yaml";
           let suffix = "";
           let templateContent = undefined;
+          let escapeHtml = true;
+          let builtin: ExportFormat["format"] | undefined = undefined;
+          let builtinSettings: Record<string, any> | undefined = undefined;
 
           // Check Default Formats first
           const defaultFormat = useAppStore
@@ -142,11 +257,14 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
           if (defaultFormat) {
             extension = defaultFormat.extension;
             suffix = defaultFormat.suffix;
+            builtin = defaultFormat.builtin as ExportFormat["format"] | undefined;
+            builtinSettings = defaultFormat.settings;
           } else {
             // Check Custom Templates
             const t = exportTemplates.find((x) => x.id === formatId);
             if (t) {
               templateContent = t.content;
+              escapeHtml = t.escape_html ?? true;
               extension = t.extension;
               suffix = t.suffix || "";
             } else {
@@ -156,13 +274,24 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
 
           const finalPath = `${basePath}${suffix}.${extension}`;
 
-          // Only send image on the first format to avoid overwriting identical PNGs wastefully
-          await BackendAPI.exportWaypoints(
-            finalPath,
-            waypointsToExport as any[],
-            templateContent,
-            i === 0 ? imageDataB64 : undefined,
-          );
+          if (builtin) {
+            await BackendAPI.exportBuiltin(
+              finalPath,
+              projectData,
+              { ...builtinSettings, format: builtin } as ExportFormat,
+              routeOptions,
+              frameTransform,
+            );
+            continue;
+          }
+
+          await BackendAPI.exportProject(finalPath, projectData, templateContent, undefined, {
+            route_options: routeOptions,
+            helpers: templateHelpers,
+            project_name: projectName,
+            transform: frameTransform,
+            escape_html: escapeHtml,
+          });
         }
 
         alert("エクスポートが完了しました。");
@@ -189,6 +318,105 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
 
         <div className="p-6 space-y-6">
           <div className="space-y-4">
+            <div className="space-y-3">
+              <div className="flex items-center justify-between">
+                <label className="text-sm font-medium text-slate-300">
+                  Export Profiles
+                </label>
+                <div className="flex gap-2">
+                  <button
+                    onClick={handleAddProfile}
+                    className="text-xs text-blue-300 hover:text-white transition-colors"
+                  >
+                    New Profile
+                  </button>
+                  <button
+                    onClick={() =>
+                      setProfilesText(
+                        profilesText === null
+                          ? JSON.stringify(exportProfiles, null, 2)
+                          : null,
+                      )
+                    }
+                    className="text-xs text-slate-400 hover:text-white transition-colors"
+                  >
+                    {profilesText === null ? "Edit JSON" : "Cancel"}
+                  </button>
+                </div>
+              </div>
+              {profilesText !== null ? (
+                <div className="space-y-2">
+                  <textarea
+                    value={profilesText}
+                    onChange={(e) => setProfilesText(e.target.value)}
+                    rows={10}
+                    spellCheck={false}
+                    className="w-full bg-slate-950 border border-slate-700 rounded p-2 text-[11px] font-mono text-slate-300"
+                  />
+                  <button
+                    onClick={handleApplyProfiles}
+                    className="ui-btn ui-btn-sm ui-btn-primary"
+                  >
+                    Apply
+                  </button>
+                </div>
+              ) : exportProfiles.length === 0 ? (
+                <p className="text-xs text-slate-500">
+                  No profiles. A profile writes several formats into one
+                  folder in a single step.
+                </p>
+              ) : (
+                <div className="flex flex-col gap-2 bg-slate-900/50 p-3 rounded border border-slate-700">
+                  {exportProfiles.map((p) => (
+                    <div key={p.id} className="flex items-center justify-between">
+                      <span className="text-sm text-slate-300">
+                        {p.name}{" "}
+                        <span className="text-xs text-slate-500">
+                          ({p.targets.length} targets)
+                        </span>
+                      </span>
+                      <button
+                        onClick={() => handleRunProfile(p)}
+                        className="ui-btn ui-btn-sm ui-btn-primary"
+                      >
+                        <Play size={12} /> Run
+                      </button>
+                    </div>
+                  ))}
+                </div>
+              )}
+            </div>
+
+            <div className="space-y-2">
+              <label className="text-sm font-medium text-slate-300 block">
+                Mission Report
+              </label>
+              <div className="flex items-end gap-2">
+                <label className="text-xs text-slate-400 flex-1">
+                  Default speed (m/s)
+                  <input
+                    type="number"
+                    min={0.01}
+                    step={0.1}
+                    value={reportSpeed}
+                    onChange={(e) => setReportSpeed(Number(e.target.value) || 0.5)}
+                    className="ui-input w-full"
+                  />
+                </label>
+                <button
+                  onClick={handleExportReport}
+                  className="ui-btn ui-btn-sm ui-btn-primary"
+                >
+                  <FileText size={12} /> Export Report...
+                </button>
+              </div>
+              <p className="text-xs text-slate-500">
+                PDF or SVG with the map, waypoint and leg tables, estimated
+                time (from each waypoint's speed option) and validation
+                warnings.
+              </p>
+            </div>
+
             <div className="space-y-3">
               <label className="text-sm font-medium text-slate-300 block">
                 Output Formats
@@ -236,6 +464,79 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
               </p>
             </div>
 
+            <div className="space-y-2 pt-4 border-t border-slate-700">
+              <label className="text-sm font-medium text-slate-300 block">
+                Frame Transform
+              </label>
+              <div className="grid grid-cols-4 gap-2">
+                {(
+                  [
+                    ["x", "X [m]"],
+                    ["y", "Y [m]"],
+                    ["z", "Z [m]"],
+                    ["yawDeg", "Yaw [°]"],
+                  ] as const
+                ).map(([key, label]) => (
+                  <label key={key} className="text-xs text-slate-400">
+                    {label}
+                    <input
+                      type="number"
+                      value={transform[key]}
+                      onChange={(e) =>
+                        setTransform({ ...transform, [key]: Number(e.target.value) || 0 })
+                      }
+                      className="ui-input w-full"
+                    />
+                  </label>
+                ))}
+              </div>
+              <div className="grid grid-cols-3 gap-2">
+                <label className="text-xs text-slate-400">
+                  Scale
+                  <input
+                    type="number"
+                    value={transform.scale}
+                    onChange={(e) =>
+                      setTransform({ ...transform, scale: Number(e.target.value) || 1 })
+                    }
+                    className="ui-input w-full"
+                  />
+                </label>
+                <label className="text-xs text-slate-400">
+                  Axes
+                  <select
+                    value={transform.axes}
+                    onChange={(e) =>
+                      setTransform({ ...transform, axes: Number(e.target.value) })
+                    }
+                    className="ui-input w-full"
+                  >
+                    {AXIS_PRESETS.map((p, i) => (
+                      <option key={p.label} value={i}>
+                        {p.label}
+                      </option>
+                    ))}
+                  </select>
+                </label>
+                <label className="text-xs text-slate-400">
+                  frame_id
+                  <input
+                    type="text"
+                    value={transform.frameId}
+                    placeholder="map"
+                    onChange={(e) =>
+                      setTransform({ ...transform, frameId: e.target.value })
+                    }
+                    className="ui-input w-full"
+                  />
+                </label>
+              </div>
+              <p className="text-xs text-slate-500">
+                Pose of the map frame in the target frame, then the axis
+                convention, then the unit scale (1000 = millimeters).
+              </p>
+            </div>
+
             <div className="pt-4 border-t border-slate-700">
               <label className="flex items-start gap-3 cursor-pointer group">
                 <div className="relative flex items-center justify-center pt-0.5">
@@ -252,15 +553,27 @@ export function ExportModal({ isOpen, onClose }: ExportModalProps) {
                 </div>
                 <div>
                   <p className="text-sm font-bold text-slate-200 group-hover:text-white transition-colors">
-                    Include Canvas Image
+                    Include Map Image
                   </p>
                   <p className="text-xs text-slate-500 mt-1">
-                    Saves a .png image of the current visible canvas (Map +
-                    Waypoints with visible attributes) alongside the exported
-                    file.
+                    Saves a .png of the visible map layers with the route,
+                    waypoint arrows, ids and zones alongside the exported file,
+                    independent of the current canvas view.
                   </p>
                 </div>
               </label>
+              {includeImage && (
+                <label className="text-xs text-slate-400 block mt-2 ml-8">
+                  Scale (px/m, 0 = map resolution)
+                  <input
+                    type="number"
+                    min={0}
+                    value={imageScale}
+                    onChange={(e) => setImageScale(Math.max(0, Number(e.target.value) || 0))}
+                    className="ui-input w-full"
+                  />
+                </label>
+              )}
             </div>
           </div>
 
@@ -1,13 +1,8 @@
 import React from "react";
+import { OptionDef } from "../../types/store";
 
-export interface PluginProperty {
-  name: string;
-  label?: string;
-  type: string;
-  default?: any;
-  options?: string[];
-  description?: string;
-}
+// Plugin properties share the options schema language
+export type PluginProperty = OptionDef;
 
 interface PluginPropertyEditorProps {
   property: PluginProperty;
@@ -27,7 +22,15 @@ export const PluginPropertyEditor: React.FC<PluginPropertyEditorProps> = ({
 
   return (
     <div className={`space-y-1 ${className}`}>
-      <label className="text-xs font-semibold text-slate-300">{label}</label>
+      <label className="text-xs font-semibold text-slate-300">
+        {label}
+        {property.required && <span className="text-amber-500">*</span>}
+        {property.unit && (
+          <span className="ml-1 text-[10px] font-normal text-slate-500">
+            [{property.unit}]
+          </span>
+        )}
+      </label>
 
       {property.type === "boolean" ? (
         <label className="flex items-center gap-3 mt-1 bg-slate-800/50 p-2 rounded border border-slate-700/50 cursor-pointer hover:bg-slate-800/80 transition-colors">
@@ -42,7 +45,9 @@ export const PluginPropertyEditor: React.FC<PluginPropertyEditorProps> = ({
       ) : property.type === "integer" || property.type === "float" ? (
         <input
           type="number"
-          step={property.type === "float" ? "any" : "1"}
+          step={property.step ?? (property.type === "float" ? "any" : "1")}
+          min={property.min}
+          max={property.max}
           value={value ?? ""}
           onChange={(e) => {
             const val =
@@ -54,13 +59,14 @@ export const PluginPropertyEditor: React.FC<PluginPropertyEditorProps> = ({
           className="ui-input-sm"
           placeholder={String(property.default ?? "")}
         />
-      ) : Array.isArray(property.options) && property.options.length > 0 ? (
+      ) : Array.isArray(property.enum_values) &&
+        property.enum_values.length > 0 ? (
         <select
           value={value ?? property.default ?? ""}
           onChange={(e) => onChange(e.target.value)}
           className="ui-select-sm"
         >
-          {property.options.map((opt: string) => (
+          {property.enum_values.map((opt: string) => (
             <option key={opt} value={opt}>
               {opt}
             </option>
@@ -70,8 +76,9 @@ export const PluginPropertyEditor: React.FC<PluginPropertyEditorProps> = ({
         <input
           type="text"
           value={value || ""}
+          pattern={property.pattern}
           onChange={(e) => onChange(e.target.value)}
-          className="ui-input-sm"
+          className="ui-input-sm invalid:border-red-500"
           placeholder={String(property.default ?? "")}
         />
       )}
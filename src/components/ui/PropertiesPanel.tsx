@@ -451,7 +451,15 @@ export function PropertiesPanel() {
             </div>
           ) : (
             <div className="space-y-2 pt-2">
-              {optionsSchema.options.map((opt: OptionDef) => {
+              {optionsSchema.options.filter((opt: OptionDef) => {
+                if (!opt.visible_when || isMultiSelection) return true;
+                const cond = opt.visible_when;
+                const ref = optionsSchema.options.find((o) => o.name === cond.option);
+                const current = node?.options?.[cond.option] ?? ref?.default;
+                return Array.isArray(cond.equals)
+                  ? cond.equals.includes(current)
+                  : cond.equals === current;
+              }).map((opt: OptionDef) => {
                 const nodeOptVal = isMultiSelection
                   ? ""
                   : (node?.options?.[opt.name] ?? opt.default ?? "");
@@ -484,11 +492,20 @@ export function PropertiesPanel() {
                 return (
                   <div key={opt.name}>
                     <div className="flex justify-between items-center mb-1">
-                      <label className="text-xs text-slate-500">
-                        {opt.label || opt.name}{" "}
+                      <label
+                        className="text-xs text-slate-500"
+                        title={opt.description}
+                      >
+                        {opt.label || opt.name}
+                        {opt.required && <span className="text-amber-500">*</span>}{" "}
                         <span className="opacity-50 text-[10px] ml-1 uppercase">
                           ({opt.type})
                         </span>
+                        {opt.unit && (
+                          <span className="opacity-50 text-[10px] ml-1">
+                            [{opt.unit}]
+                          </span>
+                        )}
                       </label>
                       <button
                         onClick={() =>
@@ -567,7 +584,9 @@ export function PropertiesPanel() {
                     ) : opt.type === "integer" || opt.type === "float" ? (
                       <input
                         type="number"
-                        step={opt.type === "float" ? "0.1" : "1"}
+                        step={opt.step ?? (opt.type === "float" ? "0.1" : "1")}
+                        min={opt.min}
+                        max={opt.max}
                         value={String(nodeOptVal)}
                         placeholder={
                           isMultiSelection ? "Mixed" : String(opt.default || "")
@@ -595,8 +614,9 @@ export function PropertiesPanel() {
                         placeholder={
                           isMultiSelection ? "Mixed" : String(opt.default || "")
                         }
+                        pattern={opt.pattern}
                         onChange={(e) => handleChange(e.target.value)}
-                        className={`w-full bg-slate-900 border rounded px-2 py-1 text-sm text-white focus:outline-none focus:border-primary placeholder:text-slate-600 ${
+                        className={`w-full bg-slate-900 border rounded px-2 py-1 text-sm text-white focus:outline-none focus:border-primary placeholder:text-slate-600 invalid:border-red-500 ${
                           String(nodeOptVal).trim() === "" && !isMultiSelection
                             ? "border-amber-500/50"
                             : "border-slate-700"
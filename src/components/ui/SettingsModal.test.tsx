@@ -10,6 +10,8 @@ vi.mock('../../api/backend', () => ({
     loadOptionsSchema: vi.fn(),
     scanCustomPlugin: vi.fn(),
     checkSdkVersion: vi.fn().mockResolvedValue("1.0.0"),
+    listTemplatePartials: vi.fn().mockResolvedValue({}),
+    saveExportTemplate: vi.fn().mockResolvedValue(undefined),
   },
 }));
 
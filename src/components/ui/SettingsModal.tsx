@@ -1,9 +1,37 @@
-import { X, Plus, Trash2, Save, RefreshCw } from "lucide-react";
+import { X, Plus, Trash2, Save, RefreshCw, Eye } from "lucide-react";
 import { useState, useEffect } from "react";
 import { useAppStore } from "../../stores/appStore";
 import { OptionDef } from "../../types/store";
+import { CsvColumn, CsvSettings, TemplatePreview } from "../../api/backend";
 import { v4 as uuidv4 } from "uuid";
 
+const CSV_FIELDS = ["index", "id", "type", "parent_id", "x", "y", "z", "qx", "qy", "qz", "qw"];
+
+/** CSV columns as the comma-separated tokens shown in the settings form. */
+function csvColumnsToText(columns?: CsvColumn[]): string {
+  if (!columns) return "index, id, x, y, yaw";
+  return columns
+    .map((c) => {
+      if (c.field === "option") return c.key;
+      if (c.field === "yaw" && c.unit === "degrees") return "yaw_deg";
+      return c.field;
+    })
+    .join(", ");
+}
+
+function textToCsvColumns(text: string): CsvColumn[] {
+  return text
+    .split(",")
+    .map((token) => token.trim())
+    .filter((token) => token !== "")
+    .map((token): CsvColumn => {
+      if (token === "yaw") return { field: "yaw", unit: "radians" };
+      if (token === "yaw_deg") return { field: "yaw", unit: "degrees" };
+      if (CSV_FIELDS.includes(token)) return { field: token } as CsvColumn;
+      return { field: "option", key: token };
+    });
+}
+
 interface SettingsModalProps {
   isOpen: boolean;
   onClose: () => void;
@@ -42,6 +70,8 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
   const updateDefaultExportFormat = useAppStore(
     (state) => state.updateDefaultExportFormat,
   );
+  const templateHelpers = useAppStore((state) => state.templateHelpers);
+  const setTemplateHelpers = useAppStore((state) => state.setTemplateHelpers);
 
   type TabType = "general" | "options" | "export" | "plugins";
   const [activeTab, setActiveTab] = useState<TabType>("general");
@@ -50,6 +80,10 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
   const setPluginSettings = useAppStore((state) => state.setPluginSettings);
   const setPlugins = useAppStore((state) => state.setPlugins);
   const [localOptions, setLocalOptions] = useState<OptionDef[]>([]);
+  const [partials, setPartials] = useState<Record<string, string>>({});
+  const [templatePreviews, setTemplatePreviews] = useState<
+    Record<string, TemplatePreview>
+  >({});
 
   const [pythonEnvs, setPythonEnvs] = useState<string[]>([]);
   const [bundledSdkVersion, setBundledSdkVersion] = useState<string | null>(
@@ -67,6 +101,9 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
         BackendAPI.checkSdkVersion()
           .then((v) => setBundledSdkVersion(v))
           .catch(() => setBundledSdkVersion(null));
+        BackendAPI.listTemplatePartials()
+          .then((p) => setPartials(p))
+          .catch(console.error);
       });
     }
   }, [isOpen, globalOptionsSchema]);
@@ -87,7 +124,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     return true; // string/list are generally freeform in this basic level
   };
 
-  const handleSaveOptions = () => {
+  const handleSaveOptions = async () => {
     // Validation
     const hasEmptyName = localOptions.some((opt) => opt.name.trim() === "");
     const names = localOptions.map((opt) => opt.name);
@@ -120,6 +157,40 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
       return { ...opt, default: parsedDefault };
     });
 
+    // Convert existing node values for options whose type changed
+    const retyped = parsedOptions.filter((opt) => {
+      const prev = globalOptionsSchema?.options.find((o) => o.name === opt.name);
+      return prev && (prev.type !== opt.type || prev.item_type !== opt.item_type);
+    });
+    if (retyped.length > 0) {
+      try {
+        const { BackendAPI } = await import("../../api/backend");
+        const { nodes, rootNodeIds } = useAppStore.getState();
+        const { data, reports } = await BackendAPI.migrateOptionsSchema(
+          { root_node_ids: rootNodeIds, nodes, options_schema: { options: parsedOptions } },
+          retyped.map((opt) => ({
+            op: "retype" as const,
+            name: opt.name,
+            type: opt.type,
+            item_type: opt.item_type,
+          })),
+        );
+        useAppStore.setState({ nodes: data.nodes });
+        const failures = reports.flatMap((r) => r.failures);
+        if (failures.length > 0) {
+          alert(
+            `${failures.length} 件の値を新しい型に変換できませんでした。\n` +
+              failures
+                .slice(0, 10)
+                .map((f) => `- ${f.node_id} / ${f.option}: ${f.reason}`)
+                .join("\n"),
+          );
+        }
+      } catch (err) {
+        console.error("Failed to convert option values:", err);
+      }
+    }
+
     setGlobalOptionsSchema({ options: parsedOptions });
     alert("Schema applied successfully.");
   };
@@ -148,6 +219,63 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     setLocalOptions(localOptions.filter((_, i) => i !== index));
   };
 
+  const handleExportTemplateFile = async (templateId: string) => {
+    const template = globalExportTemplates.find((t) => t.id === templateId);
+    if (!template) return;
+    try {
+      const { save } = await import("@tauri-apps/plugin-dialog");
+      const { BackendAPI } = await import("../../api/backend");
+      const savePath = await save({
+        defaultPath: `${template.name}.hbs`,
+        filters: [{ name: "Handlebars Template", extensions: ["hbs"] }],
+      });
+      if (!savePath) return;
+      // Save first so pending edits are part of the exported file
+      await BackendAPI.saveExportTemplate(template);
+      await BackendAPI.exportExportTemplate(template.id, savePath);
+    } catch (err) {
+      console.error("Failed to export template:", err);
+      alert(`テンプレートの書き出しに失敗しました。\nエラー詳細: ${String(err)}`);
+    }
+  };
+
+  const handleSavePartial = async (name: string, content: string) => {
+    const { BackendAPI } = await import("../../api/backend");
+    try {
+      await BackendAPI.saveTemplatePartial(name, content);
+    } catch (err) {
+      alert(`パーシャルの保存に失敗しました。\nエラー詳細: ${String(err)}`);
+    }
+  };
+
+  const handlePreviewTemplate = async (templateId: string) => {
+    const template = globalExportTemplates.find((t) => t.id === templateId);
+    if (!template) return;
+    const { BackendAPI } = await import("../../api/backend");
+    const state = useAppStore.getState();
+    try {
+      // Strict: a misspelled field fails here instead of exporting as an empty string
+      const preview = await BackendAPI.previewTemplate(
+        template,
+        {
+          root_node_ids: state.rootNodeIds,
+          nodes: state.nodes,
+          options_schema: state.optionsSchema ?? undefined,
+        },
+        { index_start: state.indexStartIndex },
+        templateHelpers,
+        true,
+        state.projectName ?? undefined,
+      );
+      setTemplatePreviews((prev) => ({ ...prev, [templateId]: preview }));
+    } catch (err) {
+      setTemplatePreviews((prev) => ({
+        ...prev,
+        [templateId]: { error: { stage: "render", message: String(err) } },
+      }));
+    }
+  };
+
   const insertTemplateVar = (templateId: string, text: string) => {
     const el = document.getElementById(
       `template-${templateId}`,
@@ -428,6 +556,37 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                     >
                       <Plus size={14} /> Load from File
                     </button>
+                    <button
+                      onClick={async () => {
+                        try {
+                          const { save } =
+                            await import("@tauri-apps/plugin-dialog");
+                          const { BackendAPI } =
+                            await import("../../api/backend");
+                          const savePath = await save({
+                            defaultPath: lastDirectory || undefined,
+                            filters: [
+                              {
+                                name: "Options Schema YAML",
+                                extensions: ["yaml", "yml"],
+                              },
+                            ],
+                          });
+                          if (!savePath) return;
+                          await BackendAPI.saveOptionsSchema(savePath, {
+                            options: localOptions,
+                          });
+                        } catch (err) {
+                          console.error("Failed to export options schema:", err);
+                          alert(
+                            `オプションスキーマの書き出しに失敗しました。\nエラー詳細: ${String(err)}`,
+                          );
+                        }
+                      }}
+                      className="ui-btn ui-btn-secondary ui-btn-sm"
+                    >
+                      <Save size={14} /> Export to File
+                    </button>
                     <button
                       onClick={handleAddOption}
                       className="ui-btn ui-btn-secondary ui-btn-sm"
@@ -641,6 +800,47 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                       formats.
                     </p>
                   </div>
+                  <div className="flex gap-2">
+                  <button
+                    onClick={async () => {
+                      try {
+                        const { open } =
+                          await import("@tauri-apps/plugin-dialog");
+                        const { BackendAPI } =
+                          await import("../../api/backend");
+                        const selectedPath = await open({
+                          multiple: false,
+                          directory: false,
+                          filters: [
+                            { name: "Handlebars Template", extensions: ["hbs"] },
+                          ],
+                        });
+                        if (!selectedPath) return;
+                        const imported = await BackendAPI.importExportTemplate(
+                          typeof selectedPath === "string"
+                            ? selectedPath
+                            : (selectedPath as any).path,
+                        );
+                        // The backend already stored it; mirror it in the store
+                        useAppStore.setState((state) => ({
+                          exportTemplates: [
+                            ...state.exportTemplates.filter(
+                              (t) => t.id !== imported.id,
+                            ),
+                            imported,
+                          ],
+                        }));
+                      } catch (err) {
+                        console.error("Failed to import template:", err);
+                        alert(
+                          `テンプレートの読み込みに失敗しました。\nエラー詳細: ${String(err)}`,
+                        );
+                      }
+                    }}
+                    className="ui-btn ui-btn-secondary ui-btn-sm"
+                  >
+                    <Plus size={14} /> Import
+                  </button>
                   <button
                     onClick={() =>
                       addExportTemplate({
@@ -656,6 +856,7 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                   >
                     <Plus size={14} /> New Template
                   </button>
+                  </div>
                 </div>
 
                 <div className="space-y-4">
@@ -702,6 +903,94 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                       </div>
                     </div>
                   ))}
+                  {defaultExportFormats
+                    .filter((format) => format.builtin === "csv")
+                    .map((format) => {
+                      const settings: CsvSettings = format.settings ?? {};
+                      const update = (changes: Partial<CsvSettings>) =>
+                        updateDefaultExportFormat(format.id, {
+                          settings: { ...settings, ...changes },
+                        });
+                      return (
+                        <div
+                          key={`${format.id}-settings`}
+                          className="bg-slate-900 rounded-lg border border-slate-700/50 p-3 space-y-3"
+                        >
+                          <span className="text-sm font-bold text-slate-200">
+                            {format.name} Columns
+                          </span>
+                          <input
+                            type="text"
+                            defaultValue={csvColumnsToText(settings.columns)}
+                            onBlur={(e) =>
+                              update({ columns: textToCsvColumns(e.target.value) })
+                            }
+                            className="ui-input w-full"
+                            placeholder="index, id, x, y, yaw"
+                          />
+                          <p className="text-xs text-slate-500">
+                            Comma-separated: index, id, type, parent_id, x, y,
+                            z, yaw, yaw_deg, qx, qy, qz, qw. Any other name is
+                            read from the waypoint options.
+                          </p>
+                          <div className="flex items-center gap-4 text-xs text-slate-400 font-medium">
+                            <label className="flex items-center gap-2">
+                              <input
+                                type="checkbox"
+                                checked={settings.header ?? true}
+                                onChange={(e) =>
+                                  update({ header: e.target.checked })
+                                }
+                                className="ui-checkbox"
+                              />
+                              Header
+                            </label>
+                            <label className="flex items-center gap-2">
+                              Delimiter :
+                              <input
+                                type="text"
+                                maxLength={1}
+                                value={settings.delimiter ?? ","}
+                                onChange={(e) =>
+                                  update({ delimiter: e.target.value || "," })
+                                }
+                                className="ui-input w-10"
+                              />
+                            </label>
+                            <label className="flex items-center gap-2">
+                              List :
+                              <input
+                                type="text"
+                                value={settings.list_delimiter ?? ";"}
+                                onChange={(e) =>
+                                  update({ list_delimiter: e.target.value })
+                                }
+                                className="ui-input w-10"
+                              />
+                            </label>
+                            <label className="flex items-center gap-2">
+                              Decimals :
+                              <input
+                                type="number"
+                                min={0}
+                                max={12}
+                                value={settings.precision ?? ""}
+                                onChange={(e) =>
+                                  update({
+                                    precision:
+                                      e.target.value === ""
+                                        ? undefined
+                                        : Number(e.target.value),
+                                  })
+                                }
+                                className="ui-input w-16"
+                                placeholder="full"
+                              />
+                            </label>
+                          </div>
+                        </div>
+                      );
+                    })}
                 </div>
 
                 <div className="bg-slate-900 border border-slate-700/50 p-3 rounded-lg text-xs text-slate-300 mt-6 mt-4">
@@ -773,6 +1062,35 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                             placeholder="yaml"
                           />
                         </div>
+                        <label
+                          className="flex items-center gap-1 text-xs text-slate-400 font-medium"
+                          title={`Write & < > " ' in values as HTML entities, as Handlebars does by default. Turn off for YAML/CSV output.`}
+                        >
+                          <input
+                            type="checkbox"
+                            checked={template.escape_html ?? true}
+                            onChange={(e) =>
+                              updateExportTemplate(template.id, {
+                                escape_html: e.target.checked,
+                              })
+                            }
+                          />
+                          HTML Escape
+                        </label>
+                        <button
+                          onClick={() => handleExportTemplateFile(template.id)}
+                          className="ui-btn ui-btn-secondary ui-btn-sm ml-2"
+                          title="Save as a .hbs file with front matter to share with other machines"
+                        >
+                          <Save size={14} /> Export
+                        </button>
+                        <button
+                          onClick={() => handlePreviewTemplate(template.id)}
+                          className="ui-btn ui-btn-secondary ui-btn-sm ml-2"
+                          title="Render against the current project (or sample waypoints) without writing a file"
+                        >
+                          <Eye size={14} /> Preview
+                        </button>
                         <button
                           onClick={() => removeExportTemplate(template.id)}
                           className="p-1.5 text-slate-500 hover:text-red-400 hover:bg-red-400/10 rounded transition-colors ml-2"
@@ -793,6 +1111,22 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                           placeholder="{{#each waypoints}}..."
                           spellCheck="false"
                         />
+                        {templatePreviews[template.id] && (
+                          <div className="mt-2 space-y-1">
+                            {templatePreviews[template.id].error ? (
+                              <p className="text-xs text-red-400 font-mono whitespace-pre-wrap">
+                                {templatePreviews[template.id].error!.line !==
+                                  undefined &&
+                                  `Line ${templatePreviews[template.id].error!.line}, column ${templatePreviews[template.id].error!.column ?? 0}: `}
+                                {templatePreviews[template.id].error!.message}
+                              </p>
+                            ) : (
+                              <pre className="max-h-40 overflow-auto bg-slate-950 border border-slate-800 rounded p-2 text-[11px] font-mono text-slate-300">
+                                {templatePreviews[template.id].output}
+                              </pre>
+                            )}
+                          </div>
+                        )}
                         <div className="mt-2 flex flex-wrap gap-1 items-center">
                           <span className="text-xs font-bold text-slate-500 mr-2">
                             Core:
@@ -819,6 +1153,53 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                             </button>
                           ))}
                         </div>
+                        <div className="mt-1 flex flex-wrap gap-1 items-center">
+                          <span className="text-xs font-bold text-slate-500 mr-2">
+                            Derived:
+                          </span>
+                          {[
+                            "{{yaw_deg}}",
+                            "{{distance_from_prev}}",
+                            "{{cumulative_distance}}",
+                            "{{heading}}",
+                            "{{parent_id}}",
+                            "{{@root.project.name}}",
+                            "{{@root.project.exported_at}}",
+                            "{{@root.map.resolution}}",
+                            "{{json @root.map.origin}}",
+                            "{{@root.map.image}}",
+                          ].map((v) => (
+                            <button
+                              key={v}
+                              onClick={() => insertTemplateVar(template.id, v)}
+                              className="bg-slate-800 hover:bg-slate-700 px-1.5 py-0.5 rounded text-[10px] font-mono text-amber-300 border border-slate-700 transition-colors"
+                            >
+                              {v}
+                            </button>
+                          ))}
+                        </div>
+                        <div className="mt-1 flex flex-wrap gap-1 items-center">
+                          <span className="text-xs font-bold text-slate-500 mr-2">
+                            Helpers:
+                          </span>
+                          {[
+                            "{{add @index 1}}",
+                            "{{fixed x 3}}",
+                            "{{round (deg (yaw this)) 1}}",
+                            "{{pad index 3}}",
+                            "{{json options}}",
+                            "{{#if (eq type \"manual\")}}{{/if}}",
+                            "{{#unless @last}},{{/unless}}",
+                          ].map((v) => (
+                            <button
+                              key={v}
+                              onClick={() => insertTemplateVar(template.id, v)}
+                              className="bg-slate-800 hover:bg-slate-700 px-1.5 py-0.5 rounded text-[10px] font-mono text-emerald-300 border border-slate-700 transition-colors"
+                            >
+                              {v}
+                            </button>
+                          ))}
+                        </div>
                         {globalOptionsSchema?.options &&
                           globalOptionsSchema.options.length > 0 && (
                             <div className="mt-1 flex flex-wrap gap-1 items-center">
@@ -850,6 +1231,144 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                     </div>
                   )}
                 </div>
+
+                <div className="space-y-3">
+                  <div className="flex items-center justify-between border-b border-slate-700 pb-1">
+                    <h4 className="font-bold text-slate-200 text-sm">
+                      Partials
+                    </h4>
+                    <button
+                      onClick={() => {
+                        const name = window.prompt("Partial name (letters, digits, _ or -)")?.trim();
+                        if (!name || name in partials) return;
+                        setPartials({ ...partials, [name]: "" });
+                        handleSavePartial(name, "");
+                      }}
+                      className="ui-btn ui-btn-secondary ui-btn-sm"
+                    >
+                      <Plus size={14} /> New Partial
+                    </button>
+                  </div>
+                  <p className="text-xs text-slate-500">
+                    Shared snippets included from any template with{" "}
+                    <code className="bg-slate-800 text-primary px-1 rounded">
+                      {"{{> name}}"}
+                    </code>
+                    , e.g. a common file header.
+                  </p>
+                  {Object.entries(partials).map(([name, content]) => (
+                    <div key={name} className="flex items-start gap-2">
+                      <span className="w-32 pt-1 font-mono text-xs text-slate-300 truncate">
+                        {name}
+                      </span>
+                      <textarea
+                        value={content}
+                        onChange={(e) =>
+                          setPartials({ ...partials, [name]: e.target.value })
+                        }
+                        onBlur={(e) => handleSavePartial(name, e.target.value)}
+                        className="ui-textarea h-16 flex-1 bg-slate-950 border-slate-800 p-2 text-xs font-mono text-slate-300"
+                        spellCheck="false"
+                      />
+                      <button
+                        onClick={async () => {
+                          const { BackendAPI } =
+                            await import("../../api/backend");
+                          await BackendAPI.deleteTemplatePartial(name).catch(
+                            console.error,
+                          );
+                          const { [name]: _removed, ...rest } = partials;
+                          setPartials(rest);
+                        }}
+                        className="p-1.5 text-slate-500 hover:text-red-400 hover:bg-red-400/10 rounded transition-colors"
+                      >
+                        <Trash2 size={16} />
+                      </button>
+                    </div>
+                  ))}
+                </div>
+
+                <div className="space-y-3">
+                  <div className="flex items-center justify-between border-b border-slate-700 pb-1">
+                    <h4 className="font-bold text-slate-200 text-sm">
+                      Template Helpers
+                    </h4>
+                    <button
+                      onClick={() => {
+                        let n = Object.keys(templateHelpers).length + 1;
+                        while (`helper${n}` in templateHelpers) n++;
+                        setTemplateHelpers({
+                          ...templateHelpers,
+                          [`helper${n}`]: "",
+                        });
+                      }}
+                      className="ui-btn ui-btn-secondary ui-btn-sm"
+                    >
+                      <Plus size={14} /> New Helper
+                    </button>
+                  </div>
+                  <p className="text-xs text-slate-500">
+                    A helper is a small template called as{" "}
+                    <code className="bg-slate-800 text-primary px-1 rounded">
+                      {"{{name a b key=value}}"}
+                    </code>
+                    . Inside it, parameters are available as{" "}
+                    <code className="bg-slate-800 text-primary px-1 rounded">
+                      {"{{lookup args 0}}"}
+                    </code>{" "}
+                    and{" "}
+                    <code className="bg-slate-800 text-primary px-1 rounded">
+                      {"{{hash.key}}"}
+                    </code>
+                    .
+                  </p>
+                  {Object.entries(templateHelpers).map(([name, snippet]) => (
+                    <div key={name} className="flex items-center gap-2">
+                      <input
+                        type="text"
+                        defaultValue={name}
+                        onBlur={(e) => {
+                          const newName = e.target.value.trim();
+                          if (!newName || newName === name || newName in templateHelpers) {
+                            e.target.value = name;
+                            return;
+                          }
+                          // Rebuild to keep the helper's position in the list
+                          setTemplateHelpers(
+                            Object.fromEntries(
+                              Object.entries(templateHelpers).map(([k, v]) =>
+                                k === name ? [newName, v] : [k, v],
+                              ),
+                            ),
+                          );
+                        }}
+                        className="ui-input w-32 font-mono text-xs"
+                      />
+                      <input
+                        type="text"
+                        value={snippet}
+                        onChange={(e) =>
+                          setTemplateHelpers({
+                            ...templateHelpers,
+                            [name]: e.target.value,
+                          })
+                        }
+                        className="ui-input flex-1 font-mono text-xs"
+                        placeholder="[{{fixed (lookup args 0) 3}}, {{fixed (lookup args 1) 3}}]"
+                        spellCheck="false"
+                      />
+                      <button
+                        onClick={() => {
+                          const { [name]: _removed, ...rest } = templateHelpers;
+                          setTemplateHelpers(rest);
+                        }}
+                        className="p-1.5 text-slate-500 hover:text-red-400 hover:bg-red-400/10 rounded transition-colors"
+                      >
+                        <Trash2 size={16} />
+                      </button>
+                    </div>
+                  ))}
+                </div>
               </div>
             )}
 
@@ -1,7 +1,8 @@
 import { useState, useRef, useEffect } from "react";
 import { useAppStore } from "../../stores/appStore";
 import { open, save as tauriSave, ask } from "@tauri-apps/plugin-dialog";
-import { BackendAPI } from "../../api/backend";
+import { BackendAPI, RecoveryInfo } from "../../api/backend";
+import { ProjectData } from "../../types/store";
 import { invoke } from "@tauri-apps/api/core";
 import { saveWindowState, StateFlags } from "@tauri-apps/plugin-window-state";
 import { MousePointer2 } from "lucide-react";
@@ -82,9 +83,9 @@ function DropdownMenu({
   );
 }
 
+const AUTOSAVE_INTERVAL_MS = 60_000;
+
 export function TopMenu() {
-  const rootNodeIds = useAppStore((state) => state.rootNodeIds);
-  const nodes = useAppStore((state) => state.nodes);
   const lastDirectory = useAppStore((state) => state.lastDirectory);
   const setLastDirectory = useAppStore((state) => state.setLastDirectory);
   const selectedNodeIds = useAppStore((state) => state.selectedNodeIds);
@@ -108,11 +109,126 @@ export function TopMenu() {
     }
   };
 
+  // Path of the project currently open, used to key crash-recovery autosaves
+  const currentProjectPath = useRef<string | null>(null);
+
+  const buildProjectData = () => {
+    const mapLayersToSave = useAppStore.getState().mapLayers.map((layer) => ({
+      id: layer.id,
+      name: layer.name,
+      info: layer.info,
+      image_base64: layer.image_base64,
+      width: layer.width,
+      height: layer.height,
+      visible: layer.visible,
+      opacity: layer.opacity,
+      z_index: layer.z_index,
+    }));
+
+    return {
+      root_node_ids: useAppStore.getState().rootNodeIds,
+      nodes: useAppStore.getState().nodes,
+      map_layers: mapLayersToSave,
+      options_schema: useAppStore.getState().optionsSchema ?? undefined,
+      export_profiles: useAppStore.getState().exportProfiles,
+    };
+  };
+
+  const buildProjectDataRef = useRef(buildProjectData);
+  buildProjectDataRef.current = buildProjectData;
+
+  // Started once; restarting it on every store change would keep it from ever firing while editing
+  useEffect(() => {
+    const timer = setInterval(() => {
+      if (!useAppStore.getState().isDirty) return;
+      BackendAPI.autosaveProject(currentProjectPath.current, buildProjectDataRef.current()).catch(
+        (err) => console.warn("Autosave failed:", err),
+      );
+    }, AUTOSAVE_INTERVAL_MS);
+    return () => clearInterval(timer);
+  }, []);
+
   const getDirName = (path: string) => {
     const lastSlash = Math.max(path.lastIndexOf("/"), path.lastIndexOf("\\"));
     return lastSlash > -1 ? path.substring(0, lastSlash) : path;
   };
 
+  const setProjectPath = (path: string) => {
+    currentProjectPath.current = path;
+    const fileName = path.substring(Math.max(path.lastIndexOf("/"), path.lastIndexOf("\\")) + 1);
+    const lastDot = fileName.lastIndexOf(".");
+    useAppStore.getState().setProjectName(lastDot > 0 ? fileName.substring(0, lastDot) : fileName);
+  };
+
+  const askRestore = (recovery: RecoveryInfo, message: string) =>
+    ask(`${message}（${new Date(recovery.autosave_modified).toLocaleString()}）。復元しますか？`, {
+      title: "自動保存からの復元",
+      kind: "warning",
+    });
+
+  // Loads project data into the store; returns whether integrity repairs changed it
+  const applyProjectData = async (data: ProjectData) => {
+    let projectData = data;
+    let repaired = false;
+    const integrity = await BackendAPI.validateProject(projectData);
+    if (integrity.issues.length > 0) {
+      const repair = await ask(
+        `プロジェクトに ${integrity.issues.length} 件の整合性の問題が見つかりました。自動修復しますか？`,
+        { title: "プロジェクトの整合性", kind: "warning" },
+      );
+      if (repair) {
+        projectData = (await BackendAPI.repairProject(projectData)).data;
+        repaired = true;
+      }
+    }
+
+    useAppStore.setState({
+      nodes: projectData.nodes,
+      rootNodeIds: projectData.root_node_ids,
+      selectedNodeIds: [],
+      exportProfiles: projectData.export_profiles ?? [],
+    });
+    // Older projects carry no schema; keep the one currently configured
+    if (projectData.options_schema) {
+      useAppStore.setState({ optionsSchema: projectData.options_schema });
+    }
+
+    if (projectData.map_layers && Array.isArray(projectData.map_layers)) {
+      useAppStore.setState({ mapLayers: [] });
+      projectData.map_layers.forEach((layer: any) => {
+        useAppStore
+          .getState()
+          .addMapLayer(
+            layer.name || "Restored Map",
+            layer.info || {},
+            layer.image_base64 || "",
+            layer.width || 1000,
+            layer.height || 1000,
+          );
+      });
+    }
+
+    return repaired;
+  };
+
+  // Work on a project that was never saved lives only in the untitled autosave
+  const untitledRecoveryOffered = useRef(false);
+  useEffect(() => {
+    // StrictMode runs effects twice in development; ask only once
+    if (untitledRecoveryOffered.current) return;
+    untitledRecoveryOffered.current = true;
+    (async () => {
+      const recovery = await BackendAPI.findProjectRecovery(null);
+      if (!recovery) return;
+      if (await askRestore(recovery, "前回保存されなかった新規プロジェクトの自動保存データがあります")) {
+        await applyProjectData((await BackendAPI.loadProject(recovery.autosave_path)).data);
+        useAppStore.getState().setIsDirty(true);
+      } else {
+        await BackendAPI.discardProjectRecovery(null);
+      }
+    })().catch((err) => console.warn("Autosave recovery failed:", err));
+  }, []);
+
   const handleLoadProject = async () => {
     try {
       const selectedPath = await open({
@@ -129,30 +245,25 @@ export function TopMenu() {
         if (!pathStr) return;
 
         setLastDirectory(getDirName(pathStr));
-        const projectData = await BackendAPI.loadProject(pathStr);
-
-        useAppStore.setState({
-          nodes: projectData.nodes,
-          rootNodeIds: projectData.root_node_ids,
-          selectedNodeIds: [],
-        });
-
-        if (projectData.map_layers && Array.isArray(projectData.map_layers)) {
-          useAppStore.setState({ mapLayers: [] });
-          projectData.map_layers.forEach((layer: any) => {
-            useAppStore
-              .getState()
-              .addMapLayer(
-                layer.name || "Restored Map",
-                layer.info || {},
-                layer.image_base64 || "",
-                layer.width || 1000,
-                layer.height || 1000,
-              );
-          });
+
+        const loaded = await BackendAPI.loadProject(pathStr);
+        let projectData = loaded.data;
+        let restored = false;
+        if (loaded.recovery) {
+          restored = await askRestore(
+            loaded.recovery,
+            "このプロジェクトには保存されていない自動保存データがあります",
+          );
+          if (restored) {
+            projectData = (await BackendAPI.loadProject(loaded.recovery.autosave_path)).data;
+          } else {
+            await BackendAPI.discardProjectRecovery(pathStr);
+          }
         }
+        setProjectPath(pathStr);
 
-        useAppStore.getState().setIsDirty(false);
+        const repaired = await applyProjectData(projectData);
+        useAppStore.getState().setIsDirty(restored || repaired);
       }
     } catch (err) {
       console.error("Failed to load project:", err);
@@ -177,25 +288,8 @@ export function TopMenu() {
 
         setLastDirectory(getDirName(finalPath));
 
-        const currentMapLayers = useAppStore.getState().mapLayers;
-        const mapLayersToSave = currentMapLayers.map((layer) => ({
-          id: layer.id,
-          name: layer.name,
-          info: layer.info,
-          image_base64: layer.image_base64,
-          width: layer.width,
-          height: layer.height,
-          visible: layer.visible,
-          opacity: layer.opacity,
-          z_index: layer.z_index,
-        }));
-
-        const projectData = {
-          root_node_ids: rootNodeIds,
-          nodes,
-          map_layers: mapLayersToSave,
-        };
-        await BackendAPI.saveProject(finalPath, projectData);
+        await BackendAPI.saveProject(finalPath, buildProjectData(), undefined, currentProjectPath.current === null);
+        setProjectPath(finalPath);
         useAppStore.getState().setIsDirty(false);
         alert("プロジェクトを保存しました。");
       }
@@ -2,6 +2,26 @@ import { create } from 'zustand';
 import { persist } from 'zustand/middleware';
 import { WaypointNode, ProjectMapLayer, OptionsSchema, PluginInstance, ExportTemplate } from '../types/store';
 import { v4 as uuidv4 } from 'uuid';
+import { BackendAPI, ExportProfile } from '../api/backend';
+
+// Template edits arrive per keystroke; write each template to the backend library once typing pauses
+const templateSaveTimers: Record<string, ReturnType<typeof setTimeout>> = {};
+function scheduleTemplateSave(id: string) {
+  clearTimeout(templateSaveTimers[id]);
+  templateSaveTimers[id] = setTimeout(() => {
+    delete templateSaveTimers[id];
+    const template = useAppStore.getState().exportTemplates.find((t) => t.id === id);
+    if (template) BackendAPI.saveExportTemplate(template).catch(console.error);
+  }, 500);
+}
+
+// Built-in Nav2 exporters rendered by the backend
+const NAV2_EXPORT_FORMATS: import('../types/store').DefaultExportFormat[] = [
+  { id: '__nav2_waypoints__', name: 'Nav2 Waypoints (RViz)', extension: 'yaml', suffix: '_nav2_waypoints', enabled: true, builtin: 'nav2_waypoints' },
+  { id: '__nav2_follow_waypoints__', name: 'Nav2 FollowWaypoints Goal', extension: 'yaml', suffix: '_follow_waypoints', enabled: true, builtin: 'nav2_follow_waypoints' },
+  { id: '__nav2_navigate_through_poses__', name: 'Nav2 NavigateThroughPoses Goal', extension: 'yaml', suffix: '_through_poses', enabled: true, builtin: 'nav2_navigate_through_poses' },
+  { id: '__nav2_params__', name: 'Nav2 Params (ros__parameters)', extension: 'yaml', suffix: '_params', enabled: true, builtin: 'nav2_params' },
+];
 
 export type AppState = {
   // App State
@@ -23,9 +43,12 @@ export type AppState = {
   // Maps & Layers
   mapLayers: ProjectMapLayer[];
   lastDirectory: string | null;
+  projectName: string | null; // file name of the open project without extension; `project.name` in exports
 
   optionsSchema: OptionsSchema | null;
   exportTemplates: ExportTemplate[];
+  templateHelpers: Record<string, string>; // user Handlebars helpers: name -> snippet
+  exportProfiles: ExportProfile[]; // saved with the project, not in settings
   defaultExportFormats: import('../types/store').DefaultExportFormat[];
   globalPythonPath: string;
   
@@ -54,6 +77,7 @@ export type AppState = {
   removeMapLayer: (id: string) => void;
   reorderMapLayers: (fromIndex: number, toIndex: number) => void;
   setLastDirectory: (dir: string | null) => void;
+  setProjectName: (name: string | null) => void;
   setGlobalPythonPath: (path: string) => void;
   setOptionsSchema: (schema: OptionsSchema) => void;
   toggleAttributeVisibility: (attr: string) => void;
@@ -67,6 +91,9 @@ export type AppState = {
   addExportTemplate: (template: ExportTemplate) => void;
   updateExportTemplate: (id: string, updates: Partial<ExportTemplate>) => void;
   removeExportTemplate: (id: string) => void;
+  setExportTemplates: (templates: ExportTemplate[]) => void; // replace with the backend library's contents
+  setTemplateHelpers: (helpers: Record<string, string>) => void;
+  setExportProfiles: (profiles: ExportProfile[]) => void;
   updateDefaultExportFormat: (id: string, updates: Partial<import('../types/store').DefaultExportFormat>) => void;
   
   // Plugin Methods
@@ -101,12 +128,17 @@ export const useAppStore = create<AppState>()(
       // Maps & Layers
       mapLayers: [],
       lastDirectory: null,
+      projectName: null,
 
       optionsSchema: null,
       exportTemplates: [],
+      templateHelpers: {},
+      exportProfiles: [],
       defaultExportFormats: [
         { id: '__default_yaml__', name: 'YAML Document', extension: 'yaml', suffix: '_yaml', enabled: true },
         { id: '__default_json__', name: 'JSON Document', extension: 'json', suffix: '_json', enabled: true },
+        ...NAV2_EXPORT_FORMATS,
+        { id: '__csv__', name: 'CSV', extension: 'csv', suffix: '_csv', enabled: true, builtin: 'csv' },
       ],
       globalPythonPath: 'python',
       visibleAttributes: [],
@@ -168,24 +200,39 @@ export const useAppStore = create<AppState>()(
       }),
 
       setLastDirectory: (dir: string | null) => set({ lastDirectory: dir }),
+      setProjectName: (name: string | null) => set({ projectName: name }),
       setGlobalPythonPath: (path: string) => set({ globalPythonPath: path, isDirty: true }),
 
       setOptionsSchema: (schema: OptionsSchema) => set({ optionsSchema: schema, isDirty: true }),
       
-      addExportTemplate: (template: ExportTemplate) => set((state) => ({
-        exportTemplates: [...state.exportTemplates, template],
-        isDirty: true
-      })),
+      addExportTemplate: (template: ExportTemplate) => {
+        set((state) => ({
+          exportTemplates: [...state.exportTemplates, template],
+          isDirty: true
+        }));
+        BackendAPI.saveExportTemplate(template).catch(console.error);
+      },
       
-      updateExportTemplate: (id: string, updates: Partial<ExportTemplate>) => set((state) => ({
-        exportTemplates: state.exportTemplates.map(t => t.id === id ? { ...t, ...updates } : t),
-        isDirty: true
-      })),
+      updateExportTemplate: (id: string, updates: Partial<ExportTemplate>) => {
+        set((state) => ({
+          exportTemplates: state.exportTemplates.map(t => t.id === id ? { ...t, ...updates } : t),
+          isDirty: true
+        }));
+        scheduleTemplateSave(id);
+      },
       
-      removeExportTemplate: (id: string) => set((state) => ({
-        exportTemplates: state.exportTemplates.filter(t => t.id !== id),
-        isDirty: true
-      })),
+      removeExportTemplate: (id: string) => {
+        set((state) => ({
+          exportTemplates: state.exportTemplates.filter(t => t.id !== id),
+          isDirty: true
+        }));
+        BackendAPI.deleteExportTemplate(id).catch(console.error);
+      },
+
+      setExportTemplates: (templates: ExportTemplate[]) => set({ exportTemplates: templates }),
+
+      setTemplateHelpers: (helpers: Record<string, string>) => set({ templateHelpers: helpers, isDirty: true }),
+      setExportProfiles: (profiles: ExportProfile[]) => set({ exportProfiles: profiles, isDirty: true }),
 
       updateDefaultExportFormat: (id: string, updates: Partial<import('../types/store').DefaultExportFormat>) => set((state) => ({
         defaultExportFormats: state.defaultExportFormats.map((f: import('../types/store').DefaultExportFormat) => f.id === id ? { ...f, ...updates } : f),
@@ -215,6 +262,8 @@ export const useAppStore = create<AppState>()(
           selectedNodeIds: [],
           mapLayers: data.map_layers || data.mapLayers || state.mapLayers, // Keep existing if not in project
           exportTemplates: data.export_templates || state.exportTemplates,
+          templateHelpers: data.template_helpers || state.templateHelpers,
+          exportProfiles: data.export_profiles || [],
           defaultExportFormats: data.default_export_formats || state.defaultExportFormats,
           indexStartIndex: data.index_start_index ?? state.indexStartIndex,
           decimalPrecision: data.decimal_precision ?? state.decimalPrecision,
@@ -319,6 +368,7 @@ export const useAppStore = create<AppState>()(
         lastDirectory: state.lastDirectory,
         optionsSchema: state.optionsSchema,
         exportTemplates: state.exportTemplates,
+        templateHelpers: state.templateHelpers,
         defaultExportFormats: state.defaultExportFormats,
         indexStartIndex: state.indexStartIndex,
         showPaths: state.showPaths,
@@ -328,6 +378,19 @@ export const useAppStore = create<AppState>()(
         globalPythonPath: state.globalPythonPath,
         decimalPrecision: state.decimalPrecision,
       }),
+      // Formats added in newer versions must appear even when older ones were persisted
+      merge: (persisted, current) => {
+        const saved = persisted as Partial<AppState>;
+        const formats = saved.defaultExportFormats ?? [];
+        return {
+          ...current,
+          ...saved,
+          defaultExportFormats: [
+            ...formats,
+            ...current.defaultExportFormats.filter((f) => !formats.some((s) => s.id === f.id)),
+          ],
+        };
+      },
     }
   )
 );
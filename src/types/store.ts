@@ -15,6 +15,14 @@ export type OptionDef = {
   item_type?: string;
   default?: any;
   enum_values?: string[];
+  min?: number;
+  max?: number;
+  step?: number;
+  unit?: string;
+  required?: boolean;
+  description?: string;
+  pattern?: string; // regex the whole string value must match
+  visible_when?: { option: string; equals: any }; // equals may be a list of accepted values
 };
 
 export type OptionsSchema = {
@@ -27,6 +35,7 @@ export type ExportTemplate = {
   extension: string;
   suffix: string;
   content: string;
+  escape_html?: boolean; // HTML-escape {{value}} output (default); off for YAML/CSV templates
 };
 
 export interface PluginSetting {
@@ -80,6 +89,8 @@ export type DefaultExportFormat = {
   extension: string;
   suffix: string;
   enabled: boolean;
+  builtin?: string; // backend ExportFormat tag, e.g. 'nav2_follow_waypoints'
+  settings?: Record<string, any>; // extra ExportFormat fields, e.g. CSV columns
 };
 
 export type PluginInstance = {
@@ -111,6 +122,7 @@ export interface ProjectMapLayer {
   name: string;
   info: any;
   image_base64: string;
+  image_file?: string;
   width: number;
   height: number;
   visible: boolean;
@@ -119,9 +131,12 @@ export interface ProjectMapLayer {
 }
 
 export interface ProjectData {
+  format_version?: number;
   root_node_ids: string[];
   nodes: Record<string, ObjectNode>;
   map_layers?: ProjectMapLayer[];
+  options_schema?: OptionsSchema;
+  export_profiles?: import('../api/backend').ExportProfile[];
 }
 export interface AppState {
   nodes: Record<string, ObjectNode>;